@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use wgpu_rendering::{
+    Color, HeadlessTarget, Line, RenderController, RenderStage, Renderable, Vector2,
+};
+
+const LINES: usize = 5_000_000;
+const SIZE: u32 = 256;
+
+/// Short lines in every color, all on screen.
+struct Lines(Vec<Line>);
+
+impl Lines {
+    fn new() -> Self {
+        let lines = (0..LINES)
+            .map(|i| {
+                let t = i as f32 / LINES as f32;
+                let from = Vector2::new(t * 2.0 - 1.0, (t * 977.0).fract() * 2.0 - 1.0);
+                let color = Color::hsv(t * 360.0, 0.8, 1.0).with_alpha(0.5);
+                Line::new(from, from + Vector2::same(0.01), color)
+            })
+            .collect();
+        Self(lines)
+    }
+}
+
+impl Renderable for Lines {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::Line);
+        for line in &self.0 {
+            render_controller.add_line(*line);
+        }
+    }
+}
+
+/// Whole frames of 5M lines, submitting, packing when compact, uploading and drawing them.
+fn compact(c: &mut Criterion) {
+    let mut lines = Lines::new();
+    let Ok(mut full) = HeadlessTarget::for_application(&mut lines, SIZE, SIZE) else {
+        eprintln!("no adapter available, skipping compact color benchmarks");
+        return;
+    };
+    let mut compact = HeadlessTarget::for_application_compact(&mut lines, SIZE, SIZE).unwrap();
+
+    let mut group = c.benchmark_group("5M lines");
+    group.sample_size(10);
+    group.bench_function("full colors", |b| {
+        b.iter(|| full.render(&mut lines).unwrap())
+    });
+    group.bench_function("compact colors", |b| {
+        b.iter(|| compact.render(&mut lines).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compact);
+criterion_main!(benches);
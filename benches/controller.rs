@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use wgpu_rendering::testgen::{self, random_lines};
+use wgpu_rendering::{Rect, RenderController, Vector2};
+
+const LINES: usize = 1_000_000;
+
+/// Submitting 1M lines to a fresh controller one at a time and all at once. Needs no GPU.
+fn controller(c: &mut Criterion) {
+    let region = Rect::from_center_size(Vector2::ZERO, Vector2::same(2.0));
+    let lines = random_lines(&mut testgen::seeded(0), LINES, region, &[]);
+
+    let mut group = c.benchmark_group("fill 1M lines");
+    group.bench_function("add_line", |b| {
+        b.iter_batched(
+            RenderController::new,
+            |mut render_controller| {
+                for &line in &lines {
+                    render_controller.add_line(line);
+                }
+                render_controller
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("add_lines", |b| {
+        b.iter_batched(
+            RenderController::new,
+            |mut render_controller| {
+                render_controller.add_lines(lines.iter().copied());
+                render_controller
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, controller);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use wgpu_rendering::testgen::{self, random_circles, random_lines};
+use wgpu_rendering::{
+    Camera, HeadlessTarget, Line, Rect, RectOrCircle, RenderController, RenderStage, Renderable,
+    Vector2,
+};
+
+const SHAPES: usize = 1_000_000;
+const SIZE: u32 = 512;
+
+/// Random lines or rects and circles over the whole view, the same ones every run.
+enum Shapes {
+    Lines(Vec<Line>),
+    RectsAndCircles(Vec<RectOrCircle>),
+}
+
+impl Renderable for Shapes {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        match self {
+            Self::Lines(lines) => {
+                render_controller.add_stage(RenderStage::Line);
+                render_controller.add_lines(lines.iter().copied());
+            }
+            Self::RectsAndCircles(shapes) => {
+                render_controller.add_stage(RenderStage::RectsAndCircles);
+                render_controller.add_rects_or_circles(shapes.iter().copied());
+            }
+        }
+    }
+}
+
+/// Encoding, submitting and drawing 1M shapes already uploaded, into an offscreen target.
+fn draw(c: &mut Criterion) {
+    let region = Rect::from_center_size(Vector2::ZERO, Vector2::same(2.0));
+    let mut rng = testgen::seeded(0);
+    let lines = Shapes::Lines(random_lines(&mut rng, SHAPES, region, &[]));
+    let circles = random_circles(&mut rng, SHAPES, region, 0.001..0.01, &[]);
+    let circles = Shapes::RectsAndCircles(circles);
+
+    let mut group = c.benchmark_group("draw 1M shapes");
+    group.sample_size(10);
+    for (name, mut shapes) in [("lines", lines), ("rects and circles", circles)] {
+        let Ok(mut target) = HeadlessTarget::for_application(&mut shapes, SIZE, SIZE) else {
+            eprintln!("no adapter available, skipping draw benchmarks");
+            return;
+        };
+        target.set_camera(Camera::default());
+        target.render(&mut shapes).unwrap();
+        group.bench_function(name, |b| b.iter(|| target.redraw()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use wgpu_rendering::{render_to_image, Camera, RenderController, RenderStage, Renderable};
+
+struct Empty;
+
+impl Renderable for Empty {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::RectsAndCircles);
+        render_controller.add_stage(RenderStage::Line);
+    }
+}
+
+fn startup(c: &mut Criterion) {
+    if render_to_image(&mut Empty, 1, 1, Camera::default()).is_err() {
+        eprintln!("no adapter available, skipping startup benchmarks");
+        return;
+    }
+
+    let mut group = c.benchmark_group("startup");
+    group.sample_size(10);
+    group.bench_function("headless device and pipelines", |b| {
+        b.iter(|| render_to_image(&mut Empty, 1, 1, Camera::default()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::iter;
+use wgpu::{
+    DeviceDescriptor, Features, Instance, InstanceDescriptor, Maintain, MemoryHints,
+    RequestAdapterOptions,
+};
+use wgpu_rendering::testgen::{self, random_circles};
+use wgpu_rendering::{DynamicStorageBuffer, Rect, RectOrCircle, UploadMethod, Vector2};
+
+const COUNTS: [usize; 3] = [100_000, 1_000_000, 5_000_000];
+
+fn upload(c: &mut Criterion) {
+    let instance = Instance::new(InstanceDescriptor::default());
+    let Some(adapter) =
+        pollster::block_on(instance.request_adapter(&RequestAdapterOptions::default()))
+    else {
+        eprintln!("no adapter available, skipping upload benchmarks");
+        return;
+    };
+    // the adapter's own limits, so 5M shapes fit in one buffer where it allows
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            required_features: Features::empty(),
+            required_limits: adapter.limits(),
+            memory_hints: MemoryHints::Performance,
+        },
+        None,
+    ))
+    .unwrap();
+
+    let region = Rect::from_center_size(Vector2::ZERO, Vector2::same(2.0));
+    let mut rng = testgen::seeded(0);
+    let all_shapes = random_circles(&mut rng, COUNTS[2], region, 0.001..0.01, &[]);
+
+    let mut group = c.benchmark_group("set_new_data rects and circles");
+    group.sample_size(10);
+    for count in COUNTS {
+        let shapes = &all_shapes[..count];
+        group.throughput(Throughput::Elements(count as u64));
+        for (name, method) in [
+            ("write_buffer", UploadMethod::WriteBuffer),
+            ("write_buffer_with", UploadMethod::WriteBufferWith),
+        ] {
+            let mut storage =
+                DynamicStorageBuffer::with_upload_method(&device, count as u64, method);
+            group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| {
+                b.iter(|| {
+                    storage.set_new_data(&device, &queue, shapes);
+                    queue.submit(iter::empty());
+                    device.poll(Maintain::Wait);
+                })
+            });
+        }
+
+        // a buffer too small for the shapes every time, so each upload reallocates
+        group.bench_with_input(BenchmarkId::new("realloc", count), &count, |b, _| {
+            b.iter_batched(
+                || DynamicStorageBuffer::<RectOrCircle>::with_capacity(&device, 1),
+                |mut storage| {
+                    storage.set_new_data(&device, &queue, shapes);
+                    queue.submit(iter::empty());
+                    device.poll(Maintain::Wait);
+                    storage
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, upload);
+criterion_main!(benches);
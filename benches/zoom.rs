@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use wgpu_rendering::{
+    Camera, Color, HeadlessTarget, RectOrCircle, RenderController, RenderStage, Renderable, Vector2,
+};
+
+const SIZE: u32 = 512;
+/// Shapes per side of the grid, spaced one world unit apart.
+const GRID: i32 = 300;
+
+/// A grid of rects and circles around the origin.
+struct Grid;
+
+impl Renderable for Grid {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::RectsAndCircles);
+        for x in -GRID / 2..GRID / 2 {
+            for y in -GRID / 2..GRID / 2 {
+                let center = Vector2::new(x as f32, y as f32);
+                let color = Color::categorical((x + y).unsigned_abs() as usize);
+                render_controller.add_rect_or_circle(match (x + y) % 2 == 0 {
+                    true => RectOrCircle::rectangle(center, Vector2::same(0.4), color),
+                    false => RectOrCircle::circle(center, 0.4, color),
+                });
+            }
+        }
+    }
+}
+
+/// Zoomed out, every shape is a few pixels; zoomed in, the view is inside a single rect and
+/// every other one is far off screen.
+fn zoom(c: &mut Criterion) {
+    let Ok(mut target) = HeadlessTarget::for_application(&mut Grid, SIZE, SIZE) else {
+        eprintln!("no adapter available, skipping zoom benchmarks");
+        return;
+    };
+
+    let mut group = c.benchmark_group("90k rects and circles");
+    group.sample_size(20);
+    for zoom in [0.005, 0.1, 10.0, 1e4] {
+        target.set_camera(Camera::new(Vector2::ZERO, zoom));
+        group.bench_with_input(BenchmarkId::new("zoom", zoom), &zoom, |b, _| {
+            b.iter(|| target.render(&mut Grid).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, zoom);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+use super::camera::Camera;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+#[cfg(feature = "serde")]
+use std::{fs, io, path::Path};
+
+/// Cameras saved under names or numbers, to jump back to a view later. Numbers are stored
+/// as their decimal names, so `1` and `"1"` are the same slot.
+///
+/// Keep one in the application, or use the one behind
+/// [`WindowAccess::save_bookmark`](crate::WindowAccess::save_bookmark) and
+/// [`WindowAccess::restore_bookmark`](crate::WindowAccess::restore_bookmark), which can
+/// also animate the way there. With the `serde` feature, bookmarks persist as JSON files.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CameraBookmarks {
+    slots: BTreeMap<String, Camera>,
+}
+
+impl CameraBookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `camera` in `slot`, replacing what was there.
+    pub fn save(&mut self, slot: impl ToString, camera: Camera) {
+        self.slots.insert(slot.to_string(), camera);
+    }
+
+    pub fn restore(&self, slot: impl ToString) -> Option<Camera> {
+        self.slots.get(&slot.to_string()).copied()
+    }
+
+    pub fn remove(&mut self, slot: impl ToString) -> Option<Camera> {
+        self.slots.remove(&slot.to_string())
+    }
+
+    /// Every saved slot with its camera, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Camera)> {
+        self.slots
+            .iter()
+            .map(|(slot, &camera)| (slot.as_str(), camera))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Writes the bookmarks to `path` as JSON.
+    #[cfg(feature = "serde")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Reads bookmarks written by [`Self::save_file`].
+    #[cfg(feature = "serde")]
+    pub fn load_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read(path)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+/// A camera moving from one view to another over time, driven by the renderer each frame.
+pub(crate) struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    start: Instant,
+    duration: Duration,
+}
+
+impl CameraTransition {
+    pub fn new(from: Camera, to: Camera, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+        }
+    }
+
+    /// The camera at `now`, and whether the transition is over.
+    pub fn sample(&self, now: Instant) -> (Camera, bool) {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed >= self.duration {
+            return (self.to, true);
+        }
+        let progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        // eased in and out, so the camera doesn't jerk into motion or stop abruptly
+        let eased = progress * progress * (3.0 - 2.0 * progress);
+        (self.from.interpolate(self.to, eased), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CameraBookmarks, CameraTransition};
+    use crate::{Camera, Vector2};
+    use std::time::Duration;
+
+    #[test]
+    fn numbers_and_names_share_slots() {
+        let mut bookmarks = CameraBookmarks::new();
+        let camera = Camera::new(Vector2::new(1.0, 2.0), 4.0);
+        bookmarks.save(1, camera);
+        bookmarks.save("overview", Camera::default());
+
+        assert_eq!(
+            bookmarks.restore("1").map(|c| c.target),
+            Some(camera.target)
+        );
+        assert!(bookmarks.restore(2).is_none());
+        assert_eq!(bookmarks.len(), 2);
+        assert!(bookmarks.remove(1).is_some());
+        assert!(bookmarks.restore(1).is_none());
+    }
+
+    #[test]
+    fn transitions_end_at_the_target() {
+        let from = Camera::new(Vector2::ZERO, 1.0);
+        let to = Camera::new(Vector2::new(10.0, 0.0), 100.0);
+        let transition = CameraTransition::new(from, to, Duration::from_secs(1));
+
+        let (halfway, done) = transition.sample(transition.start + Duration::from_millis(500));
+        assert!(!done);
+        assert!((halfway.target.x - 5.0).abs() < 1e-4);
+        // geometric: halfway between 1x and 100x is 10x
+        assert!((halfway.zoom - 10.0).abs() < 1e-3);
+
+        let (end, done) = transition.sample(transition.start + Duration::from_secs(2));
+        assert!(done);
+        assert_eq!(end.zoom, 100.0);
+    }
+}
@@ -1,19 +1,33 @@
+use super::rect::Rect;
 use super::util::cast_thing;
-use super::vectors::Vector2;
+use super::vectors::{DVector2, Vector2};
+use super::RenderController;
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, Device, Queue,
-    RenderPass, ShaderStages,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, ComputePass,
+    Device, Queue, RenderPass, ShaderStages,
 };
 use winit::dpi::PhysicalSize;
 
+/// How [`Camera::covering_for`] resolves a region whose aspect ratio differs from the window's.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum CoverMode {
+    /// The whole region is visible, with empty bands along one axis.
+    #[default]
+    Fit,
+    /// The region fills the window, cropping it along one axis.
+    Fill,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Camera {
     pub target: Vector2,
     pub zoom: f32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     _padding: u32,
 }
 
@@ -26,8 +40,27 @@ impl Default for Camera {
     }
 }
 
+/// The zoom after scrolling `lines` notches at `rate` per notch, kept within `range`. A zoom
+/// that isn't a number anymore stays at `zoom`.
+pub(crate) fn scroll_zoom(zoom: f32, rate: f32, lines: f32, range: (f32, f32)) -> f32 {
+    clamp_zoom(zoom * rate.powf(lines), range, zoom)
+}
+
+/// `zoom` within `range`, or `fallback` if it isn't a number.
+pub(crate) fn clamp_zoom(zoom: f32, (min, max): (f32, f32), fallback: f32) -> f32 {
+    match zoom.is_nan() {
+        true => fallback,
+        false => zoom.clamp(min, max),
+    }
+}
+
 impl Camera {
+    /// Debug builds panic unless `zoom` is finite and positive.
     pub const fn new(target: Vector2, zoom: f32) -> Self {
+        debug_assert!(
+            zoom.is_finite() && zoom > 0.0,
+            "zoom must be finite and positive"
+        );
         Self {
             target,
             zoom,
@@ -35,24 +68,96 @@ impl Camera {
         }
     }
 
+    /// The camera `t` of the way from `self` to `to`. The target moves linearly, but zoom
+    /// changes geometrically, so every step zooms by the same factor.
+    pub fn interpolate(self, to: Self, t: f32) -> Self {
+        let zoom = (self.zoom.ln() + (to.zoom.ln() - self.zoom.ln()) * t).exp();
+        Self::new(self.target.lerp(to.target, t), zoom)
+    }
+
+    /// Debug builds panic unless `zoom` is finite and positive.
     pub fn zoomed_in_by(mut self, zoom: f32) -> Self {
+        debug_assert!(
+            zoom.is_finite() && zoom > 0.0,
+            "zoom must be finite and positive"
+        );
         self.zoom *= zoom;
         self
     }
 
+    /// Degenerate regions (zero width and height) fall back to the default zoom.
     pub fn covering(top_left: Vector2, bottom_right: Vector2) -> Self {
         let target = (top_left + bottom_right) / 2.0;
         let area = bottom_right - top_left;
-        let max_dim = area.x.max(area.y);
-        Self::new(target, max_dim.recip() * 2.0)
+        let zoom = area.x.max(area.y).recip() * 2.0;
+        match zoom.is_finite() && zoom > 0.0 {
+            true => Self::new(target, zoom),
+            false => Self::new(target, Self::default().zoom),
+        }
+    }
+
+    /// Like [`Camera::covering`], for the corners of `rect`.
+    pub fn covering_rect(rect: Rect) -> Self {
+        Self::covering(rect.min, rect.max)
+    }
+
+    /// Like [`Camera::covering`], but accounts for the window's aspect ratio.
+    ///
+    /// Degenerate regions (zero width and height) or windows fall back to the default zoom.
+    pub fn covering_for(
+        top_left: Vector2,
+        bottom_right: Vector2,
+        inner_size: PhysicalSize<u32>,
+        mode: CoverMode,
+    ) -> Self {
+        let target = (top_left + bottom_right) / 2.0;
+        let half_size = (bottom_right - top_left) / 2.0;
+        let aspect = CameraTransforms::get_aspect_transform(inner_size);
+
+        let zoom_x = (half_size.x.abs() * aspect.x).recip();
+        let zoom_y = (half_size.y.abs() * aspect.y).recip();
+        let candidates = [zoom_x, zoom_y]
+            .into_iter()
+            .filter(|zoom| zoom.is_finite() && *zoom > 0.0);
+
+        let zoom = match mode {
+            CoverMode::Fit => candidates.reduce(f32::min),
+            CoverMode::Fill => candidates.reduce(f32::max),
+        };
+
+        Self::new(target, zoom.unwrap_or(Self::default().zoom))
+    }
+
+    /// Frames everything submitted to `render` this frame, or `None` if it is empty.
+    pub fn covering_shapes(
+        render: &RenderController,
+        inner_size: PhysicalSize<u32>,
+    ) -> Option<Self> {
+        let bounds = render.bounding_box()?;
+        Some(Self::covering_for(
+            bounds.min,
+            bounds.max,
+            inner_size,
+            CoverMode::Fit,
+        ))
     }
 }
 
 pub struct CameraTransforms {
+    /// What `camera.target` is relative to. The shapes are moved by the inverse on the CPU,
+    /// so the GPU only ever sees positions near the camera.
+    pub origin: DVector2,
     pub camera: Camera,
+    /// Whether [`Self::set_camera`] moves the origin along, see
+    /// [`WindowConfig::double_precision`](crate::WindowConfig::double_precision).
+    pub double_precision: bool,
+    written_camera: Camera,
+    uniform_writes: u64,
     aspect_ratio: Vector2,
     camera_uniform: Buffer,
     aspect_transform_uniform: Buffer,
+    /// The target's size in pixels, for [`Line::screen`](crate::Line::screen).
+    viewport_size_uniform: Buffer,
     bind_group: BindGroup,
 }
 
@@ -61,36 +166,128 @@ impl CameraTransforms {
         self.normalized_to_world(Self::screen_to_normalize(screen_pos, inner_size))
     }
 
+    /// A zero-sized window is treated as a single pixel, so the result stays finite.
     pub fn screen_to_normalize(screen_pos: Vector2, inner_size: PhysicalSize<u32>) -> Vector2 {
-        (screen_pos / Vector2::from(<[u32; 2]>::from(inner_size).map(|x| x as f32)))
+        (screen_pos / Vector2::from(<[u32; 2]>::from(inner_size).map(|x| x.max(1) as f32)))
             * Vector2::new(2.0, -2.0)
             - Vector2::new(1.0, -1.0)
     }
 
     pub fn normalized_to_world(&self, normalized_pos: Vector2) -> Vector2 {
-        normalized_pos / self.aspect_ratio / self.camera.zoom + self.camera.target
+        normalized_pos / self.aspect_ratio / self.camera.zoom + self.target()
+    }
+
+    pub fn normalized_to_world_64(&self, normalized_pos: Vector2) -> DVector2 {
+        let relative = normalized_pos / self.aspect_ratio / self.camera.zoom + self.camera.target;
+        self.origin + relative.into()
+    }
+
+    /// The camera target in world space, rounded to single precision.
+    pub fn target(&self) -> Vector2 {
+        self.camera.target + self.origin.as_vector2()
+    }
+
+    /// The camera with its target in world space, rounded to single precision.
+    pub fn world_camera(&self) -> Camera {
+        Camera::new(self.target(), self.camera.zoom)
+    }
+
+    pub fn target_64(&self) -> DVector2 {
+        self.origin + self.camera.target.into()
+    }
+
+    /// Moves the origin to `target` and the camera onto it.
+    pub fn set_target_64(&mut self, target: DVector2) {
+        self.origin = target;
+        self.camera.target = Vector2::ZERO;
+    }
+
+    /// Moves the camera to `camera`. With double precision, the origin moves to its target;
+    /// otherwise the origin stays put, as shapes written on the GPU are never moved by it.
+    pub fn set_camera(&mut self, camera: Camera) {
+        if self.double_precision {
+            self.set_target_64(camera.target.into());
+            self.camera.zoom = camera.zoom;
+        } else {
+            self.camera = camera;
+        }
+    }
+
+    /// Moves the origin to the camera target, so the camera stays precise however far it
+    /// pans.
+    pub fn rebase(&mut self) {
+        self.set_target_64(self.target_64());
+    }
+
+    /// The column-major homogeneous world → NDC transform, for world positions in single
+    /// precision.
+    pub fn view_matrix(&self) -> [[f32; 3]; 3] {
+        let scale = self.aspect_ratio * self.camera.zoom;
+        let offset = self.target() * scale;
+        [
+            [scale.x, 0.0, 0.0],
+            [0.0, scale.y, 0.0],
+            [-offset.x, -offset.y, 1.0],
+        ]
+    }
+
+    /// The column-major homogeneous NDC → world transform, the inverse of [`Self::view_matrix`].
+    pub fn inverse_view_matrix(&self) -> [[f32; 3]; 3] {
+        let scale = self.aspect_ratio * self.camera.zoom;
+        let target = self.target();
+        [
+            [scale.x.recip(), 0.0, 0.0],
+            [0.0, scale.y.recip(), 0.0],
+            [target.x, target.y, 1.0],
+        ]
     }
 }
 
 impl CameraTransforms {
-    fn get_aspect_transform(size: PhysicalSize<u32>) -> Vector2 {
+    pub(crate) fn get_aspect_transform(size: PhysicalSize<u32>) -> Vector2 {
+        if size.width == 0 || size.height == 0 {
+            return Vector2::same(1.0);
+        }
+
         let (width, height) = (size.width as f32, size.height as f32);
         let min_dim = f32::min(width, height);
         Vector2::new(min_dim / width, min_dim / height)
     }
 
+    /// Uploads the camera if it changed since the last upload.
     pub fn update_camera(&mut self, queue: &Queue) {
+        if cast_thing(&self.camera) == cast_thing(&self.written_camera) {
+            return;
+        }
+
         queue.write_buffer(&self.camera_uniform, 0, cast_thing(&self.camera));
+        self.written_camera = self.camera;
+        self.uniform_writes += 1;
+    }
+
+    /// Number of uniform buffer writes actually issued since creation.
+    pub fn uniform_writes(&self) -> u64 {
+        self.uniform_writes
     }
 
     pub fn update_aspect_ratio(&mut self, queue: &Queue, size: PhysicalSize<u32>) {
         self.aspect_ratio = Self::get_aspect_transform(size);
+        self.uniform_writes += 1;
 
         queue.write_buffer(
             &self.aspect_transform_uniform,
             0,
             cast_thing(&self.aspect_ratio),
         );
+        queue.write_buffer(
+            &self.viewport_size_uniform,
+            0,
+            cast_thing(&Self::viewport_size(size)),
+        );
+    }
+
+    fn viewport_size(size: PhysicalSize<u32>) -> Vector2 {
+        Vector2::new(size.width.max(1) as f32, size.height.max(1) as f32)
     }
 
     pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
@@ -99,7 +296,7 @@ impl CameraTransforms {
             entries: &[
                 BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStages::VERTEX,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -109,7 +306,17 @@ impl CameraTransforms {
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
-                    visibility: ShaderStages::VERTEX,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX | ShaderStages::COMPUTE,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -125,6 +332,10 @@ impl CameraTransforms {
         render_pass.set_bind_group(index, &self.bind_group, &[]);
     }
 
+    pub fn compute_bind_group_to(&self, compute_pass: &mut ComputePass, index: u32) {
+        compute_pass.set_bind_group(index, &self.bind_group, &[]);
+    }
+
     pub fn new(device: &Device, inner_size: PhysicalSize<u32>) -> Self {
         let camera = Camera::default();
 
@@ -142,6 +353,12 @@ impl CameraTransforms {
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
+        let viewport_size_uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("viewport size"),
+            contents: cast_thing(&Self::viewport_size(inner_size)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
         let bind_group_layout = Self::create_bind_group_layout(device);
 
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
@@ -156,15 +373,85 @@ impl CameraTransforms {
                     binding: 1,
                     resource: aspect_transform_uniform.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: viewport_size_uniform.as_entire_binding(),
+                },
             ],
         });
 
         Self {
+            origin: DVector2::ZERO,
             camera,
+            double_precision: false,
+            written_camera: camera,
+            uniform_writes: 0,
             camera_uniform,
             aspect_transform_uniform,
+            viewport_size_uniform,
             bind_group,
             aspect_ratio,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{scroll_zoom, Camera, CameraTransforms};
+    use crate::{setup, DVector2, Vector2};
+    use winit::dpi::PhysicalSize;
+
+    const RANGE: (f32, f32) = (1e-6, 1e6);
+
+    #[test]
+    fn huge_scrolls_keep_zoom_finite() {
+        assert_eq!(scroll_zoom(1.0, 1.1, 1e9, RANGE), 1e6);
+        assert_eq!(scroll_zoom(1.0, 1.1, -1e9, RANGE), 1e-6);
+        assert_eq!(scroll_zoom(2.0, 1.1, f32::NAN, RANGE), 2.0);
+
+        // one burst after another stays put at the limit
+        let mut zoom = 1.0;
+        for _ in 0..100 {
+            zoom = scroll_zoom(zoom, 1.1, 5000.0, RANGE);
+        }
+        assert_eq!(zoom, 1e6);
+        assert!((scroll_zoom(zoom, 1.1, -1.0, RANGE) - 1e6 / 1.1).abs() < 1.0);
+    }
+
+    #[test]
+    fn zero_sized_windows_map_to_finite_positions() {
+        let normalized =
+            CameraTransforms::screen_to_normalize(Vector2::new(3.0, 4.0), PhysicalSize::new(0, 0));
+        assert!(normalized.x.is_finite() && normalized.y.is_finite());
+    }
+
+    #[test]
+    fn view_matrix_agrees_with_screen_to_world() {
+        let Some((device, _)) = setup::test_device() else {
+            return;
+        };
+
+        let size = PhysicalSize::new(800, 600);
+        let mut transforms = CameraTransforms::new(&device, size);
+        transforms.origin = DVector2::new(1000.0, -20.0);
+        transforms.camera = Camera::new(Vector2::new(3.0, -2.0), 2.5);
+        let [x_axis, y_axis, translation] = transforms.view_matrix();
+
+        for offset in [(0.0, 0.0), (0.3, -0.1), (-0.5, 0.35), (1.0, 1.0)] {
+            let world = transforms.target() + Vector2::new(offset.0, offset.1);
+            let normalized = Vector2::new(
+                x_axis[0] * world.x + y_axis[0] * world.y + translation[0],
+                x_axis[1] * world.x + y_axis[1] * world.y + translation[1],
+            );
+            let screen = Vector2::new(
+                (normalized.x + 1.0) / 2.0 * size.width as f32,
+                (1.0 - normalized.y) / 2.0 * size.height as f32,
+            );
+            let round_trip = transforms.screen_to_world(screen, size);
+            assert!(
+                (round_trip - world).length() < 1e-3,
+                "{world:?} -> {round_trip:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device,
+    ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain, MapMode, Origin3d, Texture,
+    TextureAspect, TextureFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+/// Number of staging buffers frames are copied into while earlier ones are still being mapped.
+const STAGING_BUFFERS: usize = 3;
+
+/// One recorded frame as tightly packed RGBA8 rows, top to bottom.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// Position of the frame within the recording, starting at 0.
+    pub index: u64,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub(crate) type FrameSink = Box<dyn FnMut(CapturedFrame)>;
+
+/// Writes frames as `frame_000000.png`, ... into `dir` on a background thread.
+pub(crate) fn png_sink(dir: PathBuf) -> FrameSink {
+    let (sender, receiver) = mpsc::channel::<CapturedFrame>();
+    thread::spawn(move || {
+        if let Err(err) = fs::create_dir_all(&dir) {
+            log::error!("can't create recording directory {dir:?}: {err}");
+            return;
+        }
+        for frame in receiver {
+            let path = dir.join(format!("frame_{:06}.png", frame.index));
+            if let Err(err) = write_png(&path, &frame) {
+                log::error!("failed to write {path:?}: {err}");
+            }
+        }
+    });
+
+    Box::new(move |frame| {
+        let _ = sender.send(frame);
+    })
+}
+
+fn write_png(path: &Path, frame: &CapturedFrame) -> Result<(), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    encoder.write_header()?.write_image_data(&frame.pixels)?;
+    Ok(())
+}
+
+struct Staging {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+type MapResult = Arc<Mutex<Option<Result<(), BufferAsyncError>>>>;
+
+struct InFlight {
+    staging: Staging,
+    index: u64,
+    mapped: MapResult,
+}
+
+/// Copies presented frames into a small ring of staging buffers and hands them to a sink once
+/// the GPU is done with them, so recording never waits on a synchronous map.
+pub(crate) struct FrameRecorder {
+    sink: FrameSink,
+    free: Vec<Staging>,
+    copied: Option<InFlight>,
+    in_flight: VecDeque<InFlight>,
+    next_index: u64,
+    dropped: u64,
+}
+
+impl FrameRecorder {
+    pub fn new(sink: FrameSink) -> Self {
+        Self {
+            sink,
+            free: Vec::new(),
+            copied: None,
+            in_flight: VecDeque::new(),
+            next_index: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn captured_frames(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Frames that couldn't be captured because every staging buffer was still in use.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn supports_format(format: TextureFormat) -> bool {
+        matches!(
+            format,
+            TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Bgra8Unorm
+                | TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Encodes a copy of `texture`, which must have `COPY_SRC` usage.
+    pub fn capture(&mut self, device: &Device, encoder: &mut CommandEncoder, texture: &Texture) {
+        let (width, height) = (texture.width(), texture.height());
+        let allocated = self.free.len() + self.in_flight.len() + self.copied.is_some() as usize;
+
+        let staging = match self
+            .free
+            .iter()
+            .position(|staging| (staging.width, staging.height) == (width, height))
+        {
+            Some(position) => self.free.swap_remove(position),
+            None if allocated < STAGING_BUFFERS || !self.free.is_empty() => {
+                // buffers of a stale size are replaced rather than kept around
+                self.free.pop();
+                Self::create_staging(device, width, height)
+            }
+            None => {
+                self.dropped += 1;
+                return;
+            }
+        };
+
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &staging.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(staging.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+
+        self.copied = Some(InFlight {
+            staging,
+            index: self.next_index,
+            mapped: MapResult::default(),
+        });
+        self.next_index += 1;
+    }
+
+    /// Starts mapping the frame copied this frame. Must be called after the copy is submitted.
+    pub fn after_submit(&mut self) {
+        if let Some(frame) = self.copied.take() {
+            let mapped = frame.mapped.clone();
+            frame
+                .staging
+                .buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    *mapped.lock().unwrap() = Some(result);
+                });
+            self.in_flight.push_back(frame);
+        }
+    }
+
+    /// Delivers every frame whose mapping has finished, in order, optionally waiting for all.
+    pub fn collect(&mut self, device: &Device, format: TextureFormat, wait: bool) {
+        device.poll(match wait {
+            true => Maintain::Wait,
+            false => Maintain::Poll,
+        });
+
+        while let Some(result) = self
+            .in_flight
+            .front()
+            .and_then(|frame| frame.mapped.lock().unwrap().take())
+        {
+            let frame = self.in_flight.pop_front().unwrap();
+            match result {
+                Ok(()) => {
+                    let pixels = Self::read_pixels(&frame.staging, format);
+                    frame.staging.buffer.unmap();
+                    (self.sink)(CapturedFrame {
+                        index: frame.index,
+                        width: frame.staging.width,
+                        height: frame.staging.height,
+                        pixels,
+                    });
+                }
+                Err(err) => {
+                    log::warn!("failed to map recorded frame {}: {err}", frame.index);
+                    self.dropped += 1;
+                }
+            }
+            self.free.push(frame.staging);
+        }
+    }
+
+    /// Waits for every outstanding frame and delivers it.
+    pub fn finish(mut self, device: &Device, format: TextureFormat) {
+        self.after_submit();
+        self.collect(device, format, true);
+        log::info!(
+            "recording stopped: {} frames captured, {} dropped",
+            self.next_index,
+            self.dropped
+        );
+    }
+
+    fn create_staging(device: &Device, width: u32, height: u32) -> Staging {
+        let padded_bytes_per_row = (width * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("frame capture staging buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Staging {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+        }
+    }
+
+    fn read_pixels(staging: &Staging, format: TextureFormat) -> Vec<u8> {
+        let row_bytes = staging.width as usize * 4;
+        let mut pixels: Vec<u8> = staging
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(staging.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect();
+
+        if let TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb = format {
+            pixels
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2));
+        }
+        pixels
+    }
+}
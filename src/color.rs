@@ -1,6 +1,11 @@
 use bytemuck::{Pod, Zeroable};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::mem;
+use std::str::FromStr;
 
+/// A color as the shaders read it from instance data: linear RGBA, with the color channels
+/// premultiplied by alpha when made by [`Color::raw_pre_mult`].
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default, PartialEq, Zeroable, Pod)]
 pub struct RawColor {
@@ -10,8 +15,11 @@ pub struct RawColor {
     a: f32,
 }
 
+/// A linear RGBA color with straight alpha. With the `serde` feature it serializes as its
+/// four linear channels, or as a hex string through [`color_hex`].
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     red: f32,
     green: f32,
@@ -324,6 +332,42 @@ impl Color {
         ];
     }
 
+    /// Like [`Self::srgb`], for channels in `0..=1`.
+    pub fn from_srgb_f32(r: f32, g: f32, b: f32) -> Self {
+        Self::rgb(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+    }
+
+    /// The color as sRGB bytes, with alpha scaled to `0..=255`.
+    pub fn to_srgb_u8(self) -> [u8; 4] {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [
+            to_u8(linear_to_srgb(self.red)),
+            to_u8(linear_to_srgb(self.green)),
+            to_u8(linear_to_srgb(self.blue)),
+            to_u8(self.alpha),
+        ]
+    }
+
+    #[inline]
+    pub const fn red(self) -> f32 {
+        self.red
+    }
+
+    #[inline]
+    pub const fn green(self) -> f32 {
+        self.green
+    }
+
+    #[inline]
+    pub const fn blue(self) -> f32 {
+        self.blue
+    }
+
+    #[inline]
+    pub const fn alpha(self) -> f32 {
+        self.alpha
+    }
+
     pub const fn srgb(r: u8, g: u8, b: u8) -> Self {
         Self::rgb(
             Self::srgb_lookup(r),
@@ -332,12 +376,170 @@ impl Color {
         )
     }
 
-    pub fn lerp(self, end: Self, progress: f32) -> Self {
-        #[inline]
-        fn lerp(start: f32, end: f32, progress: f32) -> f32 {
-            start + progress * (end - start)
+    /// Hue in degrees, wrapping outside `0..360`, with saturation and value clamped to
+    /// `0..=1`. The conversion happens in sRGB, so the result matches other tools' HSV.
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+        let chroma = value * saturation;
+        Self::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Hue in degrees, wrapping outside `0..360`, with saturation and lightness clamped to
+    /// `0..=1`. Like [`Self::hsv`], the conversion happens in sRGB.
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Self::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// [`Self::hsl`] with alpha, from `[hue, saturation, lightness, alpha]` as physics debug
+    /// renderers such as rapier's hand colors out.
+    pub fn hsla([hue, saturation, lightness, alpha]: [f32; 4]) -> Self {
+        Self::hsl(hue, saturation, lightness).with_alpha(alpha.clamp(0.0, 1.0))
+    }
+
+    /// The sRGB color with the given hue and chroma, with `min` added to every channel.
+    fn from_hue_chroma(hue: f32, chroma: f32, min: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Self::rgb(
+            srgb_to_linear(r + min),
+            srgb_to_linear(g + min),
+            srgb_to_linear(b + min),
+        )
+    }
+
+    /// Rotates the hue by `degrees` in HSV, keeping saturation, value and alpha.
+    pub fn with_hue_rotated(self, degrees: f32) -> Self {
+        let r = linear_to_srgb(self.red);
+        let g = linear_to_srgb(self.green);
+        let b = linear_to_srgb(self.blue);
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        Self::from_hue_chroma(hue + degrees, chroma, max - chroma).with_alpha(self.alpha)
+    }
+
+    /// The `index`th of 10 distinct colors for plotting series, cycling after that. These
+    /// are the Tableau 10 colors matplotlib uses by default.
+    pub const fn categorical(index: usize) -> Self {
+        Self::from_hex(CATEGORICAL_20[index % 10 * 2])
+    }
+
+    /// Like [`Self::categorical`], with 20 colors: the same 10, each followed by a lighter
+    /// variant.
+    pub const fn categorical_20(index: usize) -> Self {
+        Self::from_hex(CATEGORICAL_20[index % 20])
+    }
+
+    /// Moves the color towards black by `amount` in `0..=1`, in OKLab. Colors outside the
+    /// sRGB gamut come back with their channels clamped to `0..=1`, unless `amount` is 0.
+    pub fn darken(self, amount: f32) -> Self {
+        if amount.clamp(0.0, 1.0) == 0.0 {
+            return self;
+        }
+        self.mix(Self::BLACK.with_alpha(self.alpha), amount)
+    }
+
+    /// Moves the color towards white by `amount` in `0..=1`, in OKLab. Colors outside the
+    /// sRGB gamut come back with their channels clamped to `0..=1`, unless `amount` is 0.
+    pub fn lighten(self, amount: f32) -> Self {
+        if amount.clamp(0.0, 1.0) == 0.0 {
+            return self;
         }
+        self.mix(Self::WHITE.with_alpha(self.alpha), amount)
+    }
+
+    /// Scales chroma by `1 + amount` in OKLab, with `amount` clamped to `-1..=1`, so -1 is
+    /// [`Self::grayscale`]. Channels pushed out of the sRGB gamut are clamped to `0..=1`,
+    /// unless `amount` is 0, which returns the color as it is.
+    pub fn saturate(self, amount: f32) -> Self {
+        let amount = amount.clamp(-1.0, 1.0);
+        if amount == 0.0 {
+            return self;
+        }
+        let [l, a, b] = self.to_oklab();
+        let scale = 1.0 + amount;
+        Self::from_oklab([l, a * scale, b * scale], self.alpha)
+    }
+
+    /// The gray with the same OKLab lightness.
+    pub fn grayscale(self) -> Self {
+        let [l, _, _] = self.to_oklab();
+        Self::from_oklab([l, 0.0, 0.0], self.alpha)
+    }
+
+    /// Interpolates in OKLab, with `progress` clamped to `0..=1`. Unlike [`Self::lerp`],
+    /// midpoints keep a lightness between the ends.
+    pub fn mix(self, other: Self, progress: f32) -> Self {
+        let progress = progress.clamp(0.0, 1.0);
+        let start = self.to_oklab();
+        let end = other.to_oklab();
+        Self::from_oklab(
+            [0, 1, 2].map(|i| lerp(start[i], end[i], progress)),
+            lerp(self.alpha, other.alpha, progress),
+        )
+    }
+
+    /// Björn Ottosson's OKLab, from linear sRGB.
+    #[allow(clippy::excessive_precision)]
+    fn to_oklab(self) -> [f32; 3] {
+        let (r, g, b) = (self.red, self.green, self.blue);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        [
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        ]
+    }
 
+    /// The inverse of [`Self::to_oklab`], clamping channels outside the sRGB gamut.
+    #[allow(clippy::excessive_precision)]
+    fn from_oklab([l, a, b]: [f32; 3], alpha: f32) -> Self {
+        let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+        Self::new(
+            (4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_).clamp(0.0, 1.0),
+            (-1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_).clamp(0.0, 1.0),
+            (-0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_).clamp(0.0, 1.0),
+            alpha,
+        )
+    }
+
+    /// `0xRRGGBB`, with the channels in sRGB like [`Self::srgb`].
+    pub const fn from_hex(rgb: u32) -> Self {
+        Self::srgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    /// `0xRRGGBBAA`, with the color channels in sRGB like [`Self::srgb`] and alpha linear.
+    pub const fn from_hex_rgba(rgba: u32) -> Self {
+        Self::from_hex(rgba >> 8).with_alpha((rgba & 0xFF) as f32 / 255.0)
+    }
+
+    /// Interpolates in linear space, with `progress` clamped to `0..=1`.
+    pub fn lerp(self, end: Self, progress: f32) -> Self {
+        let progress = progress.clamp(0.0, 1.0);
         Self {
             red: lerp(self.red, end.red, progress),
             green: lerp(self.green, end.green, progress),
@@ -346,6 +548,21 @@ impl Color {
         }
     }
 
+    /// Interpolates the color channels in sRGB space, which looks more even to the eye than
+    /// [`Self::lerp`] but blends less like light does. Alpha is interpolated linearly.
+    pub fn lerp_srgb(self, end: Self, progress: f32) -> Self {
+        let progress = progress.clamp(0.0, 1.0);
+        let channel = |start: f32, end: f32| {
+            srgb_to_linear(lerp(linear_to_srgb(start), linear_to_srgb(end), progress))
+        };
+        Self {
+            red: channel(self.red, end.red),
+            green: channel(self.green, end.green),
+            blue: channel(self.blue, end.blue),
+            alpha: lerp(self.alpha, end.alpha, progress),
+        }
+    }
+
     #[inline]
     pub const fn with_alpha(self, alpha: f32) -> Self {
         Self::new(self.red, self.green, self.blue, alpha)
@@ -361,8 +578,21 @@ impl Color {
         unsafe { mem::transmute(self) }
     }
 
+    /// The inverse of [`Self::raw_pre_mult`]. Fully transparent colors come back black.
+    pub fn from_pre_mult(raw: RawColor) -> Self {
+        let unmultiply = |c: f32| if raw.a > 0.0 { c / raw.a } else { 0.0 };
+        Self::new(
+            unmultiply(raw.r),
+            unmultiply(raw.g),
+            unmultiply(raw.b),
+            raw.a,
+        )
+    }
+
+    /// The color with its channels multiplied by alpha, as [`Line`](crate::Line) and
+    /// [`RectOrCircle`](crate::RectOrCircle) store it.
     #[inline]
-    pub fn raw_pre_mult(self) -> RawColor {
+    pub const fn raw_pre_mult(self) -> RawColor {
         Self::new(
             self.red * self.alpha,
             self.green * self.alpha,
@@ -372,3 +602,479 @@ impl Color {
         .raw()
     }
 }
+
+impl RawColor {
+    #[inline]
+    pub const fn red(self) -> f32 {
+        self.r
+    }
+
+    #[inline]
+    pub const fn green(self) -> f32 {
+        self.g
+    }
+
+    #[inline]
+    pub const fn blue(self) -> f32 {
+        self.b
+    }
+
+    #[inline]
+    pub const fn alpha(self) -> f32 {
+        self.a
+    }
+}
+
+/// Same as [`Color::raw`], without premultiplying.
+impl From<Color> for RawColor {
+    fn from(color: Color) -> Self {
+        color.raw()
+    }
+}
+
+impl From<RawColor> for Color {
+    fn from(raw: RawColor) -> Self {
+        Self::new(raw.r, raw.g, raw.b, raw.a)
+    }
+}
+
+/// `x`, `y`, `z` and `w` as linear red, green, blue and straight alpha.
+#[cfg(feature = "mint")]
+impl From<mint::Vector4<f32>> for Color {
+    fn from(value: mint::Vector4<f32>) -> Self {
+        Self::new(value.x, value.y, value.z, value.w)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Color> for mint::Vector4<f32> {
+    fn from(color: Color) -> Self {
+        Self {
+            x: color.red,
+            y: color.green,
+            z: color.blue,
+            w: color.alpha,
+        }
+    }
+}
+
+/// The linear color, which is what wgpu expects for clear colors of sRGB targets.
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        Self {
+            r: color.red as f64,
+            g: color.green as f64,
+            b: color.blue as f64,
+            a: color.alpha as f64,
+        }
+    }
+}
+
+/// Serializes a [`Color`] as an sRGB `#rrggbbaa` string, for
+/// `#[serde(with = "wgpu_rendering::color_hex")]`. Deserializing accepts every form
+/// [`Color`]'s `FromStr` does.
+#[cfg(feature = "serde")]
+pub mod color_hex {
+    use super::Color;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.to_srgb_u8();
+        serializer.collect_str(&format_args!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[inline]
+fn lerp(start: f32, end: f32, progress: f32) -> f32 {
+    start + progress * (end - start)
+}
+
+/// Decodes an sRGB channel in `0..=1` to linear light.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear channel in `0..=1` as sRGB, the inverse of [`srgb_to_linear`].
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Colors placed along `0..=1`, interpolated in linear space between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    /// `stops` are `(position, color)` pairs in any order. Panics if there are none.
+    pub fn new(stops: &[(f32, Color)]) -> Self {
+        assert!(!stops.is_empty(), "a gradient needs at least one stop");
+        let mut stops = stops.to_vec();
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { stops }
+    }
+
+    /// Evenly spaced stops from `0xRRGGBB` sRGB colors.
+    fn from_hex_stops(colors: &[u32]) -> Self {
+        let last = (colors.len() - 1) as f32;
+        let stops: Vec<_> = (0..)
+            .zip(colors)
+            .map(|(i, &hex)| (i as f32 / last, Color::from_hex(hex)))
+            .collect();
+        Self::new(&stops)
+    }
+
+    /// Matplotlib's perceptually uniform viridis colormap, from dark blue to yellow.
+    pub fn viridis() -> Self {
+        Self::from_hex_stops(&VIRIDIS)
+    }
+
+    /// Google's turbo colormap, a smoother rainbow from dark blue through green to dark red.
+    pub fn turbo() -> Self {
+        Self::from_hex_stops(&TURBO)
+    }
+
+    /// The color at `t`, clamped to `0..=1`. Positions before the first stop or after the
+    /// last take its color.
+    pub fn sample(&self, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let next = self.stops.partition_point(|&(position, _)| position <= t);
+        match (next.checked_sub(1), self.stops.get(next)) {
+            (Some(prev), Some(&(end, end_color))) => {
+                let (start, start_color) = self.stops[prev];
+                start_color.lerp(end_color, (t - start) / (end - start))
+            }
+            (Some(prev), None) => self.stops[prev].1,
+            (None, _) => self.stops[0].1,
+        }
+    }
+
+    /// `len` evenly spaced samples from 0 to 1, for lookup tables such as a 1D texture.
+    pub fn lut(&self, len: usize) -> Vec<Color> {
+        let last = len.saturating_sub(1).max(1) as f32;
+        (0..len).map(|i| self.sample(i as f32 / last)).collect()
+    }
+}
+
+/// Tableau 20, in sRGB. The even entries are Tableau 10.
+const CATEGORICAL_20: [u32; 20] = [
+    0x1F77B4, 0xAEC7E8, 0xFF7F0E, 0xFFBB78, 0x2CA02C, 0x98DF8A, 0xD62728, 0xFF9896, 0x9467BD,
+    0xC5B0D5, 0x8C564B, 0xC49C94, 0xE377C2, 0xF7B6D2, 0x7F7F7F, 0xC7C7C7, 0xBCBD22, 0xDBDB8D,
+    0x17BECF, 0x9EDAE5,
+];
+
+/// Viridis at every tenth, in sRGB.
+const VIRIDIS: [u32; 11] = [
+    0x440154, 0x482475, 0x414487, 0x355F8D, 0x2A788E, 0x21918C, 0x22A884, 0x44BF70, 0x7AD151,
+    0xBDDF26, 0xFDE725,
+];
+
+/// Turbo at every sixteenth, in sRGB, from its published polynomial approximation.
+const TURBO: [u32; 17] = [
+    0x23171B, 0x493EAE, 0x4569EE, 0x3295F7, 0x26BCE1, 0x29DCBC, 0x3FF393, 0x65FD6E, 0x95FB51,
+    0xC5EC3C, 0xECD12E, 0xFFAD24, 0xFF821D, 0xF05616, 0xCB2F0D, 0xA31302, 0x900C00,
+];
+
+/// Why a string couldn't be parsed as a [`Color`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseColorError {
+    /// The string doesn't start with `#`.
+    MissingHash,
+    /// The string has a number of digits other than 3, 6 or 8.
+    InvalidLength(usize),
+    /// The string contains something other than hex digits after the `#`.
+    InvalidDigit(char),
+}
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHash => write!(f, "color doesn't start with '#'"),
+            Self::InvalidLength(len) => {
+                write!(f, "color has {len} digits, expected 3, 6 or 8")
+            }
+            Self::InvalidDigit(c) => write!(f, "color contains non-hex digit {c:?}"),
+        }
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    /// Parses `#rgb`, `#rrggbb` or `#rrggbbaa`, as in CSS.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('#').ok_or(ParseColorError::MissingHash)?;
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ParseColorError::InvalidDigit(c));
+        }
+        let value = u32::from_str_radix(digits, 16).unwrap_or(0);
+        match digits.len() {
+            3 => {
+                // each digit is repeated, so 0xF becomes 0xFF
+                let expand = |shift: u32| ((value >> shift) & 0xF) * 0x11;
+                Ok(Self::from_hex(expand(8) << 16 | expand(4) << 8 | expand(0)))
+            }
+            6 => Ok(Self::from_hex(value)),
+            8 => Ok(Self::from_hex_rgba(value)),
+            len => Err(ParseColorError::InvalidLength(len)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_matches_srgb() {
+        for (r, g, b) in [(0, 0, 0), (255, 255, 255), (255, 128, 0), (12, 34, 56)] {
+            let hex = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+            assert_eq!(Color::from_hex(hex), Color::srgb(r, g, b));
+            assert_eq!(Color::from_hex_rgba(hex << 8 | 0xFF), Color::srgb(r, g, b));
+        }
+        assert_eq!(Color::from_hex(0x0080FF), Color::BLUE);
+    }
+
+    fn assert_close(a: Color, b: Color) {
+        let (a, b) = (a.raw(), b.raw());
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-4;
+        assert!(
+            close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b) && close(a.a, b.a),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn transfer_function_breakpoints() {
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-6;
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert_eq!(srgb_to_linear(1.0), 1.0);
+        assert!(close(srgb_to_linear(0.04045), 0.04045 / 12.92));
+        // just past the breakpoints, the curves continue from the linear segments
+        assert!(close(srgb_to_linear(0.04046), 0.0031316));
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!(close(linear_to_srgb(1.0), 1.0));
+        assert!(close(linear_to_srgb(0.0031308), 0.0031308 * 12.92));
+        assert!(close(linear_to_srgb(0.0031309), 0.0404512));
+        for i in 0..=100 {
+            let c = i as f32 / 100.0;
+            assert!(close(linear_to_srgb(srgb_to_linear(c)), c), "{c}");
+        }
+    }
+
+    #[test]
+    fn srgb_bytes_round_trip() {
+        for c in 0..=255 {
+            let color = Color::srgb(c, 255 - c, c / 2);
+            assert_eq!(color.to_srgb_u8(), [c, 255 - c, c / 2, 255]);
+            assert_close(
+                Color::from_srgb_f32(c as f32 / 255.0, 1.0 - c as f32 / 255.0, 0.0),
+                Color::srgb(c, 255 - c, 0),
+            );
+        }
+        assert_eq!(
+            Color::ORANGE.with_alpha(0.5).to_srgb_u8(),
+            [255, 128, 0, 128]
+        );
+    }
+
+    #[test]
+    fn conversions_keep_components() {
+        let color = Color::new(0.1, 0.2, 0.3, 0.4);
+        let raw = RawColor::from(color);
+        assert_eq!(
+            [raw.red(), raw.green(), raw.blue(), raw.alpha()],
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        );
+        assert_eq!(Color::from(raw), color);
+        assert_eq!(
+            wgpu::Color::from(Color::WHITE),
+            wgpu::Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            }
+        );
+    }
+
+    #[cfg(feature = "mint")]
+    #[test]
+    fn mint_round_trip() {
+        let color = Color::new(0.1, 0.2, 0.3, 0.4);
+        let vector = mint::Vector4::from(color);
+        assert_eq!(
+            [vector.x, vector.y, vector.z, vector.w],
+            [0.1, 0.2, 0.3, 0.4]
+        );
+        assert_eq!(Color::from(vector), color);
+    }
+
+    #[test]
+    fn hsv_and_hsl_anchor_points() {
+        assert_close(Color::hsv(0.0, 1.0, 1.0), Color::RED);
+        assert_close(Color::hsv(180.0, 1.0, 1.0), Color::CYAN);
+        assert_close(Color::hsl(0.0, 1.0, 0.5), Color::RED);
+        assert_close(Color::hsl(180.0, 1.0, 0.5), Color::CYAN);
+
+        // 50% gray is 0.5 in sRGB, about 0.214 linear
+        let gray = Color::rgb(0.21404114, 0.21404114, 0.21404114);
+        assert_close(Color::hsv(123.0, 0.0, 0.5), gray);
+        assert_close(Color::hsl(123.0, 0.0, 0.5), gray);
+    }
+
+    #[test]
+    fn hue_wraps_and_inputs_clamp() {
+        assert_close(Color::hsv(540.0, 1.0, 1.0), Color::CYAN);
+        assert_close(Color::hsv(-180.0, 1.0, 1.0), Color::CYAN);
+        assert_close(Color::hsv(360.0, 2.0, 1.5), Color::RED);
+        assert_close(
+            Color::hsla([120.0, 1.0, 0.5, 0.25]),
+            Color::GREEN.with_alpha(0.25),
+        );
+        assert_close(Color::hsl(0.0, -1.0, 2.0), Color::WHITE);
+    }
+
+    #[test]
+    fn hue_rotation() {
+        assert_close(Color::RED.with_hue_rotated(120.0), Color::GREEN);
+        assert_close(Color::RED.with_hue_rotated(-60.0), Color::MAGENTA);
+        assert_close(Color::CYAN.with_hue_rotated(180.0), Color::RED);
+        assert_close(
+            Color::ORANGE.with_alpha(0.5).with_hue_rotated(360.0),
+            Color::ORANGE.with_alpha(0.5),
+        );
+        assert_close(Color::GRAY.with_hue_rotated(90.0), Color::GRAY);
+    }
+
+    #[test]
+    fn lerp_clamps() {
+        assert_close(Color::BLACK.lerp(Color::WHITE, 2.0), Color::WHITE);
+        assert_close(Color::BLACK.lerp(Color::WHITE, -1.0), Color::BLACK);
+        assert_close(Color::BLACK.lerp_srgb(Color::WHITE, 1.5), Color::WHITE);
+        // halfway in sRGB is 50% gray, not halfway in linear
+        assert_close(
+            Color::BLACK.lerp_srgb(Color::WHITE, 0.5),
+            Color::hsv(0.0, 0.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn gradient_sampling() {
+        let gradient = Gradient::new(&[
+            (1.0, Color::RED),
+            (0.0, Color::PURE_BLUE),
+            (0.5, Color::WHITE),
+        ]);
+        assert_close(gradient.sample(0.0), Color::PURE_BLUE);
+        assert_close(gradient.sample(0.5), Color::WHITE);
+        assert_close(gradient.sample(1.0), Color::RED);
+        assert_close(
+            gradient.sample(0.25),
+            Color::PURE_BLUE.lerp(Color::WHITE, 0.5),
+        );
+        assert_close(gradient.sample(-3.0), Color::PURE_BLUE);
+        assert_close(gradient.sample(3.0), Color::RED);
+
+        let single = Gradient::new(&[(0.5, Color::GREEN)]);
+        assert_close(single.sample(0.0), Color::GREEN);
+        assert_close(single.sample(1.0), Color::GREEN);
+    }
+
+    #[test]
+    fn colormap_luts() {
+        let viridis = Gradient::viridis().lut(256);
+        assert_eq!(viridis.len(), 256);
+        assert_close(viridis[0], Color::srgb(0x44, 0x01, 0x54));
+        assert_close(viridis[255], Color::srgb(0xFD, 0xE7, 0x25));
+        assert_close(Gradient::turbo().sample(0.5), Color::from_hex(0x95FB51));
+    }
+
+    #[test]
+    fn categorical_colors_cycle() {
+        assert_eq!(Color::categorical(0), Color::from_hex(0x1F77B4));
+        assert_eq!(Color::categorical(1), Color::from_hex(0xFF7F0E));
+        assert_eq!(Color::categorical(13), Color::categorical(3));
+        assert_eq!(Color::categorical_20(1), Color::from_hex(0xAEC7E8));
+        assert_eq!(Color::categorical_20(22), Color::categorical(1));
+    }
+
+    #[test]
+    fn manipulation_identities_and_limits() {
+        let base = Color::ORANGE.with_alpha(0.75);
+        assert_eq!(base.darken(0.0), base);
+        assert_eq!(base.lighten(-1.0), base);
+        assert_eq!(base.saturate(0.0), base);
+        // out of gamut, which a round trip through OKLab would clamp
+        let bright = Color::new(2.0, 0.5, -0.25, 1.0);
+        assert_eq!(bright.darken(0.0), bright);
+        assert_eq!(bright.saturate(0.0), bright);
+        assert_ne!(bright.darken(0.1), bright);
+        assert_close(base.mix(Color::BLUE, 0.0), base);
+        assert_close(base.mix(Color::BLUE, 1.0), Color::BLUE);
+
+        assert_close(base.darken(5.0), Color::BLACK.with_alpha(0.75));
+        assert_close(base.lighten(5.0), Color::WHITE.with_alpha(0.75));
+        assert_close(base.saturate(-5.0), base.grayscale());
+        assert_close(Color::GRAY.grayscale(), Color::GRAY);
+    }
+
+    #[test]
+    fn manipulation_directions() {
+        let gray = Color::ORANGE.grayscale().raw();
+        assert!((gray.r - gray.g).abs() < 1e-4 && (gray.g - gray.b).abs() < 1e-4);
+
+        let darker = Color::BLUE.darken(0.3).raw();
+        let lighter = Color::BLUE.lighten(0.3).raw();
+        let blue = Color::BLUE.raw();
+        assert!(darker.b < blue.b && lighter.r > blue.r && lighter.g > blue.g);
+
+        // a gray midpoint in OKLab is about as light as the ends, not darker like in RGB
+        let mid = Color::PURE_BLUE.mix(Color::YELLOW, 0.5).to_oklab()[0];
+        let ends = (Color::PURE_BLUE.to_oklab()[0] + Color::YELLOW.to_oklab()[0]) / 2.0;
+        assert!((mid - ends).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parses_hex_strings() {
+        assert_eq!("#ff8000".parse(), Ok(Color::ORANGE));
+        assert_eq!("#FF8000".parse(), Ok(Color::ORANGE));
+        assert_eq!("#f0f".parse(), Ok(Color::MAGENTA));
+        assert_eq!("#0c2238".parse(), Ok(Color::srgb(12, 34, 56)));
+        assert_eq!("#0080ff00".parse(), Ok(Color::BLUE.with_alpha(0.0)));
+    }
+
+    #[test]
+    fn rejects_malformed_strings() {
+        assert_eq!("ff8000".parse::<Color>(), Err(ParseColorError::MissingHash));
+        assert_eq!(
+            "#ff80".parse::<Color>(),
+            Err(ParseColorError::InvalidLength(4))
+        );
+        assert_eq!(
+            "#ff80zz".parse::<Color>(),
+            Err(ParseColorError::InvalidDigit('z'))
+        );
+        assert_eq!(
+            "#+f8000".parse::<Color>(),
+            Err(ParseColorError::InvalidDigit('+'))
+        );
+    }
+}
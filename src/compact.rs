@@ -0,0 +1,204 @@
+use super::color::{Color, RawColor};
+use super::dynamic_storage::{DynamicStorageBuffer, GrowthStrategy, ShrinkPolicy, UploadMethod};
+use bytemuck::Pod;
+use std::mem;
+use std::ops::Range;
+use wgpu::{
+    BindGroup, BindGroupLayout, BufferAddress, CommandEncoder, Device, Queue, RenderPass,
+    ShaderModuleDescriptor, ShaderSource,
+};
+
+/// An instance type that can also be stored with its colors packed into 8-bit sRGB, see
+/// [`WindowConfig::compact_colors`](crate::WindowConfig::compact_colors).
+pub(crate) trait CompactInstance: Pod {
+    /// The packed layout, matching the `StoredInstance` of the stage's `*_compact.wgsl`.
+    type Compact: Pod;
+
+    fn compact(&self) -> Self::Compact;
+}
+
+/// `color` as straight sRGB bytes, red in the lowest byte, for `unpack4x8unorm`.
+pub(crate) fn pack_color(color: RawColor) -> u32 {
+    u32::from_le_bytes(Color::from_pre_mult(color).to_srgb_u8())
+}
+
+/// The instance buffer of a stage, in the full or the compact layout.
+pub(crate) enum InstanceBuffer<I: CompactInstance> {
+    Full(DynamicStorageBuffer<I>),
+    /// With the packed instances of the last upload, kept to reuse the allocation.
+    Compact(DynamicStorageBuffer<I::Compact>, Vec<I::Compact>),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $buffer:ident => $body:expr) => {
+        match $self {
+            InstanceBuffer::Full($buffer) => $body,
+            InstanceBuffer::Compact($buffer, _) => $body,
+        }
+    };
+}
+
+impl<I: CompactInstance> InstanceBuffer<I> {
+    pub fn with_label(
+        device: &Device,
+        label: &'static str,
+        item_capacity: BufferAddress,
+        upload_method: UploadMethod,
+        compact: bool,
+    ) -> Self {
+        match compact {
+            true => Self::Compact(
+                DynamicStorageBuffer::with_label(device, label, item_capacity, upload_method),
+                Vec::new(),
+            ),
+            false => Self::Full(DynamicStorageBuffer::with_label(
+                device,
+                label,
+                item_capacity,
+                upload_method,
+            )),
+        }
+    }
+
+    pub fn is_compact(&self) -> bool {
+        matches!(self, Self::Compact(..))
+    }
+
+    /// Bytes per instance on the GPU.
+    pub fn item_size(&self) -> BufferAddress {
+        (match self {
+            Self::Full(_) => mem::size_of::<I>(),
+            Self::Compact(..) => mem::size_of::<I::Compact>(),
+        }) as BufferAddress
+    }
+
+    pub fn set_new_data(&mut self, device: &Device, queue: &Queue, data: &[I]) {
+        match self {
+            Self::Full(buffer) => buffer.set_new_data(device, queue, data),
+            Self::Compact(buffer, packed) => {
+                packed.clear();
+                packed.extend(data.iter().map(I::compact));
+                buffer.set_new_data(device, queue, packed);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        dispatch!(self, buffer => buffer.clear())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        dispatch!(self, buffer => buffer.is_empty())
+    }
+
+    pub fn capacity(&self) -> BufferAddress {
+        dispatch!(self, buffer => buffer.capacity())
+    }
+
+    pub fn max_capacity(&self) -> BufferAddress {
+        dispatch!(self, buffer => buffer.max_capacity())
+    }
+
+    pub fn memory_usage(&self) -> BufferAddress {
+        dispatch!(self, buffer => buffer.memory_usage())
+    }
+
+    pub fn take_uploaded_bytes(&mut self) -> BufferAddress {
+        dispatch!(self, buffer => buffer.take_uploaded_bytes())
+    }
+
+    pub fn reallocations(&self) -> u64 {
+        dispatch!(self, buffer => buffer.reallocations())
+    }
+
+    pub fn set_buffer_count(&mut self, device: &Device, count: usize) {
+        dispatch!(self, buffer => buffer.set_buffer_count(device, count))
+    }
+
+    pub fn advance_frame(&mut self) {
+        dispatch!(self, buffer => buffer.advance_frame())
+    }
+
+    pub fn set_growth_strategy(&mut self, growth_strategy: GrowthStrategy) {
+        dispatch!(self, buffer => buffer.set_growth_strategy(growth_strategy))
+    }
+
+    pub fn set_shrink_policy(&mut self, shrink_policy: ShrinkPolicy) {
+        dispatch!(self, buffer => buffer.set_shrink_policy(shrink_policy))
+    }
+
+    pub fn maintain(&mut self, device: &Device, command_encoder: &mut CommandEncoder) {
+        dispatch!(self, buffer => buffer.maintain(device, command_encoder))
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        dispatch!(self, buffer => buffer.bind_group_layout())
+    }
+
+    pub fn enable_read_write(&mut self, device: &Device) -> &BindGroupLayout {
+        dispatch!(self, buffer => buffer.enable_read_write(device))
+    }
+
+    pub fn read_write_bind_group(&self) -> Option<&BindGroup> {
+        dispatch!(self, buffer => buffer.read_write_bind_group())
+    }
+
+    pub fn bind_to(&self, render_pass: &mut RenderPass, index: u32) {
+        dispatch!(self, buffer => buffer.bind_to(render_pass, index))
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&BindGroup, Range<u32>)> {
+        let (full, compact) = match self {
+            Self::Full(buffer) => (Some(buffer.chunks()), None),
+            Self::Compact(buffer, _) => (None, Some(buffer.chunks())),
+        };
+        full.into_iter()
+            .flatten()
+            .chain(compact.into_iter().flatten())
+    }
+}
+
+/// A built-in shader, with the part from its `// instance layout` line to its
+/// `// end of instance layout` line replaced by `compact_layout`, if any.
+pub(crate) fn shader(
+    label: &'static str,
+    source: &'static str,
+    compact_layout: Option<&'static str>,
+) -> ShaderModuleDescriptor<'static> {
+    let source = match compact_layout {
+        None => source.into(),
+        Some(layout) => {
+            let start = source
+                .find("// instance layout")
+                .expect("no instance layout");
+            let end_marker = "// end of instance layout";
+            let end = source.find(end_marker).expect("no end of instance layout");
+            format!(
+                "{}{layout}{}",
+                &source[..start],
+                &source[end + end_marker.len()..]
+            )
+            .into()
+        }
+    };
+    ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Wgsl(source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pack_color;
+    use crate::Color;
+
+    #[test]
+    fn colors_pack_as_straight_srgb_bytes() {
+        let color = Color::srgb(255, 128, 0).with_alpha(0.5);
+        assert_eq!(
+            pack_color(color.raw_pre_mult()).to_le_bytes(),
+            [255, 128, 0, 128]
+        );
+        assert_eq!(pack_color(Color::WHITE.with_alpha(0.0).raw_pre_mult()), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use super::stages::StagePipelines;
+use super::{util, RenderStage};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, CommandEncoder,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, PipelineCache,
+    PipelineCompilationOptions, Queue, ShaderModuleDescriptor, ShaderStages,
+};
+
+/// A compute shader that runs every frame before the render pass and writes the instances of
+/// `target` on the GPU.
+///
+/// The shader sees these bind groups:
+/// - group 0, binding 0: the target's instances, as a `read_write` storage array of `Line` or
+///   `RectOrCircle`, with premultiplied colors. Only the first storage binding's worth of
+///   instances is reachable.
+/// - group 1, binding 0: `uniform`, as a uniform buffer.
+/// - group 2, binding 0: with [`WindowConfig::indirect_draws`](crate::WindowConfig), the
+///   target's indirect draw args as a `read_write` storage buffer of `u32`s.
+///
+/// Instances submitted in `render` are uploaded before the shader runs, so it sees and may
+/// overwrite them. When the buffer grows to fit more submitted instances, only the submitted
+/// ones are carried over.
+pub struct ComputeStage {
+    /// [`RenderStage::Line`] or [`RenderStage::RectsAndCircles`]; the grid has no instances.
+    pub target: RenderStage,
+    pub shader: ShaderModuleDescriptor<'static>,
+    pub entry_point: &'static str,
+    pub workgroups: (u32, u32, u32),
+    /// Initial contents of the uniform buffer, which keeps this size. Updated with
+    /// [`WindowAccess::set_compute_uniform`](crate::WindowAccess::set_compute_uniform).
+    pub uniform: Vec<u8>,
+}
+
+/// A [`ComputeStage`] with its pipeline and bind groups created.
+pub(crate) struct ComputeRunner {
+    target: RenderStage,
+    pipeline: ComputePipeline,
+    workgroups: (u32, u32, u32),
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    indirect_bind_group: Option<BindGroup>,
+}
+
+impl ComputeRunner {
+    pub fn new(
+        device: &Device,
+        stage: ComputeStage,
+        stages: &mut StagePipelines,
+        cache: Option<&PipelineCache>,
+    ) -> Self {
+        let shader = device.create_shader_module(stage.shader);
+
+        // uniform buffers must be bindable, which an empty or unaligned one isn't
+        let mut uniform = stage.uniform;
+        uniform.resize(uniform.len().max(16).next_multiple_of(16), 0);
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("compute stage uniform"),
+            contents: &uniform,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let uniform_layout = Self::create_single_buffer_layout(device, BufferBindingType::Uniform);
+        let uniform_bind_group =
+            Self::create_single_buffer_bind_group(device, &uniform_layout, &uniform_buffer);
+
+        let indirect_layout = Self::create_single_buffer_layout(
+            device,
+            BufferBindingType::Storage { read_only: false },
+        );
+        let indirect_bind_group = stages.indirect_args(stage.target).map(|indirect_args| {
+            Self::create_single_buffer_bind_group(device, &indirect_layout, indirect_args)
+        });
+
+        let instance_layout = stages.enable_read_write(device, stage.target);
+        let mut bind_group_layouts = vec![instance_layout, &uniform_layout];
+        if indirect_bind_group.is_some() {
+            bind_group_layouts.push(&indirect_layout);
+        }
+        let pipeline_layout =
+            util::create_pipeline_layout(device, "compute stage layout", &bind_group_layouts);
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("compute stage"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: stage.entry_point,
+            compilation_options: PipelineCompilationOptions::default(),
+            cache,
+        });
+
+        Self {
+            target: stage.target,
+            pipeline,
+            workgroups: stage.workgroups,
+            uniform_buffer,
+            uniform_bind_group,
+            indirect_bind_group,
+        }
+    }
+
+    fn create_single_buffer_layout(device: &Device, ty: BufferBindingType) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    fn create_single_buffer_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        buffer: &Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        })
+    }
+
+    pub fn set_workgroups(&mut self, workgroups: (u32, u32, u32)) {
+        self.workgroups = workgroups;
+    }
+
+    /// Overwrites the start of the uniform buffer. Data beyond its size is ignored.
+    pub fn set_uniform(&self, queue: &Queue, data: &[u8]) {
+        let len = data.len().min(self.uniform_buffer.size() as usize);
+        queue.write_buffer(&self.uniform_buffer, 0, &data[..len]);
+    }
+
+    /// Encodes the compute pass. wgpu inserts the barriers between it and the render pass
+    /// that reads the instances afterwards.
+    pub fn run(&self, command_encoder: &mut CommandEncoder, stages: &StagePipelines) {
+        let Some(instances) = stages.read_write_bind_group(self.target) else {
+            return;
+        };
+        let (x, y, z) = self.workgroups;
+
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("compute stage"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, instances, &[]);
+        compute_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        if let Some(indirect_bind_group) = &self.indirect_bind_group {
+            compute_pass.set_bind_group(2, indirect_bind_group, &[]);
+        }
+        compute_pass.dispatch_workgroups(x, y, z);
+    }
+}
@@ -0,0 +1,313 @@
+use super::dynamic_storage::{GrowthStrategy, ShrinkPolicy, INITIAL_CAPACITY};
+use super::post::PostEffect;
+use std::path::PathBuf;
+use std::time::Duration;
+use wgpu::{
+    Backends, BufferAddress, Dx12Compiler, Features, Gles3MinorVersion, InstanceFlags, Limits,
+    PowerPreference, PresentMode, ShaderModuleDescriptor,
+};
+use winit::dpi::PhysicalSize;
+use winit::window::{Fullscreen, Icon, Window, WindowBuilder};
+
+/// Whether and how the window covers a whole monitor.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// A borderless window covering the monitor with the given index, or the current one.
+    Borderless { monitor: Option<usize> },
+    /// Exclusive fullscreen at the monitor's largest video mode, falling back to borderless on
+    /// platforms or monitors where no video mode is available.
+    Exclusive { monitor: Option<usize> },
+}
+
+/// When the event loop updates and redraws.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum UpdateMode {
+    /// Update and redraw as fast as possible (or as vsync allows).
+    #[default]
+    Continuous,
+    /// Sleep until there's input, a [`WindowAccess::request_redraw`](crate::WindowAccess::request_redraw)
+    /// or `max_wait` has passed since the last update. Each update ticks exactly once with the
+    /// real time since the previous update, ignoring [`Renderable::TICK_RATE`](crate::Renderable::TICK_RATE),
+    /// then redraws. Holding a camera movement key updates continuously.
+    Reactive { max_wait: Duration },
+}
+
+/// Replacements for the shaders of the built-in stages. Each needs a `vs_main` vertex and an
+/// `fs_main` fragment entry point, and may use these bind groups, which stay stable:
+/// - group 0, binding 0: the stage's instances, as a `read` storage array of `Line` or
+///   `RectOrCircle` (see `lines.wgsl` and `rect_circle.wgsl` for their layout), with
+///   premultiplied colors.
+/// - group 1: the camera, with the `Camera` uniform at binding 0, the `vec2<f32>` aspect
+///   transform at binding 1 and the viewport size in pixels at binding 2.
+/// - group 2, lines only: the `rgba32float` accumulation texture as a `read_write` storage
+///   texture at binding 0, which only exists when the
+///   [`LineAccumulation`](crate::LineAccumulation) is a storage texture, and the alpha
+///   settings uniform at binding 1.
+/// - group 2, rects and circles only: a uniform at binding 0 whose first `f32` is
+///   [`Renderable::SHAPE_FEATHER`](crate::Renderable::SHAPE_FEATHER).
+///
+/// Rectangles and circles are drawn as indexed quads and lines as line lists, one instance per
+/// shape. The line shader replaces the hairline shader only; glowing lines keep the built-in
+/// one. WGSL that fails to compile or lacks an entry point falls back to the built-in shader,
+/// logging the error and reporting it through
+/// [`WindowAccess::shader_error`](crate::WindowAccess::shader_error).
+#[derive(Debug, Clone, Default)]
+pub struct StageShaders {
+    pub line: Option<ShaderModuleDescriptor<'static>>,
+    pub rect_circle: Option<ShaderModuleDescriptor<'static>>,
+}
+
+impl FullscreenMode {
+    pub(crate) fn to_winit(self, window: &Window) -> Option<Fullscreen> {
+        let monitor = |index: Option<usize>| match index {
+            Some(index) => window.available_monitors().nth(index),
+            None => window.current_monitor(),
+        };
+
+        match self {
+            Self::Windowed => None,
+            Self::Borderless { monitor: index } => Some(Fullscreen::Borderless(monitor(index))),
+            Self::Exclusive { monitor: index } => {
+                let monitor = monitor(index);
+                let video_mode = monitor.as_ref().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|mode| {
+                        let size = mode.size();
+                        (size.width * size.height, mode.refresh_rate_millihertz())
+                    })
+                });
+
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => {
+                        log::warn!("no exclusive video mode available, using borderless");
+                        Some(Fullscreen::Borderless(monitor))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Window and rendering options for [`run_with`](crate::run_with).
+///
+/// The default reproduces the window [`run`](crate::run) has always opened.
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    /// `None` keeps winit's default title.
+    pub title: Option<String>,
+    pub inner_size: PhysicalSize<u32>,
+    pub min_inner_size: Option<PhysicalSize<u32>>,
+    pub max_inner_size: Option<PhysicalSize<u32>>,
+    pub resizable: bool,
+    pub maximized: bool,
+    pub fullscreen: FullscreenMode,
+    pub decorations: bool,
+    pub transparent: bool,
+    pub window_icon: Option<Icon>,
+
+    pub present_mode: PresentMode,
+    pub power_preference: PowerPreference,
+    /// Backends the adapter may be picked from.
+    pub backends: Backends,
+    /// Debugging and validation flags for the wgpu instance.
+    pub instance_flags: InstanceFlags,
+    pub dx12_shader_compiler: Dx12Compiler,
+    pub gles_minor_version: Gles3MinorVersion,
+    /// Whether to fall back to a software adapter when no hardware adapter is available.
+    pub allow_fallback_adapter: bool,
+    /// Samples per pixel: 1, 2, 4 or 8. Unsupported counts fall back to the highest supported
+    /// count below them.
+    pub msaa_samples: u32,
+    /// Device features needed on top of the ones the built-in stages use.
+    pub required_features: Features,
+    pub required_limits: Limits,
+    /// Directory to keep a pipeline cache in, so shaders compile faster on later runs. Only
+    /// used on adapters that support `Features::PIPELINE_CACHE`, currently Vulkan ones.
+    pub pipeline_cache_dir: Option<PathBuf>,
+    /// Prepended to the labels of the per-frame command encoder and render pass, to tell
+    /// renderers apart in graphics debugger captures.
+    pub label_prefix: Option<String>,
+    /// Directory wgpu records an API trace into for offline replay. Traces are only written
+    /// when `wgpu-core` is built with its `trace` feature; otherwise wgpu logs an error and
+    /// carries on.
+    pub trace_dir: Option<PathBuf>,
+    /// Lines the line buffer has room for before it first grows.
+    pub initial_line_capacity: BufferAddress,
+    /// Rectangles and circles the shape buffer has room for before it first grows.
+    pub initial_shape_capacity: BufferAddress,
+    pub growth_strategy: GrowthStrategy,
+    /// When the built-in instance buffers give back memory after a burst of shapes.
+    pub shrink_policy: ShrinkPolicy,
+    /// How many instance buffers each stage alternates between from frame to frame. With 2,
+    /// uploading a frame's shapes never waits on the GPU still drawing the previous frame,
+    /// at twice the memory. Leave at 1 when shapes are written by compute stages.
+    pub instance_buffers: usize,
+    /// Whether instance colors are uploaded as 8-bit sRGB instead of floats, which takes a
+    /// `Line` from 48 bytes to 32 and a `RectOrCircle` from 64 to 32, at the cost of
+    /// packing them on the CPU every upload and of color precision. Shapes are made the
+    /// same way either way. Compute stages and custom shaders then see the instances as laid
+    /// out in `lines_compact.wgsl` and `rect_circle_compact.wgsl`.
+    pub compact_colors: bool,
+    /// Whether to time each stage on the GPU, for
+    /// [`WindowAccess::gpu_stage_times`](crate::WindowAccess::gpu_stage_times). Ignored when
+    /// the adapter lacks timestamp queries inside passes.
+    pub gpu_profiling: bool,
+    /// Whether the built-in stages draw as many instances as a GPU buffer says instead of
+    /// however many were submitted, so shaders can produce shapes without a CPU round trip.
+    /// The initial capacities then need to cover whatever the shaders write, and only as much
+    /// as fits one storage binding is drawn. See
+    /// [`WindowAccess::indirect_args`](crate::WindowAccess::indirect_args).
+    pub indirect_draws: bool,
+    /// Whether rects and circles are culled against the camera in a compute pass before
+    /// drawing, so only the ones that may be visible are drawn. Needs `indirect_draws`, and
+    /// tests as many instances as the indirect args say, which stay in their buffer from
+    /// frame to frame; the count left is in
+    /// [`RenderStats::culled_survivors`](crate::RenderStats::culled_survivors). Runs after the
+    /// compute stages. The survivors are drawn as a single instance of four vertices each, so
+    /// custom rect and circle shaders need to find their instance like the built-in one does.
+    pub gpu_culling: bool,
+    /// Whether the camera target is kept in double precision, re-centring the coordinates
+    /// sent to the GPU on it every frame. Shapes added through
+    /// [`RenderController::add_line_64`](crate::RenderController::add_line_64) then stay
+    /// precise at any distance from zero, while single precision shapes are moved on the CPU
+    /// each frame.
+    pub double_precision: bool,
+    /// Upper bound on [`WindowAccess::delta_seconds`](crate::WindowAccess::delta_seconds),
+    /// so a stall doesn't produce one huge jump.
+    pub max_delta: Duration,
+    /// Whether `tick` keeps running while the window is minimized or zero-sized.
+    /// Rendering is always suspended then.
+    pub tick_while_minimized: bool,
+    /// Whether the platform input method is enabled, so composition reaches
+    /// [`Renderable::on_ime`](crate::Renderable::on_ime).
+    pub ime_allowed: bool,
+    /// Longest time between two clicks that still counts as a double click.
+    pub double_click_interval: Duration,
+    /// How far, in physical pixels, the cursor may move while a button is held before it
+    /// counts as a drag rather than a click.
+    pub drag_threshold: f32,
+    pub update_mode: UpdateMode,
+    /// Whether the built-in debug overlay starts out shown. See
+    /// [`WindowAccess::set_debug_overlay`](crate::WindowAccess::set_debug_overlay).
+    pub debug_overlay: bool,
+    /// Custom shaders for the built-in stages.
+    pub shaders: StageShaders,
+    /// Effects run over each frame in order, before the egui overlay. With any, the stages
+    /// draw into an `Rgba16Float` texture rather than the surface, so colors brighter than
+    /// white survive until the effects. Left empty, the stages draw straight into the surface
+    /// until [`WindowAccess::set_post_effects`](crate::WindowAccess::set_post_effects) first
+    /// sets some.
+    pub post_effects: Vec<PostEffect>,
+    /// Shader files to watch during development, taking precedence over [`Self::shaders`].
+    #[cfg(feature = "shader-hot-reload")]
+    pub shader_paths: crate::ShaderPaths,
+    /// Whether [`WindowAccess::request_exit`](crate::WindowAccess::request_exit) and
+    /// [`Renderable::EXIT_KEY`](crate::Renderable::EXIT_KEY) also go through
+    /// [`Renderable::on_close_requested`](crate::Renderable::on_close_requested).
+    pub confirm_exit_requests: bool,
+    /// Whether to panic after passing a frame's GPU error to
+    /// [`Renderable::on_gpu_error`](crate::Renderable::on_gpu_error), rather than go on
+    /// drawing. Handy in tests and debug builds.
+    pub panic_on_gpu_error: bool,
+    /// Whether `run_with` installs `env_logger`. Turn this off when the application sets up
+    /// its own logger.
+    pub init_logger: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            inner_size: PhysicalSize::new(1600, 1000),
+            min_inner_size: None,
+            max_inner_size: None,
+            resizable: true,
+            maximized: false,
+            fullscreen: FullscreenMode::Windowed,
+            decorations: true,
+            transparent: false,
+            window_icon: None,
+            present_mode: PresentMode::AutoVsync,
+            power_preference: PowerPreference::HighPerformance,
+            backends: Backends::PRIMARY,
+            instance_flags: InstanceFlags::default(),
+            dx12_shader_compiler: Dx12Compiler::default(),
+            gles_minor_version: Gles3MinorVersion::default(),
+            allow_fallback_adapter: true,
+            msaa_samples: 1,
+            required_features: Features::empty(),
+            required_limits: Limits::default(),
+            pipeline_cache_dir: None,
+            label_prefix: None,
+            trace_dir: None,
+            initial_line_capacity: INITIAL_CAPACITY,
+            initial_shape_capacity: INITIAL_CAPACITY,
+            growth_strategy: GrowthStrategy::default(),
+            shrink_policy: ShrinkPolicy::default(),
+            instance_buffers: 1,
+            compact_colors: false,
+            gpu_profiling: false,
+            indirect_draws: false,
+            gpu_culling: false,
+            double_precision: false,
+            max_delta: Duration::from_millis(250),
+            tick_while_minimized: true,
+            ime_allowed: false,
+            double_click_interval: Duration::from_millis(500),
+            drag_threshold: 4.0,
+            update_mode: UpdateMode::Continuous,
+            debug_overlay: false,
+            shaders: StageShaders::default(),
+            post_effects: Vec::new(),
+            #[cfg(feature = "shader-hot-reload")]
+            shader_paths: crate::ShaderPaths::default(),
+            confirm_exit_requests: false,
+            panic_on_gpu_error: false,
+            init_logger: true,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_inner_size(mut self, width: u32, height: u32) -> Self {
+        self.inner_size = PhysicalSize::new(width, height);
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub(crate) fn window_builder(&self) -> WindowBuilder {
+        let mut builder = WindowBuilder::new()
+            .with_inner_size(self.inner_size)
+            .with_resizable(self.resizable)
+            .with_maximized(self.maximized)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_window_icon(self.window_icon.clone());
+
+        if let Some(title) = &self.title {
+            builder = builder.with_title(title);
+        }
+        if let Some(min_size) = self.min_inner_size {
+            builder = builder.with_min_inner_size(min_size);
+        }
+        if let Some(max_size) = self.max_inner_size {
+            builder = builder.with_max_inner_size(max_size);
+        }
+        builder
+    }
+}
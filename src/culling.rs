@@ -0,0 +1,287 @@
+use super::camera::CameraTransforms;
+use super::compact;
+use super::util;
+use std::sync::{Arc, Mutex};
+use wgpu::util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirectArgs};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferAddress, BufferAsyncError, BufferBindingType,
+    BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor, ComputePipeline,
+    ComputePipelineDescriptor, Device, Maintain, MapMode, PipelineCompilationOptions, ShaderStages,
+};
+
+/// Invocations per workgroup of `culling.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+/// Bytes of compacted index buffer per instance: six `u32` vertex indices.
+const INDEX_BYTES_PER_INSTANCE: BufferAddress = 6 * 4;
+
+type MapResult = Arc<Mutex<Option<Result<(), BufferAsyncError>>>>;
+
+enum Readback {
+    Idle,
+    /// The surviving count was copied into the readback buffer by the frame being encoded.
+    Copied,
+    Mapping(MapResult),
+}
+
+/// Culls rects and circles against the camera with a compute pass, compacting the vertex
+/// indices of the survivors into an index buffer drawn with
+/// [`draw_indexed_indirect`](wgpu::RenderPass::draw_indexed_indirect). The instances stay
+/// where they are, so nothing is uploaded when only the camera moves.
+///
+/// How many survived is read back without waiting, arriving a frame or two late.
+pub(crate) struct GpuCulling {
+    pipeline: ComputePipeline,
+    output_layout: BindGroupLayout,
+    /// Vertex indices of the surviving instances, `4 * instance + corner`.
+    index_buffer: Buffer,
+    /// Instances `index_buffer` has room for.
+    capacity: BufferAddress,
+    /// [`DrawIndexedIndirectArgs`] over `index_buffer`, with the index count added up by the
+    /// shader.
+    culled_args: Buffer,
+    output_bind_group: BindGroup,
+    readback_buffer: Buffer,
+    readback: Readback,
+    survivors: Option<u32>,
+}
+
+impl GpuCulling {
+    /// `instance_layout` is the read-write layout of the instances, and `source_args` the
+    /// stage's indirect args, whose instance count says how many instances to test.
+    pub fn new(
+        device: &Device,
+        instance_layout: &BindGroupLayout,
+        compact_colors: bool,
+        source_args: &Buffer,
+        capacity: BufferAddress,
+    ) -> Self {
+        let storage_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let output_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("culling output bind group layout"),
+            entries: &[
+                storage_entry(0, true),
+                storage_entry(1, false),
+                storage_entry(2, false),
+            ],
+        });
+
+        let pipeline_layout = util::create_pipeline_layout(
+            device,
+            "culling pipeline layout",
+            &[
+                instance_layout,
+                &CameraTransforms::create_bind_group_layout(device),
+                &output_layout,
+            ],
+        );
+        let shader = device.create_shader_module(compact::shader(
+            "culling.wgsl",
+            include_str!("culling.wgsl"),
+            compact_colors.then_some(include_str!("rect_circle_compact.wgsl")),
+        ));
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("culling pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let args = DrawIndexedIndirectArgs {
+            index_count: 0,
+            instance_count: 1,
+            first_index: 0,
+            base_vertex: 0,
+            first_instance: 0,
+        };
+        let culled_args = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("culled draw args"),
+            contents: args.as_bytes(),
+            usage: BufferUsages::INDIRECT
+                | BufferUsages::STORAGE
+                | BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("culled count readback"),
+            size: 4,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let capacity = Self::fitting_capacity(device, capacity);
+        let index_buffer = Self::create_index_buffer(device, capacity);
+        let output_bind_group = Self::create_output_bind_group(
+            device,
+            &output_layout,
+            source_args,
+            &culled_args,
+            &index_buffer,
+        );
+
+        Self {
+            pipeline,
+            output_layout,
+            index_buffer,
+            capacity,
+            culled_args,
+            output_bind_group,
+            readback_buffer,
+            readback: Readback::Idle,
+            survivors: None,
+        }
+    }
+
+    /// `capacity`, limited to what one storage binding of indices holds.
+    fn fitting_capacity(device: &Device, capacity: BufferAddress) -> BufferAddress {
+        let max_binding = device.limits().max_storage_buffer_binding_size as BufferAddress;
+        capacity.clamp(1, max_binding / INDEX_BYTES_PER_INSTANCE)
+    }
+
+    fn create_index_buffer(device: &Device, capacity: BufferAddress) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("culled index buffer"),
+            size: capacity * INDEX_BYTES_PER_INSTANCE,
+            usage: BufferUsages::INDEX | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_output_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        source_args: &Buffer,
+        culled_args: &Buffer,
+        index_buffer: &Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("culling output bind group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: source_args.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: culled_args.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: index_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Encodes the culling pass over up to `capacity` instances, the capacity of the instance
+    /// buffer, growing the index buffer along with it.
+    pub fn run(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        instances: &BindGroup,
+        source_args: &Buffer,
+        capacity: BufferAddress,
+        camera_transforms: &CameraTransforms,
+    ) {
+        let capacity = Self::fitting_capacity(device, capacity);
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            self.index_buffer = Self::create_index_buffer(device, capacity);
+            self.output_bind_group = Self::create_output_bind_group(
+                device,
+                &self.output_layout,
+                source_args,
+                &self.culled_args,
+                &self.index_buffer,
+            );
+        }
+
+        command_encoder.clear_buffer(&self.culled_args, 0, Some(4));
+        {
+            let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("culling pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, instances, &[]);
+            camera_transforms.compute_bind_group_to(&mut compute_pass, 1);
+            compute_pass.set_bind_group(2, &self.output_bind_group, &[]);
+
+            let max_row = device.limits().max_compute_workgroups_per_dimension;
+            let workgroups = capacity.div_ceil(WORKGROUP_SIZE as BufferAddress) as u32;
+            let row = workgroups.min(max_row);
+            compute_pass.dispatch_workgroups(row, workgroups.div_ceil(row), 1);
+        }
+
+        if let Readback::Idle = self.readback {
+            command_encoder.copy_buffer_to_buffer(
+                &self.culled_args,
+                0,
+                &self.readback_buffer,
+                0,
+                4,
+            );
+            self.readback = Readback::Copied;
+        }
+    }
+
+    pub fn index_buffer(&self) -> &Buffer {
+        &self.index_buffer
+    }
+
+    pub fn culled_args(&self) -> &Buffer {
+        &self.culled_args
+    }
+
+    /// Starts mapping the count copied this frame, and picks it up once mapped. Must be called
+    /// after submitting.
+    pub fn after_submit(&mut self, device: &Device) {
+        if let Readback::Copied = self.readback {
+            let mapped = MapResult::default();
+            let callback_mapped = mapped.clone();
+            self.readback_buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    *callback_mapped.lock().unwrap() = Some(result);
+                });
+            self.readback = Readback::Mapping(mapped);
+        }
+        device.poll(Maintain::Poll);
+
+        let Readback::Mapping(mapped) = &self.readback else {
+            return;
+        };
+        let Some(result) = mapped.lock().unwrap().take() else {
+            return;
+        };
+        match result {
+            Ok(()) => {
+                let index_count: u32 = bytemuck::pod_read_unaligned(
+                    &self.readback_buffer.slice(..).get_mapped_range(),
+                );
+                self.readback_buffer.unmap();
+                self.survivors = Some(index_count / 6);
+            }
+            Err(err) => log::warn!("failed to map the culled count: {err}"),
+        }
+        self.readback = Readback::Idle;
+    }
+
+    /// Instances that survived culling in the newest frame read back.
+    pub fn survivors(&self) -> Option<u32> {
+        self.survivors
+    }
+}
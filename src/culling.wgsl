@@ -0,0 +1,85 @@
+// Tests every rect and circle against the view and writes the vertex indices of the ones
+// that may be visible, six per instance, for an indexed indirect draw.
+
+struct InstanceData {
+    offset: vec2<f32>,
+    size: vec2<f32>,
+    color: vec4<f32>,
+    border_color: vec4<f32>,
+    border_width: f32,
+}
+
+// instance layout
+// how `RectOrCircle` is stored, replaced by rect_circle_compact.wgsl with compact colors
+alias StoredInstance = InstanceData;
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return stored;
+}
+// end of instance layout
+
+struct Camera {
+    aim: vec2<f32>,
+    zoom: f32,
+}
+
+struct DrawIndexedArgs {
+    index_count: atomic<u32>,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 64u;
+
+@group(0) @binding(0)
+var<storage, read_write> instance_data: array<StoredInstance>;
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(1)
+var<uniform> aspect_transform: vec2<f32>;
+
+// the stage's own indirect args, whose instance count says how many instances there are
+@group(2) @binding(0)
+var<storage, read> source_args: array<u32, 5>;
+
+@group(2) @binding(1)
+var<storage, read_write> culled_args: DrawIndexedArgs;
+
+@group(2) @binding(2)
+var<storage, read_write> indices: array<u32>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) workgroups: vec3<u32>,
+) {
+    // large dispatches spill over into rows of workgroups
+    let index = id.x + id.y * workgroups.x * WORKGROUP_SIZE;
+    let count = min(source_args[1], min(arrayLength(&instance_data), arrayLength(&indices) / 6u));
+    if index >= count {
+        return;
+    }
+
+    let inst_data = load_instance(instance_data[index]);
+    let half_size = select(inst_data.size, inst_data.size.xx, inst_data.size.y == 0.0);
+    let scale = camera.zoom * aspect_transform;
+    let center = (inst_data.offset - camera.aim) * scale;
+    let extent = abs(half_size * scale);
+    if any(abs(center) - extent > vec2<f32>(1.0)) {
+        return;
+    }
+
+    // the corners of the quad, in the order of the regular index buffer
+    let first = atomicAdd(&culled_args.index_count, 6u);
+    let vertex = index * 4u;
+    indices[first] = vertex;
+    indices[first + 1u] = vertex + 1u;
+    indices[first + 2u] = vertex + 2u;
+    indices[first + 3u] = vertex;
+    indices[first + 4u] = vertex + 2u;
+    indices[first + 5u] = vertex + 3u;
+}
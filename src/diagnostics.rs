@@ -0,0 +1,149 @@
+use std::fmt;
+use wgpu::{
+    Adapter, AdapterInfo, Backends, Features, Instance, InstanceDescriptor, Limits, PresentMode,
+    TextureFormat,
+};
+
+/// An adapter and what it supports, for bug reports. Printing it lists the limits most likely
+/// to matter here.
+#[derive(Debug, Clone)]
+pub struct AdapterReport {
+    pub info: AdapterInfo,
+    pub features: Features,
+    pub limits: Limits,
+}
+
+impl AdapterReport {
+    pub fn new(adapter: &Adapter) -> Self {
+        Self {
+            info: adapter.get_info(),
+            features: adapter.features(),
+            limits: adapter.limits(),
+        }
+    }
+}
+
+impl fmt::Display for AdapterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let AdapterInfo {
+            name,
+            device_type,
+            backend,
+            driver,
+            driver_info,
+            ..
+        } = &self.info;
+        writeln!(f, "{name} ({device_type:?}, {backend:?})")?;
+        // GL adapters leave the driver name empty
+        writeln!(f, "  driver: {}", format!("{driver} {driver_info}").trim())?;
+        writeln!(f, "  features: {:?}", self.features)?;
+        let limits = &self.limits;
+        writeln!(f, "  max texture size: {}", limits.max_texture_dimension_2d)?;
+        writeln!(f, "  max buffer size: {}", limits.max_buffer_size)?;
+        writeln!(
+            f,
+            "  max storage binding: {}",
+            limits.max_storage_buffer_binding_size
+        )?;
+        write!(
+            f,
+            "  max workgroups per dimension: {}",
+            limits.max_compute_workgroups_per_dimension
+        )
+    }
+}
+
+/// What [`run`](crate::run) picked to draw with, logged at startup and available through
+/// [`WindowAccess::gpu_report`](crate::WindowAccess::gpu_report).
+#[derive(Debug, Clone)]
+pub struct GpuReport {
+    pub adapter: AdapterReport,
+    /// Whether no hardware adapter was found and the software fallback is drawing, see
+    /// [`WindowConfig::allow_fallback_adapter`](crate::WindowConfig::allow_fallback_adapter).
+    pub fallback_adapter: bool,
+    /// The formats the surface supports, in the adapter's order of preference.
+    pub surface_formats: Vec<TextureFormat>,
+    pub present_modes: Vec<PresentMode>,
+    /// The format frames are drawn in, the first sRGB one of `surface_formats`.
+    pub surface_format: TextureFormat,
+    /// The present mode asked for in [`WindowConfig`](crate::WindowConfig).
+    pub present_mode: PresentMode,
+}
+
+impl fmt::Display for GpuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.adapter)?;
+        if self.fallback_adapter {
+            writeln!(f, "  no hardware adapter found, using the fallback adapter")?;
+        }
+        writeln!(
+            f,
+            "  surface format: {:?} of {:?}",
+            self.surface_format, self.surface_formats
+        )?;
+        write!(
+            f,
+            "  present mode: {:?} of {:?}",
+            self.present_mode, self.present_modes
+        )
+    }
+}
+
+/// Every adapter on every backend, without opening a window. Which one [`run`](crate::run)
+/// would pick also depends on the surface and [`WindowConfig`](crate::WindowConfig).
+pub fn diagnostics() -> Vec<AdapterReport> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::all(),
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(Backends::all())
+        .iter()
+        .map(AdapterReport::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdapterReport, GpuReport};
+    use wgpu::{AdapterInfo, Backend, DeviceType, Features, Limits, PresentMode, TextureFormat};
+
+    fn adapter() -> AdapterReport {
+        AdapterReport {
+            info: AdapterInfo {
+                name: "Test GPU".to_string(),
+                vendor: 0,
+                device: 0,
+                device_type: DeviceType::Cpu,
+                driver: "testdriver".to_string(),
+                driver_info: "1.0".to_string(),
+                backend: Backend::Vulkan,
+            },
+            features: Features::CLEAR_TEXTURE,
+            limits: Limits::downlevel_defaults(),
+        }
+    }
+
+    #[test]
+    fn reports_read_like_bug_reports() {
+        let text = adapter().to_string();
+        assert!(text.starts_with("Test GPU (Cpu, Vulkan)\n"), "{text}");
+        assert!(text.contains("driver: testdriver 1.0"), "{text}");
+        assert!(text.contains("max texture size: 2048"), "{text}");
+
+        let report = GpuReport {
+            adapter: adapter(),
+            fallback_adapter: true,
+            surface_formats: vec![TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb],
+            present_modes: vec![PresentMode::Fifo],
+            surface_format: TextureFormat::Bgra8UnormSrgb,
+            present_mode: PresentMode::AutoVsync,
+        };
+        let text = report.to_string();
+        assert!(text.contains("fallback adapter"), "{text}");
+        assert!(
+            text.ends_with("present mode: AutoVsync of [Fifo]"),
+            "{text}"
+        );
+    }
+}
@@ -1,50 +1,289 @@
+use super::error::RenderError;
+use super::util::profile_span;
 use bytemuck::{cast_slice, Pod, Zeroable};
+use std::iter;
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
+use std::sync::mpsc;
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
-    BufferUsages, CommandEncoder, Device, Queue, RenderPass, ShaderStages,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferAddress, BufferBinding,
+    BufferBindingType, BufferDescriptor, BufferSize, BufferUsages, CommandEncoder,
+    CommandEncoderDescriptor, Device, Maintain, MapMode, Queue, RenderPass, ShaderStages,
 };
 
+/// How [`DynamicStorageBuffer::set_new_data`] uploads data that fits the current buffer.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum UploadMethod {
+    /// `Queue::write_buffer`, which copies the data into a staging allocation first.
+    #[default]
+    WriteBuffer,
+    /// `Queue::write_buffer_with`, which writes straight into the staging memory and saves
+    /// one copy of the data. Worth it for large uploads.
+    WriteBufferWith,
+}
+
+/// The capacity [`DynamicStorageBuffer::new`] starts out with.
+pub(crate) const INITIAL_CAPACITY: BufferAddress = 4;
+
+/// How much a [`DynamicStorageBuffer`] grows when data no longer fits.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum GrowthStrategy {
+    /// Grow to the next power of two, so repeated appends only reallocate rarely.
+    #[default]
+    PowerOfTwo,
+    /// Grow to this many times the current capacity, or exactly enough if that's more.
+    GrowBy(f32),
+    /// Grow to exactly the required capacity, wasting no memory on steady data.
+    Exact,
+}
+
+impl GrowthStrategy {
+    fn capacity_for(self, required: BufferAddress, current: BufferAddress) -> BufferAddress {
+        match self {
+            Self::PowerOfTwo => required.next_power_of_two(),
+            Self::GrowBy(factor) => {
+                ((current as f64 * factor as f64).ceil() as BufferAddress).max(required)
+            }
+            Self::Exact => required,
+        }
+    }
+}
+
+/// When [`DynamicStorageBuffer::maintain`] gives back memory after the data shrinks.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum ShrinkPolicy {
+    /// Never shrink automatically.
+    Off,
+    /// Shrink when the capacity stays above 4x the peak length for 600 frames.
+    #[default]
+    Conservative,
+    /// Shrink when the capacity stays above 2x the peak length for 60 frames.
+    Aggressive,
+}
+
+impl ShrinkPolicy {
+    /// The number of frames to watch and how many times the peak length the capacity may be.
+    const fn window_and_factor(self) -> Option<(u32, BufferAddress)> {
+        match self {
+            Self::Off => None,
+            Self::Conservative => Some((600, 4)),
+            Self::Aggressive => Some((60, 2)),
+        }
+    }
+}
+
+/// One of the buffers frames alternate between, with the bind groups into it.
+struct Slot {
+    buffer: Buffer,
+    item_capacity: BufferAddress,
+    /// One bind group per `chunk_items` items of capacity.
+    bind_groups: Vec<BindGroup>,
+    /// Gives compute shaders read-write access to the first chunk, once enabled.
+    read_write_bind_group: Option<BindGroup>,
+}
+
 pub struct DynamicStorageBuffer<I: Zeroable + Pod> {
+    label: &'static str,
     length: u32,
-    item_capacity: BufferAddress,
+    upload_method: UploadMethod,
+    growth_strategy: GrowthStrategy,
 
-    buffer: Buffer,
+    shrink_policy: ShrinkPolicy,
+    /// The highest length seen by `maintain` in the current window of frames.
+    window_peak: u32,
+    window_frames: u32,
+
+    /// Bytes written since [`Self::take_uploaded_bytes`] was last called.
+    uploaded_bytes: BufferAddress,
+    reallocations: u64,
+
+    /// Usually one; with more, each frame writes and draws from the next one in turn.
+    slots: Vec<Slot>,
+    current: usize,
     layout: BindGroupLayout,
-    bind_group: BindGroup,
+    read_write_layout: Option<BindGroupLayout>,
+    /// Items per bind group, so no binding exceeds the device's storage binding size.
+    chunk_items: BufferAddress,
+    /// Items in the largest buffer the device allows.
+    max_items: BufferAddress,
 
     phantom_data: PhantomData<I>,
 }
 
 impl<I: Zeroable + Pod> DynamicStorageBuffer<I> {
     pub fn new(device: &Device) -> Self {
-        Self::with_capacity(device, 4)
+        Self::with_capacity(device, INITIAL_CAPACITY)
     }
 
     pub fn len(&self) -> u32 {
         self.length
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The number of items the current buffer can hold before it has to grow.
+    pub fn capacity(&self) -> BufferAddress {
+        self.slot().item_capacity
+    }
+
+    /// The most items the buffer can hold, as the device limits the size of a buffer. Items
+    /// uploaded beyond this are dropped.
+    pub fn max_capacity(&self) -> BufferAddress {
+        self.max_items
+    }
+
+    /// The GPU memory held by all buffers, in bytes.
+    pub fn memory_usage(&self) -> BufferAddress {
+        self.slots
+            .iter()
+            .map(|slot| Self::item_to_byte_capacity(slot.item_capacity))
+            .sum()
+    }
+
+    /// Returns the bytes uploaded since the last call and starts counting again.
+    pub fn take_uploaded_bytes(&mut self) -> BufferAddress {
+        mem::take(&mut self.uploaded_bytes)
+    }
+
+    /// How many times a buffer was replaced by one of a different capacity.
+    pub fn reallocations(&self) -> u64 {
+        self.reallocations
+    }
+
+    fn slot(&self) -> &Slot {
+        &self.slots[self.current]
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Sets how many buffers frames alternate between, at least one. With two or more, a
+    /// frame's upload doesn't have to wait for the GPU to finish drawing the previous frame
+    /// from the same buffer, at the cost of that much more memory.
+    ///
+    /// Each buffer only holds what was written while it was current, so this suits data that
+    /// is replaced with [`Self::set_new_data`] every frame, not data built up with
+    /// [`Self::extend`] or written by compute shaders.
+    pub fn set_buffer_count(&mut self, device: &Device, count: usize) {
+        let count = count.max(1);
+        let item_capacity = self.capacity();
+        while self.slots.len() < count {
+            let slot = self.create_slot(device, item_capacity, false);
+            self.slots.push(slot);
+        }
+        self.slots.truncate(count);
+        self.current %= count;
+    }
+
+    /// Moves on to the next buffer, which the following writes and draws use. Call once per
+    /// frame, before uploading.
+    pub fn advance_frame(&mut self) {
+        self.current = (self.current + 1) % self.slots.len();
+    }
+
+    pub fn growth_strategy(&self) -> GrowthStrategy {
+        self.growth_strategy
+    }
+
+    pub fn set_growth_strategy(&mut self, growth_strategy: GrowthStrategy) {
+        self.growth_strategy = growth_strategy;
+    }
+
+    pub fn shrink_policy(&self) -> ShrinkPolicy {
+        self.shrink_policy
+    }
+
+    pub fn set_shrink_policy(&mut self, shrink_policy: ShrinkPolicy) {
+        self.shrink_policy = shrink_policy;
+        self.window_peak = 0;
+        self.window_frames = 0;
+    }
+
     pub fn bind_group_layout(&self) -> &BindGroupLayout {
         &self.layout
     }
 
     pub fn with_capacity(device: &Device, item_capacity: BufferAddress) -> Self {
-        let byte_capacity = Self::item_to_byte_capacity(item_capacity);
-        let buffer = Self::create_buffer(device, byte_capacity, false);
-        let bind_group_layout = Self::create_bind_group_layout(device);
-        let bind_group = Self::create_bind_group(device, &bind_group_layout, &buffer);
+        Self::with_upload_method(device, item_capacity, UploadMethod::default())
+    }
+
+    pub fn with_upload_method(
+        device: &Device,
+        item_capacity: BufferAddress,
+        upload_method: UploadMethod,
+    ) -> Self {
+        Self::with_label(device, "instance buffer", item_capacity, upload_method)
+    }
+
+    /// Like [`Self::with_upload_method`], with `label` naming the GPU buffers in debuggers.
+    pub fn with_label(
+        device: &Device,
+        label: &'static str,
+        item_capacity: BufferAddress,
+        upload_method: UploadMethod,
+    ) -> Self {
+        let limits = device.limits();
+        let chunk_items = Self::chunk_items(
+            limits.max_storage_buffer_binding_size.into(),
+            limits.min_storage_buffer_offset_alignment.into(),
+        );
+        Self::with_chunk_items(device, label, item_capacity, upload_method, chunk_items)
+    }
 
-        Self {
+    fn with_chunk_items(
+        device: &Device,
+        label: &'static str,
+        item_capacity: BufferAddress,
+        upload_method: UploadMethod,
+        chunk_items: BufferAddress,
+    ) -> Self {
+        let max_items = (device.limits().max_buffer_size / mem::size_of::<I>() as BufferAddress)
+            .min(u32::MAX.into());
+        let mut storage = Self {
+            label,
             length: 0,
-            item_capacity,
-            buffer,
-            layout: bind_group_layout,
-            bind_group,
+            upload_method,
+            growth_strategy: GrowthStrategy::default(),
+            shrink_policy: ShrinkPolicy::default(),
+            window_peak: 0,
+            window_frames: 0,
+            uploaded_bytes: 0,
+            reallocations: 0,
+            slots: Vec::new(),
+            current: 0,
+            layout: Self::create_bind_group_layout(device),
+            read_write_layout: None,
+            chunk_items,
+            max_items,
             phantom_data: PhantomData,
+        };
+        // like shrinking, never down to an empty buffer, which can't be bound
+        let item_capacity = item_capacity.min(max_items).max(1);
+        let slot = storage.create_slot(device, item_capacity, false);
+        storage.slots.push(slot);
+        storage
+    }
+
+    /// The most items one binding can hold, such that every chunk starts at an offset the
+    /// device accepts.
+    fn chunk_items(
+        max_binding_size: BufferAddress,
+        offset_alignment: BufferAddress,
+    ) -> BufferAddress {
+        let item_size = mem::size_of::<I>() as BufferAddress;
+        // the smallest chunk whose byte size is a multiple of the alignment
+        let mut step = 1;
+        while !(step * item_size).is_multiple_of(offset_alignment) {
+            step += 1;
         }
+
+        let max_items = max_binding_size / item_size;
+        (max_items / step * step).max(step)
     }
 
     pub const fn item_to_byte_capacity(item_capacity: BufferAddress) -> BufferAddress {
@@ -67,9 +306,53 @@ impl<I: Zeroable + Pod> DynamicStorageBuffer<I> {
         })
     }
 
-    fn create_buffer(device: &Device, size: BufferAddress, mapped_at_creation: bool) -> Buffer {
+    pub fn create_read_write_bind_group_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("read-write instance bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    /// Sets up a bind group through which compute shaders can write the first chunk, kept up
+    /// to date as the buffer is reallocated, and returns its layout.
+    pub fn enable_read_write(&mut self, device: &Device) -> &BindGroupLayout {
+        if self.read_write_layout.is_none() {
+            let layout = Self::create_read_write_bind_group_layout(device);
+            for slot in &mut self.slots {
+                slot.read_write_bind_group = Some(Self::create_first_chunk_bind_group(
+                    device,
+                    &layout,
+                    &slot.buffer,
+                    slot.item_capacity,
+                    self.chunk_items,
+                ));
+            }
+            self.read_write_layout = Some(layout);
+        }
+        self.read_write_layout.as_ref().unwrap()
+    }
+
+    pub fn read_write_bind_group(&self) -> Option<&BindGroup> {
+        self.slot().read_write_bind_group.as_ref()
+    }
+
+    fn create_buffer(
+        device: &Device,
+        label: &str,
+        size: BufferAddress,
+        mapped_at_creation: bool,
+    ) -> Buffer {
         device.create_buffer(&BufferDescriptor {
-            label: Some("instance buffer"),
+            label: Some(label),
             size,
             usage: BufferUsages::union(
                 BufferUsages::union(BufferUsages::COPY_SRC, BufferUsages::COPY_DST),
@@ -79,68 +362,450 @@ impl<I: Zeroable + Pod> DynamicStorageBuffer<I> {
         })
     }
 
+    /// Shrinks the buffers to the length, but never below one item so they can still be
+    /// bound.
     pub fn shrink_to_fit(&mut self, device: &Device, command_encoder: &mut CommandEncoder) {
-        let item_capacity = self.length as BufferAddress;
-        let old_buffer = self.replace_buffer_with_new_length(device, item_capacity, false);
-
-        command_encoder.copy_buffer_to_buffer(
-            &old_buffer,
-            0,
-            &self.buffer,
-            0,
-            Self::item_to_byte_capacity(item_capacity),
-        );
+        let item_capacity = (self.length as BufferAddress).max(1);
+        self.shrink_all(device, item_capacity, command_encoder);
+    }
+
+    fn shrink_all(
+        &mut self,
+        device: &Device,
+        item_capacity: BufferAddress,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let current = self.current;
+        for index in 0..self.slots.len() {
+            self.current = index;
+            self.replace_buffer_with_new_length(
+                device,
+                item_capacity,
+                false,
+                Some(command_encoder),
+            );
+        }
+        self.current = current;
+    }
+
+    /// Records this frame's length and, once the [`ShrinkPolicy`] says the buffer has been
+    /// oversized for long enough, shrinks it to what the [`GrowthStrategy`] would allocate for
+    /// the recent peak.
+    /// Call once per frame, before the buffer is bound.
+    pub fn maintain(&mut self, device: &Device, command_encoder: &mut CommandEncoder) {
+        let Some((window, factor)) = self.shrink_policy.window_and_factor() else {
+            return;
+        };
+
+        self.window_peak = self.window_peak.max(self.length);
+        self.window_frames += 1;
+        if self.window_frames < window {
+            return;
+        }
+
+        let peak = (self.window_peak as BufferAddress).max(1);
+        if self.capacity() > peak * factor {
+            let new_item_capacity = self.growth_strategy.capacity_for(peak, 0);
+            self.shrink_all(device, new_item_capacity, command_encoder);
+        }
+        self.window_peak = 0;
+        self.window_frames = 0;
     }
 
     pub fn set_new_data(&mut self, device: &Device, queue: &Queue, data: &[I]) {
-        if data.len() <= self.item_capacity as usize {
-            queue.write_buffer(&self.buffer, 0, cast_slice(data));
+        profile_span!("set_new_data");
+        let data = &data[..data.len().min(self.max_items as usize)];
+        let slot = &self.slots[self.current];
+        if data.len() <= slot.item_capacity as usize {
+            profile_span!("write_buffer");
+            let data: &[u8] = cast_slice(data);
+            match (
+                self.upload_method,
+                BufferSize::new(data.len() as BufferAddress),
+            ) {
+                (UploadMethod::WriteBufferWith, Some(size)) => {
+                    queue
+                        .write_buffer_with(&slot.buffer, 0, size)
+                        .expect("write fits in the buffer")
+                        .copy_from_slice(data);
+                }
+                _ => queue.write_buffer(&slot.buffer, 0, data),
+            }
         } else {
-            let new_shape_capacity = (data.len() as BufferAddress).next_power_of_two();
+            profile_span!("realloc");
+            let new_shape_capacity = self
+                .growth_strategy
+                .capacity_for(data.len() as BufferAddress, slot.item_capacity)
+                .min(self.max_items);
             let new_data = cast_slice(data);
-            self.replace_buffer_with_new_length(device, new_shape_capacity, true);
+            self.replace_buffer_with_new_length(device, new_shape_capacity, true, None);
 
-            self.buffer.slice(..).get_mapped_range_mut()[..new_data.len()]
-                .copy_from_slice(new_data);
-            self.buffer.unmap();
+            let buffer = &self.slot().buffer;
+            buffer.slice(..).get_mapped_range_mut()[..new_data.len()].copy_from_slice(new_data);
+            buffer.unmap();
         }
         self.length = data.len() as u32;
+        self.uploaded_bytes += mem::size_of_val(data) as BufferAddress;
+    }
+
+    /// Appends one item, uploading only its bytes. See [`Self::extend`].
+    pub fn push(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        command_encoder: &mut CommandEncoder,
+        item: I,
+    ) {
+        self.extend(device, queue, command_encoder, &[item]);
+    }
+
+    /// Appends `items`, uploading only their bytes. Growing copies the existing items over in
+    /// `command_encoder`, so until that is submitted, writing over them with
+    /// [`Self::write_range`] or [`Self::set_new_data`] gets undone by the copy.
+    pub fn extend(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        command_encoder: &mut CommandEncoder,
+        items: &[I],
+    ) {
+        let room = (self.max_items - self.length as BufferAddress) as usize;
+        let items = &items[..items.len().min(room)];
+        let new_length = self.length as usize + items.len();
+        if new_length > self.capacity() as usize {
+            let new_item_capacity = self
+                .growth_strategy
+                .capacity_for(new_length as BufferAddress, self.capacity())
+                .min(self.max_items);
+            // the copy only covers the items so far, which the write below doesn't touch
+            self.replace_buffer_with_new_length(
+                device,
+                new_item_capacity,
+                false,
+                Some(command_encoder),
+            );
+        }
+
+        let offset = Self::item_to_byte_capacity(self.length as BufferAddress);
+        queue.write_buffer(&self.slot().buffer, offset, cast_slice(items));
+        self.length = new_length as u32;
+        self.uploaded_bytes += mem::size_of_val(items) as BufferAddress;
+    }
+
+    /// Overwrites the items starting at index `offset`, uploading only their bytes.
+    ///
+    /// Panics if the range extends past [`Self::len`].
+    pub fn write_range(&mut self, queue: &Queue, offset: u32, items: &[I]) {
+        assert!(offset as usize + items.len() <= self.length as usize);
+
+        let byte_offset = Self::item_to_byte_capacity(offset as BufferAddress);
+        queue.write_buffer(&self.slot().buffer, byte_offset, cast_slice(items));
+        self.uploaded_bytes += mem::size_of_val(items) as BufferAddress;
+    }
+
+    /// Copies the live items back from the GPU, blocking until they arrive. Meant for
+    /// debugging and tests; it stalls the GPU.
+    pub fn read_back(&self, device: &Device, queue: &Queue) -> Result<Vec<I>, RenderError> {
+        if self.length == 0 {
+            return Ok(Vec::new());
+        }
+
+        let size = Self::item_to_byte_capacity(self.length as BufferAddress);
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("instance readback buffer"),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        command_encoder.copy_buffer_to_buffer(&self.slot().buffer, 0, &readback, 0, size);
+        queue.submit(iter::once(command_encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .expect("map_async callback dropped")
+            .map_err(RenderError::BufferMap)?;
+
+        let items = cast_slice(&slice.get_mapped_range()).to_vec();
+        readback.unmap();
+        Ok(items)
+    }
+
+    /// Empties the buffer without touching GPU memory.
+    pub fn clear(&mut self) {
+        self.length = 0;
     }
 
+    /// Binds the first chunk, which holds everything unless the data is larger than one
+    /// storage binding allows. Prefer [`Self::chunks`] when it might be.
     pub fn bind_to(&self, render_pass: &mut RenderPass, index: u32) {
-        render_pass.set_bind_group(index, &self.bind_group, &[]);
+        render_pass.set_bind_group(index, &self.slot().bind_groups[0], &[]);
     }
 
+    /// The bind group for each chunk the data is split into, with the instance range to draw
+    /// with it. Indices within each chunk start at 0.
+    pub fn chunks(&self) -> impl Iterator<Item = (&BindGroup, Range<u32>)> {
+        let chunk_items = self.chunk_items as u32;
+        self.slot()
+            .bind_groups
+            .iter()
+            .zip((0..self.length).step_by(chunk_items as usize))
+            .map(move |(bind_group, start)| (bind_group, 0..chunk_items.min(self.length - start)))
+    }
+
+    fn create_slot(
+        &self,
+        device: &Device,
+        item_capacity: BufferAddress,
+        mapped_at_creation: bool,
+    ) -> Slot {
+        let byte_capacity = Self::item_to_byte_capacity(item_capacity);
+        let buffer = Self::create_buffer(device, self.label, byte_capacity, mapped_at_creation);
+        let bind_groups = Self::create_bind_groups(
+            device,
+            &self.layout,
+            &buffer,
+            item_capacity,
+            self.chunk_items,
+        );
+        let read_write_bind_group = self.read_write_layout.as_ref().map(|layout| {
+            Self::create_first_chunk_bind_group(
+                device,
+                layout,
+                &buffer,
+                item_capacity,
+                self.chunk_items,
+            )
+        });
+        Slot {
+            buffer,
+            item_capacity,
+            bind_groups,
+            read_write_bind_group,
+        }
+    }
+
+    /// Swaps in a current buffer of a different capacity. With a command encoder, the items
+    /// that fit are copied over from the old buffer, which is returned once the copy is
+    /// encoded.
     fn replace_buffer_with_new_length(
         &mut self,
         device: &Device,
         new_item_capacity: BufferAddress,
         mapped_at_creation: bool,
+        copy_with: Option<&mut CommandEncoder>,
     ) -> Buffer {
-        let new_byte_capacity = Self::item_to_byte_capacity(new_item_capacity);
+        let new_slot = self.create_slot(device, new_item_capacity, mapped_at_creation);
+        let old_slot = mem::replace(&mut self.slots[self.current], new_slot);
+        self.reallocations += 1;
 
-        let new_buffer = Self::create_buffer(device, new_byte_capacity, mapped_at_creation);
-        let new_bind_group = Self::create_bind_group(device, &self.layout, &new_buffer);
+        if let Some(command_encoder) = copy_with {
+            let kept_items = new_item_capacity
+                .min(old_slot.item_capacity)
+                .min(self.length as BufferAddress);
+            command_encoder.copy_buffer_to_buffer(
+                &old_slot.buffer,
+                0,
+                &self.slot().buffer,
+                0,
+                Self::item_to_byte_capacity(kept_items),
+            );
+        }
 
-        let old_buffer = mem::replace(&mut self.buffer, new_buffer);
-        self.bind_group = new_bind_group;
-        self.item_capacity = new_item_capacity;
+        old_slot.buffer
+    }
 
-        old_buffer
+    fn create_bind_groups(
+        device: &Device,
+        layout: &BindGroupLayout,
+        buffer: &Buffer,
+        item_capacity: BufferAddress,
+        chunk_items: BufferAddress,
+    ) -> Vec<BindGroup> {
+        (0..item_capacity.max(1))
+            .step_by(chunk_items as usize)
+            .map(|start| {
+                let items = chunk_items.min(item_capacity - start).max(1);
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("instance bind group"),
+                    layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer,
+                            offset: Self::item_to_byte_capacity(start),
+                            size: BufferSize::new(Self::item_to_byte_capacity(items)),
+                        }),
+                    }],
+                })
+            })
+            .collect()
     }
 
-    fn create_bind_group<'a>(
-        device: &'a Device,
-        layout: &'a BindGroupLayout,
-        buffer: &'a Buffer,
+    fn create_first_chunk_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        buffer: &Buffer,
+        item_capacity: BufferAddress,
+        chunk_items: BufferAddress,
     ) -> BindGroup {
-        device.create_bind_group(&BindGroupDescriptor {
-            label: Some("instance bind group"),
-            layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-        })
+        let items = item_capacity.min(chunk_items);
+        Self::create_bind_groups(device, layout, buffer, items, chunk_items).remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynamicStorageBuffer, UploadMethod};
+    use crate::setup;
+    use std::iter;
+    use wgpu::{CommandEncoderDescriptor, Features, Limits};
+
+    #[test]
+    fn chunks_are_aligned_and_within_the_binding_limit() {
+        // 12 byte items need chunks of multiples of 64 to start at 256 byte offsets
+        let chunk_items = DynamicStorageBuffer::<[u32; 3]>::chunk_items(1000, 256);
+        assert_eq!(chunk_items, 64);
+        assert_eq!(
+            DynamicStorageBuffer::<u32>::chunk_items(1 << 20, 256),
+            1 << 18
+        );
+    }
+
+    #[test]
+    fn data_past_the_binding_limit_is_split_into_chunks() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_chunk_items(
+            &device,
+            "instance buffer",
+            4,
+            UploadMethod::WriteBuffer,
+            64,
+        );
+        storage.set_new_data(&device, &queue, &[0; 200]);
+
+        let ranges: Vec<_> = storage.chunks().map(|(_, instances)| instances).collect();
+        assert_eq!(ranges, [0..64, 0..64, 0..64, 0..8]);
+    }
+
+    #[test]
+    fn set_new_data_round_trips_through_a_grow() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_capacity(&device, 2);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [0u32; 0]);
+
+        storage.set_new_data(&device, &queue, &[1, 2]);
+        storage.set_new_data(&device, &queue, &[3, 4, 5]);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn a_zero_capacity_still_binds() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_capacity(&device, 0);
+        assert_eq!(storage.capacity(), 1);
+        storage.set_new_data(&device, &queue, &[1, 2, 3]);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn extend_keeps_contents_when_growing() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_capacity(&device, 2);
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        storage.extend(&device, &queue, &mut command_encoder, &[1, 2]);
+        storage.extend(&device, &queue, &mut command_encoder, &[3, 4, 5]);
+        storage.push(&device, &queue, &mut command_encoder, 6);
+        queue.submit(iter::once(command_encoder.finish()));
+        assert_eq!(storage.len(), 6);
+        assert_eq!(
+            storage.read_back(&device, &queue).unwrap(),
+            [1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn shrink_to_fit_keeps_contents() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_capacity(&device, 64);
+        storage.set_new_data(&device, &queue, &[7, 8, 9]);
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        storage.shrink_to_fit(&device, &mut command_encoder);
+        queue.submit(iter::once(command_encoder.finish()));
+
+        assert_eq!(storage.capacity(), 3);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [7, 8, 9]);
+    }
+
+    #[test]
+    fn frames_alternate_between_buffers() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<u32>::with_capacity(&device, 2);
+        storage.set_buffer_count(&device, 2);
+        assert_eq!(storage.memory_usage(), 16);
+
+        storage.set_new_data(&device, &queue, &[1, 2]);
+        storage.advance_frame();
+        storage.set_new_data(&device, &queue, &[3, 4, 5]);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [3, 4, 5]);
+
+        // the first buffer kept its own data and capacity
+        storage.advance_frame();
+        storage.set_new_data(&device, &queue, &[6, 7]);
+        assert_eq!(storage.capacity(), 2);
+        assert_eq!(storage.read_back(&device, &queue).unwrap(), [6, 7]);
+    }
+
+    #[test]
+    fn data_past_the_buffer_size_limit_is_dropped() {
+        let limits = Limits {
+            max_buffer_size: 64,
+            ..Limits::downlevel_defaults()
+        };
+        let Some((device, queue)) = setup::test_device_with(Features::empty(), &limits) else {
+            return;
+        };
+
+        let mut storage = DynamicStorageBuffer::<[u32; 4]>::with_capacity(&device, 100);
+        assert_eq!((storage.capacity(), storage.max_capacity()), (4, 4));
+        storage.set_new_data(&device, &queue, &[[1; 4]; 3]);
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        storage.extend(&device, &queue, &mut command_encoder, &[[2; 4]; 2]);
+        queue.submit(iter::once(command_encoder.finish()));
+        assert_eq!(
+            storage.read_back(&device, &queue).unwrap(),
+            [[1; 4], [1; 4], [1; 4], [2; 4]]
+        );
+        storage.set_new_data(&device, &queue, &[[3; 4]; 5]);
+        assert_eq!(storage.len(), 4);
     }
 }
@@ -0,0 +1,151 @@
+use super::RenderStage;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use wgpu::{
+    BufferAddress, BufferAsyncError, CreateSurfaceError, Features, RequestDeviceError, SurfaceError,
+};
+use winit::error::{EventLoopError, OsError};
+
+/// Everything that can stop [`run`](crate::run) from starting or keep it from continuing.
+#[derive(Debug)]
+pub enum RenderError {
+    EventLoop(EventLoopError),
+    WindowCreation(OsError),
+    SurfaceCreation(CreateSurfaceError),
+    /// No adapter is compatible with the window surface.
+    NoAdapter,
+    /// The surface supports no sRGB texture format.
+    NoSrgbSurfaceFormat,
+    /// The adapter lacks these requested features.
+    UnsupportedFeatures(Features),
+    /// A requested limit is beyond what the adapter allows.
+    UnsupportedLimit {
+        name: &'static str,
+        requested: u64,
+        allowed: u64,
+    },
+    DeviceRequest(RequestDeviceError),
+    /// The surface failed in a way that can't be recovered by skipping a frame.
+    Surface(SurfaceError),
+    /// The device was lost while rendering, with the driver's message.
+    DeviceLost(String),
+    /// Reading rendered pixels back from the GPU failed.
+    BufferMap(BufferAsyncError),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EventLoop(err) => write!(f, "event loop error: {err}"),
+            Self::WindowCreation(err) => write!(f, "failed to create window: {err}"),
+            Self::SurfaceCreation(err) => write!(f, "failed to create surface: {err}"),
+            Self::NoAdapter => write!(f, "no suitable graphics adapter found"),
+            Self::NoSrgbSurfaceFormat => write!(f, "surface supports no sRGB format"),
+            Self::UnsupportedFeatures(features) => {
+                write!(f, "adapter doesn't support features {features:?}")
+            }
+            Self::UnsupportedLimit {
+                name,
+                requested,
+                allowed,
+            } => write!(
+                f,
+                "adapter limit {name} is {allowed}, but {requested} was requested"
+            ),
+            Self::DeviceRequest(err) => write!(f, "failed to request device: {err}"),
+            Self::Surface(err) => write!(f, "surface error: {err}"),
+            Self::DeviceLost(message) => write!(f, "device lost: {message}"),
+            Self::BufferMap(err) => write!(f, "failed to map buffer: {err}"),
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::EventLoop(err) => Some(err),
+            Self::WindowCreation(err) => Some(err),
+            Self::SurfaceCreation(err) => Some(err),
+            Self::DeviceRequest(err) => Some(err),
+            Self::Surface(err) => Some(err),
+            Self::BufferMap(err) => Some(err),
+            Self::NoAdapter
+            | Self::NoSrgbSurfaceFormat
+            | Self::UnsupportedFeatures(_)
+            | Self::UnsupportedLimit { .. }
+            | Self::DeviceLost(_) => None,
+        }
+    }
+}
+
+/// Something that went wrong on the GPU side of a frame, passed to
+/// [`Renderable::on_gpu_error`](crate::Renderable::on_gpu_error). The frame goes on being
+/// drawn, possibly missing whatever failed.
+#[derive(Debug)]
+pub struct GpuError {
+    /// The [`WindowAccess::frame_index`](crate::WindowAccess::frame_index) of the frame.
+    pub frame: u64,
+    /// What the frame was doing, e.g. `uploading 5000000 RectsAndCircles instances
+    /// (160000000 bytes)`.
+    pub context: String,
+    pub kind: GpuErrorKind,
+}
+
+#[derive(Debug)]
+pub enum GpuErrorKind {
+    /// A validation or out-of-memory error reported by wgpu.
+    Wgpu(wgpu::Error),
+    /// More instances were submitted to `stage` than the largest buffer the device allows
+    /// holds. The ones past `max_bytes` aren't drawn.
+    TooManyInstances {
+        stage: RenderStage,
+        bytes: BufferAddress,
+        max_bytes: BufferAddress,
+    },
+}
+
+impl Display for GpuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {}, {}: ", self.frame, self.context)?;
+        match &self.kind {
+            GpuErrorKind::Wgpu(err) => write!(f, "{err}"),
+            GpuErrorKind::TooManyInstances {
+                stage,
+                bytes,
+                max_bytes,
+            } => write!(
+                f,
+                "{bytes} bytes of {stage:?} instances exceed the {max_bytes} byte buffer limit"
+            ),
+        }
+    }
+}
+
+impl Error for GpuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            GpuErrorKind::Wgpu(err) => Some(err),
+            GpuErrorKind::TooManyInstances { .. } => None,
+        }
+    }
+}
+
+macro_rules! render_error_from {
+    ($($variant: ident ($err: ty))*) => {
+        $(
+        impl From<$err> for RenderError {
+            fn from(err: $err) -> Self {
+                Self::$variant(err)
+            }
+        }
+        )*
+    };
+}
+
+render_error_from! {
+    EventLoop (EventLoopError)
+    WindowCreation (OsError)
+    SurfaceCreation (CreateSurfaceError)
+    DeviceRequest (RequestDeviceError)
+    Surface (SurfaceError)
+}
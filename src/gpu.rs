@@ -0,0 +1,47 @@
+use wgpu::{AdapterInfo, Device, Queue, TextureFormat};
+
+/// The device the crate renders with, for creating and updating GPU resources of one's own.
+/// See [`Renderable::init`](crate::Renderable::init) and
+/// [`WindowAccess::gpu`](crate::WindowAccess::gpu).
+#[derive(Copy, Clone)]
+pub struct GpuContext<'a> {
+    device: &'a Device,
+    queue: &'a Queue,
+    surface_format: TextureFormat,
+    adapter_info: &'a AdapterInfo,
+}
+
+impl<'a> GpuContext<'a> {
+    pub(crate) fn new(
+        device: &'a Device,
+        queue: &'a Queue,
+        surface_format: TextureFormat,
+        adapter_info: &'a AdapterInfo,
+    ) -> Self {
+        Self {
+            device,
+            queue,
+            surface_format,
+            adapter_info,
+        }
+    }
+
+    pub fn device(&self) -> &'a Device {
+        self.device
+    }
+
+    pub fn queue(&self) -> &'a Queue {
+        self.queue
+    }
+
+    /// The format of the surface. With
+    /// [`WindowConfig::post_effects`](crate::WindowConfig::post_effects), the stages draw into
+    /// an `Rgba16Float` texture instead.
+    pub fn surface_format(&self) -> TextureFormat {
+        self.surface_format
+    }
+
+    pub fn adapter_info(&self) -> &'a AdapterInfo {
+        self.adapter_info
+    }
+}
@@ -0,0 +1,69 @@
+use super::rect::Rect;
+use super::vectors::{IVec2, Vector2};
+
+/// Maps integer cells to world space, for tile maps and cellular automata. Cell `(0, 0)`
+/// has its minimum corner at `origin`, and cells extend towards positive x and y.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridMapper {
+    pub origin: Vector2,
+    pub cell_size: Vector2,
+}
+
+impl GridMapper {
+    pub const fn new(origin: Vector2, cell_size: Vector2) -> Self {
+        Self { origin, cell_size }
+    }
+
+    /// The center of `cell`.
+    pub fn cell_to_world(&self, cell: IVec2) -> Vector2 {
+        self.origin + (Vector2::from(cell) + Vector2::same(0.5)) * self.cell_size
+    }
+
+    /// The cell containing `world_pos`, rounding down on both axes, so positions just below
+    /// the origin land in cell -1 rather than 0.
+    pub fn world_to_cell(&self, world_pos: Vector2) -> IVec2 {
+        IVec2::from_floor((world_pos - self.origin) / self.cell_size)
+    }
+
+    /// The area covered by `cell`.
+    pub fn cell_rect(&self, cell: IVec2) -> Rect {
+        let min = self.origin + Vector2::from(cell) * self.cell_size;
+        Rect::from_corners(min, min + self.cell_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GridMapper;
+    use crate::{IVec2, Vector2};
+
+    #[test]
+    fn round_trips() {
+        let grid = GridMapper::new(Vector2::new(1.0, -2.0), Vector2::new(0.5, 2.0));
+        for x in -3..3 {
+            for y in -3..3 {
+                let cell = IVec2::new(x, y);
+                assert_eq!(grid.world_to_cell(grid.cell_to_world(cell)), cell);
+                assert_eq!(grid.cell_rect(cell).center(), grid.cell_to_world(cell));
+            }
+        }
+    }
+
+    #[test]
+    fn negative_positions_floor() {
+        let grid = GridMapper::new(Vector2::ZERO, Vector2::same(1.0));
+        assert_eq!(
+            grid.world_to_cell(Vector2::new(-0.25, 0.25)),
+            IVec2::new(-1, 0)
+        );
+        assert_eq!(
+            grid.world_to_cell(Vector2::new(-1.0, -1.5)),
+            IVec2::new(-1, -2)
+        );
+        assert_eq!(
+            grid.world_to_cell(Vector2::new(0.0, 0.99)),
+            IVec2::new(0, 0)
+        );
+    }
+}
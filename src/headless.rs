@@ -0,0 +1,692 @@
+use super::camera::{Camera, CameraTransforms};
+use super::compute::ComputeRunner;
+use super::config::{StageShaders, WindowConfig};
+use super::dynamic_storage::INITIAL_CAPACITY;
+use super::error::RenderError;
+use super::lines::LineAccumulation;
+use super::post::{PostChain, PostEffect, HDR_FORMAT};
+use super::setup;
+use super::stages::StagePipelines;
+use super::vectors::DVector2;
+use super::{GpuContext, RenderController, Renderable};
+use std::iter;
+use std::sync::mpsc;
+use wgpu::{
+    AdapterInfo, Backends, BufferDescriptor, BufferUsages, CommandEncoder,
+    CommandEncoderDescriptor, Device, Extent3d, Features, ImageCopyBuffer, ImageCopyTexture,
+    ImageDataLayout, InstanceDescriptor, Limits, LoadOp, Maintain, MapMode, Operations, Origin3d,
+    PowerPreference, Queue, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use winit::dpi::PhysicalSize;
+
+/// The format frames are rendered to offscreen, matching the sRGB surfaces used on screen.
+pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Renders one frame of `application` offscreen, without opening a window, and returns its
+/// pixels as tightly packed RGBA8 rows, top to bottom.
+pub fn render_to_image<A: Renderable>(
+    application: &mut A,
+    width: u32,
+    height: u32,
+    camera: Camera,
+) -> Result<Vec<u8>, RenderError> {
+    render_to_image_at(application, width, height, camera, DVector2::ZERO)
+}
+
+/// Like [`render_to_image`], with `camera.target` relative to `origin` in double precision,
+/// for [`Line64`](crate::Line64) and [`RectOrCircle64`](crate::RectOrCircle64).
+pub fn render_to_image_at<A: Renderable>(
+    application: &mut A,
+    width: u32,
+    height: u32,
+    camera: Camera,
+    origin: DVector2,
+) -> Result<Vec<u8>, RenderError> {
+    let mut target = HeadlessTarget::for_application(application, width, height)?;
+    target.camera_transforms.origin = origin;
+    target.set_camera(camera);
+    target.render(application)
+}
+
+/// The device and pipelines behind offscreen rendering, kept to render several frames,
+/// where [`render_to_image`] sets them up for each image.
+pub struct HeadlessTarget {
+    device: Device,
+    queue: Queue,
+    adapter_info: AdapterInfo,
+    camera_transforms: CameraTransforms,
+    stages: StagePipelines,
+    post_chain: Option<PostChain>,
+    compute_stages: Vec<ComputeRunner>,
+    /// What the last frame submitted, kept for [`Self::redraw`] and the stage priorities.
+    render_controller: RenderController,
+    color_target: Texture,
+    size: PhysicalSize<u32>,
+}
+
+impl HeadlessTarget {
+    /// Sets up rendering `application` at `width` by `height` pixels, calling its
+    /// [`Renderable::init`] and running its [`Renderable::compute_stages`], with the default
+    /// camera.
+    pub fn for_application<A: Renderable>(
+        application: &mut A,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RenderError> {
+        Self::with_options(
+            application,
+            PhysicalSize::new(width, height),
+            false,
+            Vec::new(),
+        )
+    }
+
+    /// Like [`Self::for_application`], uploading instances like
+    /// [`WindowConfig::compact_colors`](crate::WindowConfig::compact_colors) does.
+    pub fn for_application_compact<A: Renderable>(
+        application: &mut A,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RenderError> {
+        Self::with_options(
+            application,
+            PhysicalSize::new(width, height),
+            true,
+            Vec::new(),
+        )
+    }
+
+    /// Like [`Self::for_application`], with the [`WindowConfig::compact_colors`] and
+    /// [`WindowConfig::post_effects`] of `config`. The rest of it only concerns windows.
+    pub fn with_config<A: Renderable>(
+        application: &mut A,
+        width: u32,
+        height: u32,
+        config: &WindowConfig,
+    ) -> Result<Self, RenderError> {
+        let size = PhysicalSize::new(width, height);
+        let effects = config.post_effects.clone();
+        Self::with_options(application, size, config.compact_colors, effects)
+    }
+
+    fn with_options<A: Renderable>(
+        application: &mut A,
+        size: PhysicalSize<u32>,
+        compact_colors: bool,
+        post_effects: Vec<PostEffect>,
+    ) -> Result<Self, RenderError> {
+        let mut target = Self::new::<A>(size, compact_colors, post_effects)?;
+        application.init(&GpuContext::new(
+            &target.device,
+            &target.queue,
+            HEADLESS_FORMAT,
+            &target.adapter_info,
+        ));
+        target.compute_stages = (application.compute_stages().into_iter())
+            .map(|stage| ComputeRunner::new(&target.device, stage, &mut target.stages, None))
+            .collect();
+        Ok(target)
+    }
+
+    fn new<A: Renderable>(
+        size: PhysicalSize<u32>,
+        compact_colors: bool,
+        post_effects: Vec<PostEffect>,
+    ) -> Result<Self, RenderError> {
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+
+        // any backend will do offscreen, including GL where it's all there is
+        let instance = wgpu::Instance::new(InstanceDescriptor {
+            backends: Backends::all(),
+            ..Default::default()
+        });
+        let (adapter, _) =
+            setup::request_adapter(&instance, PowerPreference::HighPerformance, true, None)?;
+        let (device, queue) =
+            setup::request_device(&adapter, Features::empty(), &Limits::default(), None)?;
+
+        let camera_transforms = CameraTransforms::new(&device, size);
+        let color_target = Self::create_color_target(&device, size);
+        let post_chain = (!post_effects.is_empty())
+            .then(|| PostChain::new(&device, &queue, HEADLESS_FORMAT, size, post_effects));
+
+        let mut stages = StagePipelines::new(
+            &device,
+            match post_chain {
+                Some(_) => HDR_FORMAT,
+                None => HEADLESS_FORMAT,
+            },
+            size,
+            A::USE_LINE_ALPHA,
+            LineAccumulation::detect(&adapter, &device),
+            1,
+            INITIAL_CAPACITY,
+            INITIAL_CAPACITY,
+            compact_colors,
+            &StageShaders::default(),
+            None,
+        );
+        stages.line_render.set_blend_mode(A::LINE_BLEND_MODE);
+        stages
+            .rect_circle_render
+            .set_feather(&queue, A::SHAPE_FEATHER);
+
+        Ok(Self {
+            device,
+            queue,
+            adapter_info: adapter.get_info(),
+            camera_transforms,
+            stages,
+            post_chain,
+            compute_stages: Vec::new(),
+            render_controller: RenderController::new(),
+            color_target,
+            size,
+        })
+    }
+
+    fn create_color_target(device: &Device, size: PhysicalSize<u32>) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("headless color target"),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HEADLESS_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    #[cfg(test)]
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        self.stages.resize(&self.device, &self.queue, self.size);
+        if let Some(post_chain) = &mut self.post_chain {
+            post_chain.resize(&self.device, self.size);
+        }
+        self.color_target = Self::create_color_target(&self.device, self.size);
+        self.camera_transforms
+            .update_aspect_ratio(&self.queue, self.size);
+    }
+
+    /// Creates the post effect chain a target started out without, the way a window does.
+    #[cfg(test)]
+    fn start_post_chain(&mut self, effects: Vec<PostEffect>) {
+        (self.stages).set_texture_format(&self.device, HDR_FORMAT, None);
+        self.post_chain = Some(PostChain::new(
+            &self.device,
+            &self.queue,
+            HEADLESS_FORMAT,
+            self.size,
+            effects,
+        ));
+    }
+
+    /// Shows `camera` in the frames rendered from now on.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera_transforms.set_camera(camera);
+        self.camera_transforms.update_camera(&self.queue);
+    }
+
+    /// Encodes drawing what [`Self::render_controller`] holds into the color target.
+    fn encode_frame(&mut self, command_encoder: &mut CommandEncoder) {
+        let device = &self.device;
+        let view = self.color_target.create_view(&Default::default());
+        self.stages.pre_render(device, command_encoder);
+        for compute_stage in &self.compute_stages {
+            compute_stage.run(command_encoder, &self.stages);
+        }
+        self.stages
+            .cull(device, command_encoder, &self.camera_transforms);
+        {
+            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("headless render pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: self
+                        .post_chain
+                        .as_ref()
+                        .map_or(&view, PostChain::scene_view),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(wgpu::Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.stages.render(
+                &mut render_pass,
+                &self.render_controller,
+                &self.camera_transforms,
+                None,
+            );
+        }
+        if let Some(post_chain) = &self.post_chain {
+            post_chain.run(device, command_encoder, &view);
+        }
+    }
+
+    /// Draws what the last [`Self::render`] submitted again, without uploading it or reading
+    /// the frame back, and waits for the GPU to finish. Times encoding, submitting and
+    /// drawing on their own.
+    pub fn redraw(&mut self) {
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("headless encoder"),
+            });
+        self.encode_frame(&mut command_encoder);
+        self.queue.submit(iter::once(command_encoder.finish()));
+        self.stages.after_submit(&self.device);
+        self.device.poll(Maintain::Wait);
+    }
+
+    /// Renders a frame of `application` and reads it back as tightly packed RGBA8 rows.
+    pub fn render<A: Renderable>(&mut self, application: &mut A) -> Result<Vec<u8>, RenderError> {
+        let render_controller = &mut self.render_controller;
+        render_controller.clear();
+        application.render(render_controller);
+        render_controller.sort_stages();
+        render_controller.resolve_origin(self.camera_transforms.origin);
+        let (device, queue) = (&self.device, &self.queue);
+        self.stages.upload(device, queue, render_controller);
+        (self.stages).prepare_grid(queue, render_controller, &self.camera_transforms);
+
+        let mut command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("headless encoder"),
+        });
+        self.encode_frame(&mut command_encoder);
+
+        let (device, queue, size) = (&self.device, &self.queue, self.size);
+        let texture = &self.color_target;
+        let unpadded_bytes_per_row = size.width * 4;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("headless readback"),
+            size: (padded_bytes_per_row * size.height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        command_encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &readback,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(iter::once(command_encoder.finish()));
+        self.stages.after_submit(device);
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .expect("map_async callback dropped")
+            .map_err(RenderError::BufferMap)?;
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+        readback.unmap();
+
+        Ok(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeadlessTarget;
+    use crate::setup::or_skip;
+    use crate::{
+        Camera, Color, ComputeStage, DVector2, Line, PostEffect, RenderController, RenderStage,
+        Renderable, Vector2,
+    };
+    use winit::dpi::PhysicalSize;
+
+    /// A translucent line accumulating across the middle of the target, or nothing.
+    struct Accumulating {
+        draw: bool,
+    }
+
+    impl Renderable for Accumulating {
+        const USE_LINE_ALPHA: bool = true;
+
+        fn render(&mut self, render_controller: &mut RenderController) {
+            render_controller.add_stage(RenderStage::Line);
+            if self.draw {
+                render_controller.add_line(Line::new(
+                    Vector2::new(-2.0, 0.01),
+                    Vector2::new(2.0, 0.01),
+                    Color::RED.with_alpha(0.5),
+                ));
+            }
+        }
+    }
+
+    /// A square on screen and two well off it.
+    struct Scattered;
+
+    impl Renderable for Scattered {
+        fn render(&mut self, render_controller: &mut RenderController) {
+            render_controller.add_stage(RenderStage::RectsAndCircles);
+            for x in [-10.0, 0.0, 10.0] {
+                render_controller.add_rect_or_circle(crate::RectOrCircle::rectangle(
+                    Vector2::new(x, 0.0),
+                    Vector2::same(0.5),
+                    Color::RED,
+                ));
+            }
+        }
+    }
+
+    /// Translucent, bordered and glowing shapes of every kind.
+    struct Mixed;
+
+    impl Renderable for Mixed {
+        fn render(&mut self, render_controller: &mut RenderController) {
+            render_controller.add_stage(RenderStage::RectsAndCircles);
+            render_controller.add_stage(RenderStage::Line);
+            render_controller.add_rect_or_circle(crate::RectOrCircle::rectangle_with_border(
+                Vector2::new(-0.3, 0.2),
+                Vector2::new(0.5, 0.4),
+                Color::ORANGE.with_alpha(0.6),
+                Color::CYAN,
+                0.1,
+            ));
+            render_controller.add_rect_or_circle(crate::RectOrCircle::circle(
+                Vector2::new(0.3, -0.2),
+                0.4,
+                Color::from_hex(0x3a7bd5).with_alpha(0.8),
+            ));
+            render_controller.add_line(Line::new(
+                Vector2::same(-0.9),
+                Vector2::same(0.9),
+                Color::GREEN.with_alpha(0.5),
+            ));
+            render_controller.add_line(
+                Line::new(
+                    Vector2::new(-0.9, 0.5),
+                    Vector2::new(0.9, 0.5),
+                    Color::WHITE,
+                )
+                .with_glow(0.1),
+            );
+        }
+    }
+
+    #[test]
+    fn compact_colors_render_like_full_ones() {
+        let size = PhysicalSize::new(32, 32);
+        let Some(mut full) = or_skip(HeadlessTarget::new::<Mixed>(size, false, Vec::new())) else {
+            return;
+        };
+        let mut compact = HeadlessTarget::new::<Mixed>(size, true, Vec::new()).unwrap();
+        assert_eq!(compact.stages.instance_size(RenderStage::Line), 32);
+        assert_eq!(
+            compact.stages.instance_size(RenderStage::RectsAndCircles),
+            32
+        );
+
+        let full = full.render(&mut Mixed).unwrap();
+        let compact = compact.render(&mut Mixed).unwrap();
+        assert!(full.iter().any(|&channel| channel > 0));
+        for (index, (a, b)) in full.iter().zip(&compact).enumerate() {
+            // translucent colors lose some precision to their alpha byte
+            assert!(a.abs_diff(*b) <= 2, "channel {index}: {a} != {b}");
+        }
+    }
+
+    #[test]
+    fn post_effects_added_later_render_like_ones_from_the_start() {
+        let size = PhysicalSize::new(32, 32);
+        let effects = vec![PostEffect::Exposure {
+            exposure: 0.5,
+            gamma: 1.0,
+        }];
+        let Some(mut later) = or_skip(HeadlessTarget::new::<Mixed>(size, false, Vec::new())) else {
+            return;
+        };
+        let mut from_start = HeadlessTarget::new::<Mixed>(size, false, effects.clone()).unwrap();
+
+        let plain = later.render(&mut Mixed).unwrap();
+        later.start_post_chain(effects);
+        let later = later.render(&mut Mixed).unwrap();
+        assert_ne!(later, plain);
+        assert_eq!(later, from_start.render(&mut Mixed).unwrap());
+    }
+
+    fn reddest(image: &[u8]) -> u8 {
+        image.chunks_exact(4).map(|pixel| pixel[0]).max().unwrap()
+    }
+
+    #[test]
+    fn accumulation_does_not_bleed_between_frames() {
+        let Some(mut target) = or_skip(HeadlessTarget::new::<Accumulating>(
+            PhysicalSize::new(16, 16),
+            false,
+            Vec::new(),
+        )) else {
+            return;
+        };
+        let (mut lines, mut empty) = (Accumulating { draw: true }, Accumulating { draw: false });
+
+        let first = reddest(&target.render(&mut lines).unwrap());
+        assert!(first > 0);
+        assert_eq!(reddest(&target.render(&mut lines).unwrap()), first);
+
+        // a frame without lines skips the clear, which the next frame with lines still needs
+        assert_eq!(reddest(&target.render(&mut empty).unwrap()), 0);
+        assert_eq!(reddest(&target.render(&mut lines).unwrap()), first);
+
+        target.resize(PhysicalSize::new(24, 20));
+        assert_eq!(reddest(&target.render(&mut lines).unwrap()), first);
+        assert_eq!(reddest(&target.render(&mut lines).unwrap()), first);
+    }
+
+    #[test]
+    fn culling_draws_only_what_is_in_view() {
+        for compact_colors in [false, true] {
+            let size = PhysicalSize::new(16, 16);
+            let Some(mut target) = or_skip(HeadlessTarget::new::<Scattered>(
+                size,
+                compact_colors,
+                Vec::new(),
+            )) else {
+                return;
+            };
+            target.stages.enable_indirect(&target.device);
+            target.stages.enable_culling(&target.device);
+            let args = target.stages.indirect_args(RenderStage::RectsAndCircles);
+            target
+                .queue
+                .write_buffer(args.unwrap(), 4, &3u32.to_ne_bytes());
+
+            let image = target.render(&mut Scattered).unwrap();
+            let center = 4 * (8 * 16 + 8);
+            assert_eq!(image[center..center + 4], [255, 0, 0, 255]);
+            assert_eq!(image[..4], [0, 0, 0, 255]);
+
+            // the count arrives once the readback finishes mapping
+            target.device.poll(wgpu::Maintain::Wait);
+            target.stages.after_submit(&target.device);
+            assert_eq!(target.stages.take_stats().culled_survivors, Some(1));
+        }
+    }
+
+    #[test]
+    fn gpu_errors_are_caught_with_their_context() {
+        let Some(target) = or_skip(HeadlessTarget::new::<Scattered>(
+            PhysicalSize::new(4, 4),
+            false,
+            Vec::new(),
+        )) else {
+            return;
+        };
+        let mut errors = Vec::new();
+        let context = || "creating a buffer".to_string();
+        crate::util::catch_gpu_errors(&target.device, &mut errors, 7, context, || {
+            // mappable storage buffers need a native-only feature
+            target.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 16,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        });
+
+        assert_eq!(errors.len(), 1);
+        let message = errors[0].to_string();
+        assert!(
+            message.starts_with("frame 7, creating a buffer: "),
+            "{message}"
+        );
+    }
+
+    /// A placeholder circle, which the compute stage moves to world position (5, 5) and paints
+    /// red.
+    struct ComputeMoved;
+
+    impl Renderable for ComputeMoved {
+        fn compute_stages(&self) -> Vec<ComputeStage> {
+            const SHADER: &str = "
+                struct RectOrCircle {
+                    center: vec2<f32>,
+                    size: vec2<f32>,
+                    color: vec4<f32>,
+                    border_color: vec4<f32>,
+                    border_width: f32,
+                }
+
+                @group(0) @binding(0)
+                var<storage, read_write> shapes: array<RectOrCircle>;
+
+                @compute @workgroup_size(1)
+                fn cs_main() {
+                    let red = vec4<f32>(1.0, 0.0, 0.0, 1.0);
+                    shapes[0] = RectOrCircle(vec2<f32>(5.0), vec2<f32>(0.5), red, red, 0.0);
+                }";
+            vec![ComputeStage {
+                target: RenderStage::RectsAndCircles,
+                shader: wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+                },
+                entry_point: "cs_main",
+                workgroups: (1, 1, 1),
+                uniform: Vec::new(),
+            }]
+        }
+
+        fn render(&mut self, render_controller: &mut RenderController) {
+            render_controller.add_stage(RenderStage::RectsAndCircles);
+            render_controller.add_rect_or_circle(crate::RectOrCircle::circle(
+                Vector2::ZERO,
+                0.5,
+                Color::BLUE,
+            ));
+        }
+    }
+
+    #[test]
+    fn compute_shapes_line_up_after_moving_the_camera() {
+        let Some(mut target) = or_skip(HeadlessTarget::for_application(&mut ComputeMoved, 16, 16))
+        else {
+            return;
+        };
+        target.set_camera(Camera::new(Vector2::same(5.0), 1.0));
+        assert_eq!(target.camera_transforms.origin, DVector2::ZERO);
+
+        let image = target.render(&mut ComputeMoved).unwrap();
+        let center = 4 * (8 * 16 + 8);
+        assert_eq!(image[center..center + 4], [255, 0, 0, 255]);
+        assert_eq!(image[..4], [0, 0, 0, 255]);
+    }
+
+    /// A red square covering the whole target.
+    #[cfg(feature = "shader-hot-reload")]
+    struct Square;
+
+    #[cfg(feature = "shader-hot-reload")]
+    impl Renderable for Square {
+        fn render(&mut self, render_controller: &mut RenderController) {
+            render_controller.add_stage(RenderStage::RectsAndCircles);
+            render_controller.add_rect_or_circle(crate::RectOrCircle::rectangle(
+                Vector2::ZERO,
+                Vector2::same(2.0),
+                Color::RED,
+            ));
+        }
+    }
+
+    #[cfg(feature = "shader-hot-reload")]
+    #[test]
+    fn reloading_keeps_the_old_pipeline_on_failure() {
+        let Some(mut target) = or_skip(HeadlessTarget::new::<Square>(
+            PhysicalSize::new(4, 4),
+            false,
+            Vec::new(),
+        )) else {
+            return;
+        };
+        let green = include_str!("rect_circle.wgsl").replace(
+            "return mix(inst_data.border_color, inst_data.color, fill) * alpha;",
+            "return vec4<f32>(0.0, 1.0, 0.0, 1.0);",
+        );
+        assert_ne!(green, include_str!("rect_circle.wgsl"));
+        let stage = RenderStage::RectsAndCircles;
+
+        target
+            .stages
+            .reload_shader(&target.device, stage, "green", green, None)
+            .unwrap();
+        assert_eq!(target.render(&mut Square).unwrap()[..4], [0, 255, 0, 255]);
+
+        // a shader that parses but doesn't fit the pipeline layout
+        let unbound = "@group(3) @binding(0) var<uniform> extra: vec4<f32>;
+        @vertex fn vs_main() -> @builtin(position) vec4<f32> { return extra; }
+        @fragment fn fs_main() -> @location(0) vec4<f32> { return extra; }";
+        for broken in ["fn vs_main( {", unbound] {
+            let result = target.stages.reload_shader(
+                &target.device,
+                stage,
+                "broken",
+                broken.to_string(),
+                None,
+            );
+            assert!(result.is_err());
+            assert!(target
+                .stages
+                .shader_errors
+                .iter()
+                .any(|(errored, _)| *errored == stage));
+            assert_eq!(target.render(&mut Square).unwrap()[..4], [0, 255, 0, 255]);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use super::stages::StagePipelines;
+use super::RenderStage;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use wgpu::{Device, PipelineCache};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// WGSL files to load the stage shaders from and reload whenever they change, taking the place
+/// of [`StageShaders`](crate::StageShaders). The same contract applies, and a file that fails
+/// to compile leaves the previous shader in use.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderPaths {
+    pub line: Option<PathBuf>,
+    pub rect_circle: Option<PathBuf>,
+}
+
+struct WatchedShader {
+    stage: RenderStage,
+    path: PathBuf,
+    /// When the file was last modified as of the last poll, `None` before the first.
+    modified: Option<SystemTime>,
+}
+
+/// Polls the files of [`ShaderPaths`] for changes.
+pub struct ShaderWatcher {
+    watched: Vec<WatchedShader>,
+    last_poll: Option<Instant>,
+}
+
+impl ShaderWatcher {
+    pub fn new(paths: &ShaderPaths) -> Self {
+        let watched = [
+            (RenderStage::Line, &paths.line),
+            (RenderStage::RectsAndCircles, &paths.rect_circle),
+        ]
+        .into_iter()
+        .filter_map(|(stage, path)| {
+            Some(WatchedShader {
+                stage,
+                path: path.clone()?,
+                modified: None,
+            })
+        })
+        .collect();
+
+        Self {
+            watched,
+            last_poll: None,
+        }
+    }
+
+    /// Rebuilds the pipelines of the shaders whose files changed, checking at most once per
+    /// second, and on the first call. Returns whether any pipeline changed.
+    pub fn poll(
+        &mut self,
+        device: &Device,
+        stages: &mut StagePipelines,
+        cache: Option<&PipelineCache>,
+    ) -> bool {
+        if self.watched.is_empty()
+            || self
+                .last_poll
+                .is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.last_poll = Some(Instant::now());
+
+        let mut reloaded = false;
+        for watched in &mut self.watched {
+            let modified = match fs::metadata(&watched.path).and_then(|meta| meta.modified()) {
+                Ok(modified) => modified,
+                Err(err) => {
+                    // reported once, rather than every second until the file comes back
+                    if watched.modified.take().is_some() {
+                        log::warn!("can't watch shader {:?}: {err}", watched.path);
+                    }
+                    continue;
+                }
+            };
+            if watched.modified == Some(modified) {
+                continue;
+            }
+            watched.modified = Some(modified);
+
+            let label = watched.path.to_string_lossy();
+            let result = fs::read_to_string(&watched.path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| {
+                    stages.reload_shader(device, watched.stage, &label, source, cache)
+                });
+            match result {
+                Ok(()) => {
+                    log::info!("reloaded {:?} shader from {label}", watched.stage);
+                    reloaded = true;
+                }
+                Err(error) => log::error!(
+                    "failed to reload {:?} shader from {label}, keeping the previous one:\n{error}",
+                    watched.stage
+                ),
+            }
+        }
+        reloaded
+    }
+}
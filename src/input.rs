@@ -0,0 +1,500 @@
+use crate::{Rect, Vector2};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+use winit::keyboard::{KeyCode, ModifiersState};
+
+/// Which modifier keys are held, on either side of the keyboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers(ModifiersState);
+
+impl Modifiers {
+    pub(crate) fn new(state: ModifiersState) -> Self {
+        Self(state)
+    }
+
+    pub fn ctrl(self) -> bool {
+        self.0.control_key()
+    }
+
+    pub fn shift(self) -> bool {
+        self.0.shift_key()
+    }
+
+    pub fn alt(self) -> bool {
+        self.0.alt_key()
+    }
+
+    /// The Windows key, or Command on macOS.
+    pub fn super_key(self) -> bool {
+        self.0.super_key()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A key or mouse button an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Input {
+    Key(KeyCode),
+    Button(MouseButton),
+}
+
+/// An input plus the modifier keys that have to be held along with it. Modifiers that aren't
+/// required may be held too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Binding {
+    pub input: Input,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ctrl: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shift: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub alt: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub super_key: bool,
+}
+
+impl Binding {
+    pub const fn new(input: Input) -> Self {
+        Self {
+            input,
+            ctrl: false,
+            shift: false,
+            alt: false,
+            super_key: false,
+        }
+    }
+
+    pub const fn key(key: KeyCode) -> Self {
+        Self::new(Input::Key(key))
+    }
+
+    pub const fn button(button: MouseButton) -> Self {
+        Self::new(Input::Button(button))
+    }
+
+    pub const fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub const fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub const fn with_alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    pub const fn with_super(mut self) -> Self {
+        self.super_key = true;
+        self
+    }
+
+    /// Whether every required modifier is held.
+    pub fn modifiers_held(&self, modifiers: Modifiers) -> bool {
+        (!self.ctrl || modifiers.ctrl())
+            && (!self.shift || modifiers.shift())
+            && (!self.alt || modifiers.alt())
+            && (!self.super_key || modifiers.super_key())
+    }
+}
+
+impl From<KeyCode> for Binding {
+    fn from(key: KeyCode) -> Self {
+        Self::key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Self::button(button)
+    }
+}
+
+/// Named actions, each bound to any number of keys and buttons, for controls that can be
+/// rebound. An action triggers through any of its bindings, and an input may be bound to
+/// several actions, all of which trigger.
+///
+/// Set the initial map through [`Renderable::input_map`](crate::Renderable::input_map) and
+/// query actions with [`WindowAccess::is_action_down`](crate::WindowAccess::is_action_down)
+/// and friends. With the `serde` feature the map serializes, to persist rebindings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_binding(mut self, action: impl Into<String>, binding: impl Into<Binding>) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Adds `binding` to `action`, unless it is bound already.
+    pub fn bind(&mut self, action: impl Into<String>, binding: impl Into<Binding>) {
+        let bindings = self.actions.entry(action.into()).or_default();
+        let binding = binding.into();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes `binding` from `action`, returning whether it was bound. The action stays
+    /// known even without bindings.
+    pub fn unbind(&mut self, action: &str, binding: impl Into<Binding>) -> bool {
+        let binding = binding.into();
+        let Some(bindings) = self.actions.get_mut(action) else {
+            return false;
+        };
+        let len = bindings.len();
+        bindings.retain(|&bound| bound != binding);
+        bindings.len() != len
+    }
+
+    /// Removes every binding of `action`.
+    pub fn clear_action(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    /// The bindings of `action`, in the order they were added.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every action with its bindings, sorted by name.
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        self.actions
+            .iter()
+            .map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+    }
+
+    /// Whether `is_active` holds for any binding of `action`. Unknown actions never are.
+    pub(crate) fn any_binding(
+        &self,
+        action: &str,
+        is_active: impl FnMut(&Binding) -> bool,
+    ) -> bool {
+        self.bindings(action).iter().any(is_active)
+    }
+}
+
+/// Which keys and buttons are held, and which changed since the last tick saw them.
+#[derive(Default)]
+pub(crate) struct InputTracker {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_repeated: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+}
+
+impl InputTracker {
+    /// Auto-repeats only count as repeats, so a held key is pressed once.
+    pub fn key(&mut self, key: KeyCode, state: ElementState, repeat: bool) {
+        match state {
+            ElementState::Pressed => {
+                self.keys_down.insert(key);
+                match repeat {
+                    true => self.keys_repeated.insert(key),
+                    false => self.keys_pressed.insert(key),
+                };
+            }
+            ElementState::Released => {
+                self.keys_down.remove(&key);
+                self.keys_released.insert(key);
+            }
+        }
+    }
+
+    pub fn button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                self.buttons_down.insert(button);
+                self.buttons_pressed.insert(button);
+            }
+            ElementState::Released => {
+                self.buttons_down.remove(&button);
+                self.buttons_released.insert(button);
+            }
+        }
+    }
+
+    /// Releases everything held, returning what was, e.g. once focus is lost and releases
+    /// won't be delivered anymore.
+    pub fn release_all(&mut self) -> (Vec<KeyCode>, Vec<MouseButton>) {
+        let keys: Vec<_> = self.keys_down.drain().collect();
+        let buttons: Vec<_> = self.buttons_down.drain().collect();
+        self.keys_released.extend(&keys);
+        self.buttons_released.extend(&buttons);
+        (keys, buttons)
+    }
+
+    /// Forgets presses, repeats and releases once a tick has seen them.
+    pub fn clear_edges(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_repeated.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+    }
+
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn is_key_repeated(&self, key: KeyCode) -> bool {
+        self.keys_repeated.contains(&key)
+    }
+
+    pub fn is_key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+}
+
+/// Roughly how many pixels one wheel notch scrolls by.
+const PIXELS_PER_LINE: f32 = 14.0; // isn't 14 like the best font size or something
+
+/// Normalizes a wheel event to lines, the unit mouse wheels report in.
+pub(crate) fn scroll_lines(delta: MouseScrollDelta) -> Vector2 {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => Vector2::new(x, y),
+        MouseScrollDelta::PixelDelta(position) => {
+            Vector2::new(position.x as f32, position.y as f32) / PIXELS_PER_LINE
+        }
+    }
+}
+
+/// A mouse button held down and moved beyond the drag threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drag {
+    pub start_screen: Vector2,
+    pub start_world: Vector2,
+    pub current_screen: Vector2,
+    pub current_world: Vector2,
+}
+
+impl Drag {
+    /// The rectangle dragged out so far, in pixels, e.g. for
+    /// [`RenderController::add_marquee`](crate::RenderController::add_marquee).
+    pub fn screen_rect(&self) -> Rect {
+        Rect::from_corners(self.start_screen, self.current_screen)
+    }
+
+    /// The rectangle dragged out so far, in world space, e.g. for
+    /// [`RenderController::query_rect`](crate::RenderController::query_rect). The camera
+    /// moving during the drag leaves its start where it was in the world.
+    pub fn world_rect(&self) -> Rect {
+        Rect::from_corners(self.start_world, self.current_world)
+    }
+}
+
+struct Press {
+    start_screen: Vector2,
+    start_world: Vector2,
+    dragged: bool,
+    /// Whether this press completed a double click.
+    double: bool,
+}
+
+/// Turns raw button and cursor events into double clicks and drags.
+pub(crate) struct GestureTracker {
+    double_click_interval: Duration,
+    drag_threshold: f32,
+    presses: HashMap<MouseButton, Press>,
+    /// When and where each button last completed a click that didn't drag.
+    last_clicks: HashMap<MouseButton, (Instant, Vector2)>,
+    double_clicked: HashSet<MouseButton>,
+}
+
+impl GestureTracker {
+    pub fn new(double_click_interval: Duration, drag_threshold: f32) -> Self {
+        Self {
+            double_click_interval,
+            drag_threshold,
+            presses: HashMap::new(),
+            last_clicks: HashMap::new(),
+            double_clicked: HashSet::new(),
+        }
+    }
+
+    pub fn press(&mut self, button: MouseButton, screen: Vector2, world: Vector2) {
+        let now = Instant::now();
+        let double = self.last_clicks.remove(&button).is_some_and(|(at, pos)| {
+            now.duration_since(at) <= self.double_click_interval
+                && (screen - pos).length() <= self.drag_threshold
+        });
+        if double {
+            self.double_clicked.insert(button);
+        }
+
+        self.presses.insert(
+            button,
+            Press {
+                start_screen: screen,
+                start_world: world,
+                dragged: false,
+                double,
+            },
+        );
+    }
+
+    pub fn release(&mut self, button: MouseButton, screen: Vector2) {
+        let Some(press) = self.presses.remove(&button) else {
+            return;
+        };
+        // a third click starts over instead of counting as another double click
+        if !press.dragged && !press.double {
+            self.last_clicks.insert(button, (Instant::now(), screen));
+        }
+    }
+
+    pub fn cursor_moved(&mut self, screen: Vector2) {
+        for press in self.presses.values_mut() {
+            if (screen - press.start_screen).length() > self.drag_threshold {
+                press.dragged = true;
+            }
+        }
+    }
+
+    pub fn is_double_clicked(&self, button: MouseButton) -> bool {
+        self.double_clicked.contains(&button)
+    }
+
+    pub fn drag(
+        &self,
+        button: MouseButton,
+        current_screen: Vector2,
+        current_world: Vector2,
+    ) -> Option<Drag> {
+        self.presses
+            .get(&button)
+            .filter(|press| press.dragged)
+            .map(|press| Drag {
+                start_screen: press.start_screen,
+                start_world: press.start_world,
+                current_screen,
+                current_world,
+            })
+    }
+
+    /// Forgets double clicks once a tick has seen them.
+    pub fn clear_edges(&mut self) {
+        self.double_clicked.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Binding, InputMap, InputTracker, Modifiers};
+    use winit::event::{ElementState, MouseButton};
+    use winit::keyboard::{KeyCode, ModifiersState};
+
+    #[test]
+    fn bindings_are_kept_once_per_action() {
+        let mut map = InputMap::new()
+            .with_binding("pan_up", KeyCode::KeyW)
+            .with_binding("pan_up", KeyCode::ArrowUp)
+            .with_binding("select", MouseButton::Left)
+            // conflicts are allowed
+            .with_binding("select", KeyCode::KeyW);
+        map.bind("pan_up", KeyCode::KeyW);
+        assert_eq!(map.bindings("pan_up").len(), 2);
+        assert_eq!(map.bindings("missing"), []);
+
+        assert!(map.unbind("pan_up", KeyCode::KeyW));
+        assert!(!map.unbind("pan_up", KeyCode::KeyW));
+        assert_eq!(map.bindings("pan_up"), [Binding::key(KeyCode::ArrowUp)]);
+
+        map.clear_action("select");
+        let actions: Vec<_> = map.actions().map(|(action, _)| action).collect();
+        assert_eq!(actions, ["pan_up", "select"]);
+    }
+
+    #[test]
+    fn required_modifiers_have_to_be_held() {
+        let binding = Binding::key(KeyCode::KeyS).with_ctrl();
+        let ctrl_shift = Modifiers::new(ModifiersState::CONTROL | ModifiersState::SHIFT);
+        assert!(binding.modifiers_held(ctrl_shift));
+        assert!(!binding.modifiers_held(Modifiers::new(ModifiersState::SHIFT)));
+        assert!(Binding::key(KeyCode::KeyS).modifiers_held(ctrl_shift));
+    }
+
+    #[test]
+    fn repeats_are_not_presses() {
+        let mut input = InputTracker::default();
+        input.key(KeyCode::KeyA, ElementState::Pressed, false);
+        assert!(input.is_key_pressed(KeyCode::KeyA));
+        assert!(!input.is_key_repeated(KeyCode::KeyA));
+        input.clear_edges();
+
+        input.key(KeyCode::KeyA, ElementState::Pressed, true);
+        assert!(input.is_key_down(KeyCode::KeyA));
+        assert!(!input.is_key_pressed(KeyCode::KeyA));
+        assert!(input.is_key_repeated(KeyCode::KeyA));
+        input.clear_edges();
+        assert!(!input.is_key_repeated(KeyCode::KeyA));
+
+        input.key(KeyCode::KeyA, ElementState::Released, false);
+        assert!(!input.is_key_down(KeyCode::KeyA));
+        assert!(input.is_key_released(KeyCode::KeyA));
+    }
+
+    #[test]
+    fn press_and_release_within_a_tick_both_show() {
+        let mut input = InputTracker::default();
+        input.button(MouseButton::Left, ElementState::Pressed);
+        input.button(MouseButton::Left, ElementState::Released);
+        assert!(input.is_button_pressed(MouseButton::Left));
+        assert!(input.is_button_released(MouseButton::Left));
+        assert!(!input.is_button_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn releasing_everything_reports_what_was_held() {
+        let mut input = InputTracker::default();
+        input.key(KeyCode::KeyW, ElementState::Pressed, false);
+        input.button(MouseButton::Right, ElementState::Pressed);
+        input.clear_edges();
+
+        let (keys, buttons) = input.release_all();
+        assert_eq!(keys, [KeyCode::KeyW]);
+        assert_eq!(buttons, [MouseButton::Right]);
+        assert!(input.is_key_released(KeyCode::KeyW));
+        assert!(input.is_button_released(MouseButton::Right));
+        assert!(!input.is_key_down(KeyCode::KeyW));
+        assert_eq!(input.release_all(), (Vec::new(), Vec::new()));
+    }
+}
@@ -1,39 +1,99 @@
+use bookmarks::CameraTransition;
 use camera::CameraTransforms;
-use lines::LineRenderPipeline;
-use pollster::block_on;
-use rect_circle::RectCircleRenderPipeline;
-use std::collections::{HashSet, VecDeque};
-use std::iter;
-use std::mem::replace;
-use std::time::Instant;
-use wgpu::{
-    include_wgsl, Backends, CommandEncoderDescriptor, CompositeAlphaMode, DeviceDescriptor,
-    Features, InstanceDescriptor, Limits, LoadOp, MemoryHints, Operations, PowerPreference,
-    PresentMode, RenderPassColorAttachment, RenderPassDescriptor, RequestAdapterOptions, StoreOp,
-    SurfaceConfiguration, TextureFormat, TextureUsages, TextureViewDescriptor,
-};
-use winit::dpi::PhysicalSize;
-use winit::event::{Event, KeyEvent, MouseScrollDelta, WindowEvent};
+use capture::FrameSink;
+use input::{GestureTracker, InputTracker};
+use stages::StagePipelines;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use timing::FrameTiming;
+use winit::event::Event;
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::keyboard::PhysicalKey;
-use winit::window::WindowBuilder;
 
-pub use camera::Camera;
-pub use color::Color;
-pub use dynamic_storage::DynamicStorageBuffer;
-pub use lines::Line;
+pub use bookmarks::CameraBookmarks;
+pub use camera::{Camera, CoverMode};
+pub use capture::CapturedFrame;
+#[cfg(feature = "serde")]
+pub use color::color_hex;
+pub use color::{linear_to_srgb, srgb_to_linear, Color, Gradient, ParseColorError, RawColor};
+pub use compute::ComputeStage;
+pub use config::{FullscreenMode, StageShaders, UpdateMode, WindowConfig};
+pub use diagnostics::{diagnostics, AdapterReport, GpuReport};
+pub use dynamic_storage::{DynamicStorageBuffer, GrowthStrategy, ShrinkPolicy, UploadMethod};
+#[cfg(feature = "egui")]
+pub use egui;
+pub use error::{GpuError, GpuErrorKind, RenderError};
+pub use gpu::GpuContext;
+pub use grid::GridMapper;
+pub use headless::{render_to_image, render_to_image_at, HeadlessTarget, HEADLESS_FORMAT};
+#[cfg(feature = "shader-hot-reload")]
+pub use hot_reload::ShaderPaths;
+pub use input::{Binding, Drag, Input, InputMap, Modifiers};
+pub use lines::{Line, LineAccumulation, LineBlendMode, LineGlowUnits, LineTonemap};
+pub use plot::{Axes, AxesStyle, AxisScale};
+pub use post::PostEffect;
+pub use precise::{Line64, RectOrCircle64};
+pub use rect::Rect;
 pub use rect_circle::RectOrCircle;
-#[cfg(feature = "glam")]
+pub use reference_grid::GridStyle;
+pub use renderer::Renderer;
+pub use scene::{Scene, SceneError};
+pub use stats::RenderStats;
+#[cfg(any(feature = "glam", feature = "mint"))]
 pub use vectors::AsVector2;
-pub use vectors::Vector2;
-pub use winit::event::{ElementState, MouseButton};
-pub use winit::keyboard::KeyCode;
+pub use vectors::{DVector2, IVec2, Vector2};
+pub use wgpu::{
+    AdapterInfo, Backends, Buffer, Device, Dx12Compiler, Features, Gles3MinorVersion,
+    InstanceFlags, Limits, PowerPreference, PresentMode, Queue, ShaderModuleDescriptor,
+    TextureFormat,
+};
+pub use winit::dpi::PhysicalSize;
+pub use winit::event::{
+    DeviceEvent, DeviceId, ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch,
+    TouchPhase, WindowEvent,
+};
+pub use winit::keyboard::{Key, KeyCode, NamedKey};
+pub use winit::window::{CursorGrabMode, CursorIcon, Icon, Theme};
 
+mod bookmarks;
 mod camera;
+mod capture;
 mod color;
+mod compact;
+mod compute;
+mod config;
+mod culling;
+mod diagnostics;
 mod dynamic_storage;
+mod error;
+mod gpu;
+mod grid;
+mod headless;
+#[cfg(feature = "shader-hot-reload")]
+mod hot_reload;
+mod input;
 mod lines;
+mod overlay;
+#[cfg(feature = "palette")]
+pub mod palette;
+mod pipeline_cache;
+mod plot;
+mod post;
+mod precise;
+mod profiler;
+mod rect;
 mod rect_circle;
+mod reference_grid;
+mod renderer;
+mod scene;
+mod setup;
+mod stages;
+mod stats;
+mod svg;
+pub mod testgen;
+mod timing;
+#[cfg(feature = "egui")]
+mod ui;
 mod util;
 mod vectors;
 
@@ -41,13 +101,37 @@ mod vectors;
 pub enum RenderStage {
     Line,
     RectsAndCircles,
+    /// A grid over the whole view, styled with [`RenderController::set_grid_style`]. It is
+    /// drawn per pixel rather than from instances, so it costs the same at any zoom.
+    Grid,
+}
+
+impl RenderStage {
+    /// Every stage, each of which a frame can draw once.
+    pub const ALL: [Self; 3] = [Self::Line, Self::RectsAndCircles, Self::Grid];
+}
+
+/// A shape submitted to a [`RenderController`] this frame: the stage it draws in, and its
+/// index among that stage's shapes in submission order, counting the double precision
+/// shapes after the rest, the order they draw in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ShapeRef {
+    pub stage: RenderStage,
+    pub index: usize,
 }
 
 #[derive(Default)]
 pub struct RenderController {
     render_order: Vec<RenderStage>,
+    /// Set through [`Self::set_stage_priority`], kept from frame to frame.
+    stage_priorities: Vec<(RenderStage, i32)>,
     lines: Vec<Line>,
     rects: Vec<RectOrCircle>,
+    lines_64: Vec<Line64>,
+    rects_64: Vec<RectOrCircle64>,
+    grid_style: GridStyle,
+    interpolation_alpha: f32,
+    paused: bool,
 }
 
 impl RenderController {
@@ -59,6 +143,9 @@ impl RenderController {
         self.render_order.clear();
         self.lines.clear();
         self.rects.clear();
+        self.lines_64.clear();
+        self.rects_64.clear();
+        self.grid_style = GridStyle::default();
     }
 
     /// Panics if render stage has already been added.
@@ -76,6 +163,41 @@ impl RenderController {
         }
     }
 
+    /// Where `stage` draws relative to the others, whatever order they were added in: lower
+    /// priorities draw first, so below, and stages of equal priority draw in the order they
+    /// were added. Every built-in stage starts at priority 0, so without priorities stages
+    /// draw in the order they were added. Kept across frames.
+    pub fn set_stage_priority(&mut self, stage: RenderStage, priority: i32) {
+        match self
+            .stage_priorities
+            .iter_mut()
+            .find(|(other, _)| *other == stage)
+        {
+            Some((_, old)) => *old = priority,
+            None => self.stage_priorities.push((stage, priority)),
+        }
+    }
+
+    pub fn stage_priority(&self, stage: RenderStage) -> i32 {
+        let priority = self
+            .stage_priorities
+            .iter()
+            .find(|(other, _)| *other == stage);
+        priority.map_or(0, |&(_, priority)| priority)
+    }
+
+    /// The stages added this frame in the order they draw, by priority.
+    fn sorted_render_order(&self) -> Vec<RenderStage> {
+        let mut render_order = self.render_order.clone();
+        render_order.sort_by_key(|&stage| self.stage_priority(stage));
+        render_order
+    }
+
+    /// Puts the stages in the order they draw, once everything is submitted.
+    fn sort_stages(&mut self) {
+        self.render_order = self.sorted_render_order();
+    }
+
     pub fn add_line(&mut self, line: Line) {
         self.lines.push(line);
     }
@@ -83,74 +205,537 @@ impl RenderController {
     pub fn add_rect_or_circle(&mut self, shape: RectOrCircle) {
         self.rects.push(shape);
     }
+
+    /// Like calling [`Self::add_line`] for each line, reserving room for them all at once.
+    pub fn add_lines(&mut self, lines: impl IntoIterator<Item = Line>) {
+        self.lines.extend(lines);
+    }
+
+    /// Like calling [`Self::add_rect_or_circle`] for each shape, reserving room for them all
+    /// at once.
+    pub fn add_rects_or_circles(&mut self, shapes: impl IntoIterator<Item = RectOrCircle>) {
+        self.rects.extend(shapes);
+    }
+
+    /// How [`RenderStage::Grid`] looks this frame, [`GridStyle::default`] unless set.
+    pub fn set_grid_style(&mut self, style: GridStyle) {
+        self.grid_style = style;
+    }
+
+    /// Adds a line whose endpoints are made relative to the camera in double precision
+    /// before upload. Within the line stage, these draw after the lines from
+    /// [`Self::add_line`].
+    pub fn add_line_64(&mut self, line: Line64) {
+        self.lines_64.push(line);
+    }
+
+    /// Like [`Self::add_line_64`], for rectangles and circles.
+    pub fn add_rect_or_circle_64(&mut self, shape: RectOrCircle64) {
+        self.rects_64.push(shape);
+    }
+
+    /// Moves everything submitted into coordinates relative to `origin`, which the camera
+    /// target is relative to, converting the double precision shapes on the way.
+    fn resolve_origin(&mut self, origin: DVector2) {
+        if origin != DVector2::ZERO {
+            let offset = -origin.as_vector2();
+            for line in &mut self.lines {
+                *line = line.translated(offset);
+            }
+            for shape in &mut self.rects {
+                shape.center += offset;
+            }
+        }
+        let lines_64 = self.lines_64.drain(..).map(|line| line.relative_to(origin));
+        self.lines.extend(lines_64);
+        let rects_64 = self
+            .rects_64
+            .drain(..)
+            .map(|shape| shape.relative_to(origin));
+        self.rects.extend(rects_64);
+    }
+
+    /// How many instances of `stage` were submitted.
+    fn instance_count(&self, stage: RenderStage) -> usize {
+        match stage {
+            RenderStage::Line => self.lines.len(),
+            RenderStage::RectsAndCircles => self.rects.len(),
+            RenderStage::Grid => 0,
+        }
+    }
+
+    /// How far rendering is between the last fixed tick and the next, in `[0, 1)`, for
+    /// interpolating positions. Always `1.0` when [`Renderable::TICK_RATE`] is `None`.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.interpolation_alpha
+    }
+
+    /// Whether `tick` is paused, e.g. to draw an indicator.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The box containing every world space shape submitted this frame, or `None` if nothing
+    /// has been submitted. [`Line::screen`] lines don't count.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        let line_bounds = self
+            .lines
+            .iter()
+            .filter(|line| !line.is_screen_space())
+            .map(Line::bounds);
+        let rect_bounds = self.rects.iter().map(RectOrCircle::bounds);
+        let line_64_bounds = self.lines_64.iter().map(Line64::bounds);
+        let rect_64_bounds = self.rects_64.iter().map(RectOrCircle64::bounds);
+
+        line_bounds
+            .chain(rect_bounds)
+            .chain(line_64_bounds)
+            .chain(rect_64_bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// The world space shapes submitted so far this frame that overlap `world_rect`, lines
+    /// first, e.g. for rubber-band selection with [`Drag::world_rect`]. [`Line::screen`] lines
+    /// are never included.
+    pub fn query_rect(&self, world_rect: Rect) -> Vec<ShapeRef> {
+        let lines_64 = self
+            .lines_64
+            .iter()
+            .map(|line| line.relative_to(DVector2::ZERO));
+        let lines = self.lines.iter().copied().chain(lines_64);
+        let line_hits = lines.map(|line| !line.is_screen_space() && line.intersects(&world_rect));
+        let rects_64 = self
+            .rects_64
+            .iter()
+            .map(|shape| shape.relative_to(DVector2::ZERO));
+        let rects = self.rects.iter().copied().chain(rects_64);
+        let rect_hits = rects.map(|shape| shape.intersects(&world_rect));
+
+        fn refs(
+            stage: RenderStage,
+            hits: impl Iterator<Item = bool>,
+        ) -> impl Iterator<Item = ShapeRef> {
+            let indices = hits.enumerate().filter(|(_, hit)| *hit);
+            indices.map(move |(index, _)| ShapeRef { stage, index })
+        }
+        refs(RenderStage::Line, line_hits)
+            .chain(refs(RenderStage::RectsAndCircles, rect_hits))
+            .collect()
+    }
+
+    /// Outlines `screen_rect` with [`Line::screen`] lines, e.g. the marquee of a
+    /// [`Drag::screen_rect`]. They draw in the line stage, which has to be added.
+    pub fn add_marquee(&mut self, screen_rect: Rect, color: Color) {
+        let Rect { min, max } = screen_rect;
+        let corners = [
+            min,
+            Vector2::new(max.x, min.y),
+            max,
+            Vector2::new(min.x, max.y),
+        ];
+        for (index, &corner) in corners.iter().enumerate() {
+            self.add_line(Line::screen(corner, corners[(index + 1) % 4], color));
+        }
+    }
 }
 
 #[allow(unused_variables)]
 pub trait Renderable {
     const CAMERA_MOVE_SPEED: f32 = 0.01;
     const ZOOM_RATE: f32 = 1.1;
+    /// The least and most zoom reachable by scrolling or through [`WindowAccess::set_camera`]
+    /// and bookmarks.
+    const ZOOM_RANGE: (f32, f32) = (1e-6, 1e6);
     const SHIFT_SPEED_MULT: f32 = 5.0;
 
     const USE_LINE_ALPHA: bool = false;
+    /// The initial [`LineBlendMode`], see [`WindowAccess::set_line_blend_mode`].
+    const LINE_BLEND_MODE: LineBlendMode = LineBlendMode::Alpha;
+    /// How many pixels the edges of rects and circles are smoothed over. `0.0` keeps them
+    /// hard, for pixel art. Changed later with [`WindowAccess::set_shape_feather`].
+    const SHAPE_FEATHER: f32 = 1.0;
+
+    /// Ticks per second. `None` ticks once per frame; `Some` runs `tick` at a fixed rate,
+    /// zero or more times per frame, independently of rendering.
+    const TICK_RATE: Option<f64> = None;
+    /// The most ticks run in one frame when catching up; any further backlog is dropped.
+    const MAX_CATCH_UP_TICKS: u32 = 8;
+
+    /// Pressing this key closes the window, as if [`WindowAccess::request_exit`] was called.
+    const EXIT_KEY: Option<KeyCode> = None;
+    /// Pressing this key pauses or resumes `tick`, like [`WindowAccess::set_paused`].
+    const PAUSE_KEY: Option<KeyCode> = None;
+    /// Pressing this key while paused runs a single tick, like [`WindowAccess::step_once`].
+    const STEP_KEY: Option<KeyCode> = None;
+
+    /// Called once the device exists, before the first frame and before
+    /// [`Self::compute_stages`], to create GPU resources on the device the crate uses.
+    fn init(&mut self, gpu: &GpuContext) {}
 
     fn initial_camera(&self) -> Camera {
         Camera::default()
     }
 
+    /// The actions queried through [`WindowAccess::is_action_down`] and friends. Called once
+    /// at startup; rebind later through [`WindowAccess::set_input_map`].
+    fn input_map(&self) -> InputMap {
+        InputMap::default()
+    }
+
+    /// Compute shaders to run every frame before rendering, in order. Called once at startup;
+    /// they are later referred to by their index in the returned list.
+    fn compute_stages(&self) -> Vec<ComputeStage> {
+        Vec::new()
+    }
+
     fn tick(&mut self, access: &WindowAccess) {}
     fn render(&mut self, render: &mut RenderController);
 
+    /// Every window event, before the crate handles it. Return `true` to consume it, skipping
+    /// the built-in handling (input tracking, camera zoom) and the other callbacks. Resizes,
+    /// scale factor changes and redraw requests are always handled regardless.
+    fn on_raw_event(&mut self, event: &WindowEvent) -> bool {
+        false
+    }
+    /// Like [`Self::on_raw_event`], for device events such as raw mouse motion.
+    fn on_raw_device_event(&mut self, event: &DeviceEvent) -> bool {
+        false
+    }
+
     fn on_key_event(&mut self, key_code: KeyCode, state: ElementState, repeat: bool) {}
+    /// Like [`Self::on_key_event`], but with the key as the keyboard layout and modifiers
+    /// interpret it. Also fires for keys without a [`KeyCode`].
+    fn on_logical_key_event(&mut self, key: &Key, state: ElementState, repeat: bool) {}
+    /// Text produced by a key press, with shift and the keyboard layout applied.
+    fn on_text_input(&mut self, text: &str) {}
+    /// Raw input method events; only sent when [`WindowConfig::ime_allowed`] is set.
+    fn on_ime(&mut self, ime: &Ime) {}
     fn on_mouse_event(&mut self, button: MouseButton, state: ElementState) {}
+    /// Like [`Self::on_mouse_event`], which is called as well, with where the cursor was: in
+    /// physical pixels from the top left, and in world space through the camera at the time
+    /// of the event. Both are `None` for buttons used before the cursor ever moved over the
+    /// window, as its position isn't known yet.
+    fn on_mouse_button(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+        screen: Option<Vector2>,
+        world: Option<Vector2>,
+    ) {
+    }
+    /// Wheel or touchpad scrolling, in lines; positive `y` scrolls up. Return `true` to
+    /// consume the event and skip the built-in zoom.
+    fn on_scroll(&mut self, delta: Vector2, modifiers: Modifiers) -> bool {
+        false
+    }
+    /// Called once the surface and camera fit a new size or scale factor, unless minimized.
+    fn on_resize(&mut self, new_size: PhysicalSize<u32>, scale_factor: f64) {}
+    /// Raw mouse movement, unaffected by acceleration or the window edges. Only sent while the
+    /// cursor is grabbed with [`WindowAccess::set_cursor_grab`].
+    fn on_mouse_delta(&mut self, delta: Vector2) {}
+    /// Called with `true` when the window gains keyboard focus and `false` when it loses it.
+    /// Keys and buttons still held when focus is lost are released first.
+    fn on_focus_changed(&mut self, focused: bool) {}
+    /// Called when the user tries to close the window. Returning `false` keeps it open, e.g. to
+    /// ask for confirmation first and call [`WindowAccess::request_exit`] afterwards.
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+    /// Called with `true` when the cursor enters the window and `false` when it leaves.
+    fn on_cursor_inside_changed(&mut self, inside: bool) {}
+    /// A file was dropped onto the window at `world_pos`. Dropping several files calls this
+    /// once per file, in order.
+    fn on_file_dropped(&mut self, path: &Path, world_pos: Vector2) {}
+    /// A file is being dragged over the window at `world_pos`.
+    fn on_file_hovered(&mut self, path: &Path, world_pos: Vector2) {}
+    /// The files being dragged over the window left it or the drag was cancelled.
+    fn on_file_hover_cancelled(&mut self) {}
+
+    /// Called after a frame whose GPU work went wrong, once per error, instead of wgpu
+    /// panicking. Logs the error by default; rendering goes on either way, unless
+    /// [`WindowConfig::panic_on_gpu_error`] is set.
+    fn on_gpu_error(&mut self, error: GpuError) {
+        log::error!("{error}");
+    }
+
+    /// Builds the egui overlay, once per frame after [`Self::render`]. Clicks, scrolling and
+    /// key presses the UI takes aren't passed on to the other callbacks.
+    #[cfg(feature = "egui")]
+    fn ui(&mut self, context: &egui::Context) {}
 }
 
-pub struct WindowAccess<'a> {
-    keys_down: &'a HashSet<KeyCode>,
-    keys_pressed: &'a HashSet<KeyCode>,
-    keys_released: &'a HashSet<KeyCode>,
+/// Requests made through [`WindowAccess`] during `tick`, applied by the event loop afterwards.
+enum WindowCommand {
+    Exit,
+    SetFullscreen(FullscreenMode),
+    RequestRedraw,
+    SetPaused(bool),
+    StepOnce,
+    SetCursorVisible(bool),
+    SetCursorIcon(CursorIcon),
+    SetCursorGrab(CursorGrabMode),
+    StartRecording(FrameSink),
+    StopRecording,
+    SetComputeUniform(usize, Vec<u8>),
+    SetComputeWorkgroups(usize, (u32, u32, u32)),
+    SetCameraTarget64(DVector2),
+    SetLineAlpha(bool),
+    SetLineBlendMode(LineBlendMode),
+    SetLineGlowUnits(LineGlowUnits),
+    SetLineExposure(f32),
+    SetLineTonemap(LineTonemap),
+    SetShapeFeather(f32),
+    SetPostEffects(Vec<PostEffect>),
+    SetDebugOverlay(bool),
+    SetInputMap(InputMap),
+    SetCamera(Camera),
+    TransitionCamera(Camera, Duration),
+    SaveBookmark(String),
+    SetBookmarks(CameraBookmarks),
+}
 
-    buttons_down: &'a HashSet<MouseButton>,
-    buttons_pressed: &'a HashSet<MouseButton>,
-    buttons_released: &'a HashSet<MouseButton>,
+pub struct WindowAccess<'a> {
+    input: &'a InputTracker,
+    modifiers: Modifiers,
+    gestures: &'a GestureTracker,
+    input_map: &'a InputMap,
 
-    camera: &'a Camera,
+    camera_transforms: &'a CameraTransforms,
+    bookmarks: &'a CameraBookmarks,
     mouse_pos_screen: Vector2,
     mouse_pos_world: Vector2,
+    cursor_inside: bool,
+    focused: bool,
+
+    frame_timing: &'a FrameTiming,
+    delta_seconds: f32,
+
+    window_size: PhysicalSize<u32>,
+    scale_factor: f64,
+    fullscreen_mode: FullscreenMode,
+    surface_format: TextureFormat,
+    device: &'a Device,
+    queue: &'a Queue,
+    adapter_info: &'a AdapterInfo,
+    adapter_features: Features,
+    gpu_report: &'a GpuReport,
+    stages: &'a StagePipelines,
+    render_stats: RenderStats,
+    gpu_stage_times: Option<&'a [(RenderStage, Duration)]>,
+    paused: bool,
+    debug_overlay: bool,
+    /// Frames captured and dropped by the current recording, if any.
+    recording: Option<(u64, u64)>,
+
+    commands: RefCell<Vec<WindowCommand>>,
 }
 
 impl WindowAccess<'_> {
+    /// Closes the window once the current `tick` returns, after which `run` returns normally.
+    pub fn request_exit(&self) {
+        self.commands.borrow_mut().push(WindowCommand::Exit);
+    }
+
     pub fn is_key_down(&self, key: KeyCode) -> bool {
-        self.keys_down.contains(&key)
+        self.input.is_key_down(key)
     }
 
+    /// Whether `key` went down since the last tick. Auto-repeats while it is held don't
+    /// count; see [`Self::is_key_repeated`].
     pub fn is_key_pressed(&self, key: KeyCode) -> bool {
-        self.keys_pressed.contains(&key)
+        self.input.is_key_pressed(key)
+    }
+
+    /// Whether the system auto-repeated `key` since the last tick, e.g. for moving a text
+    /// cursor while an arrow key is held.
+    pub fn is_key_repeated(&self, key: KeyCode) -> bool {
+        self.input.is_key_repeated(key)
     }
 
     pub fn is_key_released(&self, key: KeyCode) -> bool {
-        self.keys_released.contains(&key)
+        self.input.is_key_released(key)
     }
 
     pub fn is_button_down(&self, button: MouseButton) -> bool {
-        self.buttons_down.contains(&button)
+        self.input.is_button_down(button)
     }
 
     pub fn is_button_pressed(&self, button: MouseButton) -> bool {
-        self.buttons_pressed.contains(&button)
+        self.input.is_button_pressed(button)
     }
 
     pub fn is_button_released(&self, button: MouseButton) -> bool {
-        self.buttons_released.contains(&button)
+        self.input.is_button_released(button)
+    }
+
+    /// Whether this button's latest press completed a double click since the last tick.
+    pub fn is_button_double_clicked(&self, button: MouseButton) -> bool {
+        self.gestures.is_double_clicked(button)
+    }
+
+    /// The drag in progress with this button, once it has moved past
+    /// [`WindowConfig::drag_threshold`].
+    pub fn drag_state(&self, button: MouseButton) -> Option<Drag> {
+        self.gestures
+            .drag(button, self.mouse_pos_screen, self.mouse_pos_world)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Whether any binding of `action` in the [`InputMap`] is held, with its modifiers.
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.input_map.any_binding(action, |binding| {
+            binding.modifiers_held(self.modifiers)
+                && match binding.input {
+                    Input::Key(key) => self.input.is_key_down(key),
+                    Input::Button(button) => self.input.is_button_down(button),
+                }
+        })
+    }
+
+    /// Whether any binding of `action` was pressed since the last tick, with its modifiers
+    /// held at the time of the tick.
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.input_map.any_binding(action, |binding| {
+            binding.modifiers_held(self.modifiers)
+                && match binding.input {
+                    Input::Key(key) => self.input.is_key_pressed(key),
+                    Input::Button(button) => self.input.is_button_pressed(button),
+                }
+        })
+    }
+
+    /// Whether any binding of `action` was released since the last tick. Modifiers don't
+    /// matter here, as they are often let go of first.
+    pub fn is_action_released(&self, action: &str) -> bool {
+        self.input_map
+            .any_binding(action, |binding| match binding.input {
+                Input::Key(key) => self.input.is_key_released(key),
+                Input::Button(button) => self.input.is_button_released(button),
+            })
+    }
+
+    /// The current bindings, e.g. to show or save them.
+    pub fn input_map(&self) -> &InputMap {
+        self.input_map
+    }
+
+    /// Replaces the bindings after this tick.
+    pub fn set_input_map(&self, input_map: InputMap) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetInputMap(input_map));
+    }
+
+    /// Whether the cursor is over the window. While it isn't, the mouse positions keep
+    /// their last values inside the window.
+    pub fn is_cursor_inside(&self) -> bool {
+        self.cursor_inside
+    }
+
+    /// Whether the window has keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
     }
 
     pub fn camera_target(&self) -> Vector2 {
-        self.camera.target
+        self.camera_transforms.target()
+    }
+
+    /// The camera target in double precision, see [`WindowConfig::double_precision`].
+    pub fn camera_target_64(&self) -> DVector2 {
+        self.camera_transforms.target_64()
+    }
+
+    /// Moves the camera to `target` once the current `tick` returns, keeping its double
+    /// precision.
+    pub fn set_camera_target_64(&self, target: DVector2) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetCameraTarget64(target));
     }
 
     pub fn camera_zoom(&self) -> f32 {
-        self.camera.zoom
+        self.camera_transforms.camera.zoom
+    }
+
+    /// The camera, with its target in world space rounded to single precision.
+    pub fn camera(&self) -> Camera {
+        self.camera_transforms.world_camera()
+    }
+
+    /// Moves the camera to `camera` after this tick, ending any transition.
+    pub fn set_camera(&self, camera: Camera) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetCamera(camera));
     }
 
+    /// Moves the camera to `camera` over `duration`, starting after this tick, with zoom
+    /// changing geometrically. Moving or zooming the camera with the built-in controls ends
+    /// the transition where it is.
+    pub fn transition_camera(&self, camera: Camera, duration: Duration) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::TransitionCamera(camera, duration));
+    }
+
+    /// Saves the current camera in `slot` of [`Self::bookmarks`] after this tick.
+    pub fn save_bookmark(&self, slot: impl ToString) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SaveBookmark(slot.to_string()));
+    }
+
+    /// Moves the camera to the bookmark in `slot` over `transition`, instantly if it is
+    /// zero. Returns whether there is such a bookmark.
+    pub fn restore_bookmark(&self, slot: impl ToString, transition: Duration) -> bool {
+        let Some(camera) = self.bookmarks.restore(slot) else {
+            return false;
+        };
+        self.transition_camera(camera, transition);
+        true
+    }
+
+    pub fn bookmarks(&self) -> &CameraBookmarks {
+        self.bookmarks
+    }
+
+    /// Replaces the bookmarks after this tick, e.g. with ones loaded from a file.
+    pub fn set_bookmarks(&self, bookmarks: CameraBookmarks) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetBookmarks(bookmarks));
+    }
+
+    /// Number of times the camera uniforms have actually been uploaded to the GPU.
+    pub fn camera_uniform_writes(&self) -> u64 {
+        self.camera_transforms.uniform_writes()
+    }
+
+    /// The column-major homogeneous world → NDC transform used by the shaders.
+    pub fn view_matrix(&self) -> [[f32; 3]; 3] {
+        self.camera_transforms.view_matrix()
+    }
+
+    pub fn inverse_view_matrix(&self) -> [[f32; 3]; 3] {
+        self.camera_transforms.inverse_view_matrix()
+    }
+
+    #[cfg(feature = "glam")]
+    pub fn view_mat3(&self) -> glam::Mat3 {
+        glam::Mat3::from_cols_array_2d(&self.view_matrix())
+    }
+
+    #[cfg(feature = "glam")]
+    pub fn inverse_view_mat3(&self) -> glam::Mat3 {
+        glam::Mat3::from_cols_array_2d(&self.inverse_view_matrix())
+    }
+
+    /// The cursor position in physical pixels from the top left of the window, the same
+    /// units as [`Self::window_size`], so it needs no scale factor correction.
     pub fn mouse_pos_screen(&self) -> Vector2 {
         self.mouse_pos_screen
     }
@@ -158,308 +743,378 @@ impl WindowAccess<'_> {
     pub fn mouse_pos_world(&self) -> Vector2 {
         self.mouse_pos_world
     }
-}
 
-pub fn run<A: Renderable>(mut application: A) {
-    env_logger::init();
+    /// The cell of `grid` under the mouse.
+    pub fn mouse_cell(&self, grid: &GridMapper) -> IVec2 {
+        grid.world_to_cell(self.mouse_pos_world)
+    }
+
+    /// Like [`Self::mouse_pos_world`], in double precision.
+    pub fn mouse_pos_world_64(&self) -> DVector2 {
+        let normalized =
+            CameraTransforms::screen_to_normalize(self.mouse_pos_screen, self.window_size);
+        self.camera_transforms.normalized_to_world_64(normalized)
+    }
+
+    /// Seconds simulated by this tick: the fixed step if [`Renderable::TICK_RATE`] is set,
+    /// otherwise the real time since the last frame, clamped to [`WindowConfig::max_delta`].
+    /// Always the real time in [`UpdateMode::Reactive`].
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta_seconds
+    }
+
+    pub fn time_since_start(&self) -> Duration {
+        self.frame_timing.time_since_start()
+    }
+
+    pub fn frame_index(&self) -> u64 {
+        self.frame_timing.frame_index()
+    }
+
+    /// Frames presented within the last second.
+    pub fn fps(&self) -> u32 {
+        self.frame_timing.fps()
+    }
+
+    /// The window's inner size in physical pixels.
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    pub fn window_size_vec(&self) -> Vector2 {
+        Vector2::new(
+            self.window_size.width as f32,
+            self.window_size.height as f32,
+        )
+    }
+
+    pub fn fullscreen_mode(&self) -> FullscreenMode {
+        self.fullscreen_mode
+    }
+
+    /// Switches fullscreen mode after this tick. The window is resized accordingly.
+    pub fn set_fullscreen(&self, mode: FullscreenMode) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetFullscreen(mode));
+    }
+
+    /// Physical pixels per logical pixel.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// The format of the surface. With [`WindowConfig::post_effects`], the stages draw into
+    /// an `Rgba16Float` texture instead.
+    pub fn surface_format(&self) -> TextureFormat {
+        self.surface_format
+    }
+
+    /// The adapter rendering is happening on, for diagnostics.
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        self.adapter_info
+    }
+
+    /// The adapter, surface format and present mode picked at startup, for bug reports.
+    pub fn gpu_report(&self) -> &GpuReport {
+        self.gpu_report
+    }
+
+    /// The device the crate renders with, e.g. to upload data of one's own during `tick`.
+    pub fn gpu(&self) -> GpuContext<'_> {
+        GpuContext::new(
+            self.device,
+            self.queue,
+            self.surface_format,
+            self.adapter_info,
+        )
+    }
+
+    /// Memory held by the built-in stages and the uploads and draw calls of the last frame
+    /// drawn.
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    /// GPU memory held by the built-in line and shape buffers, in bytes.
+    pub fn instance_memory_usage(&self) -> u64 {
+        self.stages.memory_usage()
+    }
+
+    /// GPU time each stage took in a recent frame, in render order. Results lag a frame or two
+    /// behind. `None` unless [`WindowConfig::gpu_profiling`] is on and the adapter supports
+    /// timestamp queries inside passes, and until the first results arrive.
+    pub fn gpu_stage_times(&self) -> Option<&[(RenderStage, Duration)]> {
+        self.gpu_stage_times
+    }
+
+    /// The buffer holding the indirect draw arguments of `stage` when
+    /// [`WindowConfig::indirect_draws`] is on: `wgpu::util::DrawIndirectArgs` for lines and
+    /// `DrawIndexedIndirectArgs` for rects and circles. Shaders set the instance count, the
+    /// `u32` at byte offset 4, and the first that many instances of the stage's buffer are
+    /// drawn. Shapes submitted in `render` are still uploaded to the start of that buffer.
+    pub fn indirect_args(&self, stage: RenderStage) -> Option<&Buffer> {
+        self.stages.indirect_args(stage)
+    }
+
+    /// Every feature the adapter supports, whether or not it was requested, so optional
+    /// features can be checked before relying on them.
+    pub fn adapter_features(&self) -> Features {
+        self.adapter_features
+    }
+
+    /// Whether [`Renderable::USE_LINE_ALPHA`] and [`Self::set_line_alpha`] take effect on
+    /// this adapter.
+    pub fn line_accumulation(&self) -> LineAccumulation {
+        self.stages.line_render.accumulation()
+    }
+
+    /// Why the latest custom shader for `stage` isn't in use, if it isn't. That's either one
+    /// from [`WindowConfig::shaders`], replaced by the built-in one, or with the
+    /// `shader-hot-reload` feature, a changed file that failed to reload.
+    pub fn shader_error(&self, stage: RenderStage) -> Option<&str> {
+        self.stages
+            .shader_errors
+            .iter()
+            .find(|(errored, _)| *errored == stage)
+            .map(|(_, error)| error.as_str())
+    }
+
+    /// Starts saving every presented frame to `dir` as numbered PNGs, replacing any
+    /// recording in progress.
+    pub fn start_recording(&self, dir: impl Into<PathBuf>) {
+        let sink = capture::png_sink(dir.into());
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::StartRecording(sink));
+    }
+
+    /// Like [`Self::start_recording`], but hands each frame to `callback` on the event loop
+    /// thread instead, e.g. to pipe it into an encoder.
+    pub fn start_recording_with(&self, callback: impl FnMut(CapturedFrame) + 'static) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::StartRecording(Box::new(callback)));
+    }
+
+    /// Hides or shows the OS cursor while it's over the window.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetCursorVisible(visible));
+    }
+
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetCursorIcon(icon));
+    }
+
+    /// Confines or locks the cursor to the window. Not every platform supports every mode;
+    /// failures are logged and leave the grab unchanged.
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetCursorGrab(mode));
+    }
+
+    /// Stops or resumes calling `tick`. Rendering and the built-in camera controls keep running
+    /// while paused.
+    pub fn set_paused(&self, paused: bool) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetPaused(paused));
+    }
+
+    /// Pauses if not paused yet, then runs exactly one more tick on the next frame.
+    pub fn step_once(&self) {
+        self.commands.borrow_mut().push(WindowCommand::StepOnce);
+    }
+
+    /// Shows or hides the built-in overlay with the frame rate, a graph of recent frame times,
+    /// the shapes submitted to each stage and GPU memory use, in the top left corner above
+    /// everything else.
+    pub fn set_debug_overlay(&self, shown: bool) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetDebugOverlay(shown));
+    }
+
+    /// Replaces the [`WindowConfig::post_effects`] after this tick. Effects that run the same
+    /// shader as the one at their place before only have their parameters updated, so
+    /// animating them is cheap. If the config started out without effects, the first ones set
+    /// create the textures they run over and rebuild the stages' pipelines to draw into them.
+    pub fn set_post_effects(&self, effects: Vec<PostEffect>) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetPostEffects(effects));
+    }
+
+    pub fn is_debug_overlay_shown(&self) -> bool {
+        self.debug_overlay
+    }
+
+    /// Whether `tick` is paused; only `true` inside a tick run by [`Self::step_once`].
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Makes a [`UpdateMode::Reactive`] loop update and redraw again right away, e.g. while an
+    /// animation is playing. Has no effect in [`UpdateMode::Continuous`].
+    pub fn request_redraw(&self) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::RequestRedraw);
+    }
+
+    /// Stops recording once every frame still in flight has been delivered.
+    pub fn stop_recording(&self) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::StopRecording);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Frames recorded so far in the current recording, or 0 when not recording.
+    pub fn recorded_frames(&self) -> u64 {
+        self.recording.map_or(0, |(captured, _)| captured)
+    }
 
-    let event_loop = EventLoop::new().unwrap();
+    /// Frames skipped by the current recording because the GPU fell behind.
+    pub fn dropped_recorded_frames(&self) -> u64 {
+        self.recording.map_or(0, |(_, dropped)| dropped)
+    }
+
+    /// Overwrites the start of the uniform buffer of the compute stage at `index` in
+    /// [`Renderable::compute_stages`] before the next frame. `data` must be a multiple of 4
+    /// bytes long; anything beyond the size of the stage's initial uniform is ignored.
+    pub fn set_compute_uniform(&self, index: usize, data: &[u8]) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetComputeUniform(index, data.to_vec()));
+    }
+
+    /// Changes how many workgroups the compute stage at `index` dispatches from the next
+    /// frame on.
+    pub fn set_compute_workgroups(&self, index: usize, workgroups: (u32, u32, u32)) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetComputeWorkgroups(index, workgroups));
+    }
+
+    /// Switches line alpha accumulation on or off from the next frame on, overriding
+    /// [`Renderable::USE_LINE_ALPHA`].
+    pub fn set_line_alpha(&self, use_alpha: bool) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetLineAlpha(use_alpha));
+    }
 
-    let window = WindowBuilder::new()
-        .with_inner_size(PhysicalSize::new(1600, 1000))
-        .build(&event_loop)
-        .unwrap();
+    /// Changes how lines blend with what is beneath them from the next frame on.
+    pub fn set_line_blend_mode(&self, blend_mode: LineBlendMode) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetLineBlendMode(blend_mode));
+    }
+
+    /// Changes what [`Line::with_glow`] radii are measured in from the next frame on.
+    pub fn set_line_glow_units(&self, units: LineGlowUnits) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetLineGlowUnits(units));
+    }
+
+    /// Scales accumulated line colors before tonemapping them, from the next frame on. Lower
+    /// exposures keep dense overlaps from saturating.
+    pub fn set_line_exposure(&self, exposure: f32) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetLineExposure(exposure));
+    }
+
+    /// Changes how accumulated line colors are tonemapped from the next frame on.
+    pub fn set_line_tonemap(&self, tonemap: LineTonemap) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetLineTonemap(tonemap));
+    }
 
+    /// Changes how many pixels shape edges are smoothed over from the next frame on, see
+    /// [`Renderable::SHAPE_FEATHER`].
+    pub fn set_shape_feather(&self, pixels: f32) {
+        self.commands
+            .borrow_mut()
+            .push(WindowCommand::SetShapeFeather(pixels));
+    }
+}
+
+/// Opens a window with the default [`WindowConfig`] and runs `application` in it until it
+/// closes.
+///
+/// # Profiling
+///
+/// With the `profile` feature, each frame's work is split into `tracing` spans: `tick` for
+/// each call to [`Renderable::tick`], `render` for [`Renderable::render`], `upload` for
+/// writing the shapes to the GPU, `pre_render` for the work encoded before the render pass,
+/// `render_pass` for encoding it, then `submit` and `present`. Instance uploads also get a
+/// `set_new_data` span, holding either `write_buffer` or `realloc` depending on whether the
+/// buffer had to grow. These names are stable. Without the feature, nothing is traced.
+///
+/// To look at them in [Tracy](https://github.com/wolfpld/tracy), install `tracing-tracy`'s
+/// layer before running:
+///
+/// ```ignore
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let subscriber = tracing_subscriber::registry().with(tracing_tracy::TracyLayer::default());
+/// tracing::subscriber::set_global_default(subscriber).unwrap();
+/// wgpu_rendering::run(app)?;
+/// ```
+pub fn run<A: Renderable>(application: A) -> Result<(), RenderError> {
+    run_with(WindowConfig::default(), application)
+}
+
+pub fn run_with<A: Renderable>(
+    config: WindowConfig,
+    mut application: A,
+) -> Result<(), RenderError> {
+    if config.init_logger {
+        env_logger::init();
+    }
+
+    let event_loop = EventLoop::new()?;
+    let window = config.window_builder().build(&event_loop)?;
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let instance = wgpu::Instance::new(InstanceDescriptor {
-        backends: Backends::PRIMARY,
-        ..Default::default()
-    });
-
-    let surface = instance.create_surface(&window).unwrap();
-    let adapter = block_on(instance.request_adapter(&RequestAdapterOptions {
-        power_preference: PowerPreference::HighPerformance,
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: true,
-    }))
-    .unwrap();
-
-    let required_features =
-        Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES | Features::CLEAR_TEXTURE;
-    let (device, queue) = block_on(adapter.request_device(
-        &DeviceDescriptor {
-            label: None,
-            required_features,
-            required_limits: Limits::default(),
-            memory_hints: MemoryHints::Performance,
-        },
-        None,
-    ))
-    .unwrap();
-
-    let capability = surface.get_capabilities(&adapter);
-    let texture_format = capability
-        .formats
-        .into_iter()
-        .find(TextureFormat::is_srgb)
-        .unwrap();
-
-    let size = window.inner_size();
-    let mut surface_config = SurfaceConfiguration {
-        usage: TextureUsages::RENDER_ATTACHMENT,
-        format: texture_format,
-        width: size.width,
-        height: size.height,
-        present_mode: PresentMode::AutoVsync,
-        alpha_mode: CompositeAlphaMode::Auto,
-        desired_maximum_frame_latency: 2,
-        view_formats: Vec::new(),
-    };
-
-    surface.configure(&device, &surface_config);
-
-    let mut camera_transforms = CameraTransforms::new(&device, size);
-    camera_transforms.camera = application.initial_camera();
-
-    let rect_circle_data = DynamicStorageBuffer::new(&device);
-    let rect_circle_shader = device.create_shader_module(include_wgsl!("rect_circle.wgsl"));
-    let mut rect_circle_render = RectCircleRenderPipeline::new(
-        &device,
-        rect_circle_data,
-        rect_circle_shader,
-        texture_format,
-    );
-
-    let line_data = DynamicStorageBuffer::new(&device);
-    let line_shader = device.create_shader_module(include_wgsl!("lines.wgsl"));
-    let mut line_render = LineRenderPipeline::new(
-        &device,
-        line_data,
-        line_shader,
-        texture_format,
-        size,
-        A::USE_LINE_ALPHA,
-    );
-
-    let mut frame_moments = VecDeque::new();
-    let mut keys_down = HashSet::new();
-    let mut keys_pressed = HashSet::new();
-    let mut keys_released = HashSet::new();
-    let mut buttons_down = HashSet::new();
-    let mut buttons_pressed = HashSet::new();
-    let mut buttons_released = HashSet::new();
-    let mut command_encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
-    let mut mouse_pos_screen = Vector2::default();
-    let mut mouse_pos_world = Vector2::default();
-
-    let mut render_controller = RenderController::new();
-
-    let mut inner_size = window.inner_size();
-
-    event_loop
-        .run(|event, target| {
-            if let Event::AboutToWait = event {
-                const MOVE_DIRS: [(KeyCode, Vector2); 4] = [
-                    (KeyCode::KeyW, Vector2::UP),
-                    (KeyCode::KeyA, Vector2::LEFT),
-                    (KeyCode::KeyS, Vector2::DOWN),
-                    (KeyCode::KeyD, Vector2::RIGHT),
-                ];
-
-                {
-                    let mut any = false;
-                    let camera = &mut camera_transforms.camera;
-                    for &(_, dir) in MOVE_DIRS
-                        .iter()
-                        .filter(|(code, _)| keys_down.contains(code))
-                    {
-                        let speed_mult = match keys_down.contains(&KeyCode::ShiftLeft) {
-                            true => A::SHIFT_SPEED_MULT,
-                            false => 1.0,
-                        };
-
-                        camera.target += dir * A::CAMERA_MOVE_SPEED / camera.zoom * speed_mult;
-                        any = true;
-                    }
-
-                    if any {
-                        mouse_pos_world =
-                            camera_transforms.screen_to_world(mouse_pos_screen, inner_size);
-                    }
-                }
+    let mut renderer = Renderer::new(&window, config, &mut application)?;
+    let mut fatal_error = None;
+
+    event_loop.run(|event, target| {
+        let result = match event {
+            Event::AboutToWait => renderer.tick(&mut application),
+            Event::WindowEvent { event, .. } => renderer.handle_event(&mut application, &event),
+            Event::DeviceEvent { event, .. } => {
+                renderer.handle_device_event(&mut application, &event);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(err) = result {
+            fatal_error = Some(err);
+            target.exit();
+        } else if renderer.exit_requested() {
+            target.exit();
+        } else {
+            target.set_control_flow(renderer.control_flow());
+        }
+    })?;
 
-                camera_transforms.update_camera(&queue);
-
-                let access = WindowAccess {
-                    keys_down: &keys_down,
-                    keys_pressed: &keys_pressed,
-                    keys_released: &keys_released,
-                    buttons_down: &buttons_down,
-                    buttons_pressed: &buttons_pressed,
-                    buttons_released: &buttons_released,
-                    camera: &camera_transforms.camera,
-                    mouse_pos_screen,
-                    mouse_pos_world,
-                };
-                application.tick(&access);
-                keys_pressed.clear();
-                buttons_pressed.clear();
-                keys_released.clear();
-                buttons_released.clear();
-
-                window.request_redraw();
-            } else if let Event::WindowEvent {
-                window_id: _,
-                event,
-            } = event
-            {
-                match event {
-                    WindowEvent::Resized(new_size) => {
-                        inner_size = new_size;
-                        surface_config.width = new_size.width;
-                        surface_config.height = new_size.height;
-                        surface.configure(&device, &surface_config);
-
-                        camera_transforms.update_aspect_ratio(&queue, new_size);
-
-                        line_render.resize(&device, new_size);
-
-                        mouse_pos_world =
-                            camera_transforms.screen_to_world(mouse_pos_screen, inner_size);
-                    }
-                    WindowEvent::CursorMoved { position, .. } => {
-                        mouse_pos_screen = Vector2::new(position.x as f32, position.y as f32);
-
-                        mouse_pos_world =
-                            camera_transforms.screen_to_world(mouse_pos_screen, inner_size);
-                    }
-                    WindowEvent::MouseInput { button, state, .. } => {
-                        application.on_mouse_event(button, state);
-
-                        match state {
-                            ElementState::Pressed => {
-                                buttons_down.insert(button);
-                                buttons_pressed.insert(button);
-                            }
-                            ElementState::Released => {
-                                buttons_down.remove(&button);
-                                buttons_released.insert(button);
-                            }
-                        }
-                    }
-                    WindowEvent::CloseRequested => {
-                        target.exit();
-                    }
-                    WindowEvent::MouseWheel { delta, .. } => {
-                        let zoom_ratio = match delta {
-                            MouseScrollDelta::LineDelta(_, y) => A::ZOOM_RATE.powf(y),
-                            MouseScrollDelta::PixelDelta(position) => {
-                                let y = position.y as f32;
-                                A::ZOOM_RATE.powf(y / 14.0) // isn't 14 like the best font size or something
-                            }
-                        };
-                        camera_transforms.camera.zoom *= zoom_ratio;
-                        camera_transforms.update_camera(&queue);
-
-                        mouse_pos_world =
-                            camera_transforms.screen_to_world(mouse_pos_screen, inner_size);
-                    }
-                    WindowEvent::KeyboardInput {
-                        event:
-                            KeyEvent {
-                                physical_key: PhysicalKey::Code(code),
-                                state,
-                                repeat,
-                                ..
-                            },
-                        ..
-                    } => {
-                        application.on_key_event(code, state, repeat);
-
-                        match state {
-                            ElementState::Pressed => {
-                                keys_down.insert(code);
-                                keys_pressed.insert(code);
-                            }
-                            ElementState::Released => {
-                                keys_down.remove(&code);
-                                keys_released.insert(code);
-                            }
-                        };
-                    }
-                    WindowEvent::RedrawRequested => {
-                        let now = Instant::now();
-                        frame_moments.push_back(now);
-
-                        render_controller.clear();
-                        application.render(&mut render_controller);
-
-                        line_render.line_data.set_new_data(
-                            &device,
-                            &queue,
-                            &render_controller.lines,
-                        );
-                        rect_circle_render.instance_data.set_new_data(
-                            &device,
-                            &queue,
-                            &render_controller.rects,
-                        );
-
-                        line_render.pre_render(&mut command_encoder);
-
-                        while frame_moments
-                            .front()
-                            .is_some_and(|inst| inst.elapsed().as_secs_f32() > 1.0)
-                        {
-                            frame_moments.pop_front();
-                        }
-
-                        let texture = surface.get_current_texture().unwrap();
-                        let view = texture
-                            .texture
-                            .create_view(&TextureViewDescriptor::default());
-
-                        // begin drawing
-                        {
-                            let mut render_pass =
-                                command_encoder.begin_render_pass(&RenderPassDescriptor {
-                                    label: None,
-                                    color_attachments: &[Some(RenderPassColorAttachment {
-                                        view: &view,
-                                        resolve_target: None,
-                                        ops: Operations {
-                                            load: LoadOp::Clear(wgpu::Color::BLACK),
-                                            store: StoreOp::Store,
-                                        },
-                                    })],
-                                    depth_stencil_attachment: None,
-                                    timestamp_writes: None,
-                                    occlusion_query_set: None,
-                                });
-
-                            for &stage in &render_controller.render_order {
-                                match stage {
-                                    RenderStage::RectsAndCircles => {
-                                        rect_circle_render
-                                            .render(&mut render_pass, &camera_transforms);
-                                    }
-                                    RenderStage::Line => {
-                                        line_render.render(&mut render_pass, &camera_transforms);
-                                    }
-                                }
-                            }
-                        }
-
-                        let new_ce = device.create_command_encoder(&CommandEncoderDescriptor::default());
-                        let old_ce = replace(&mut command_encoder, new_ce);
-                        queue.submit(iter::once(old_ce.finish()));
-
-                        texture.present();
-                    }
-                    _ => {}
-                }
-            };
-        })
-        .unwrap();
+    match fatal_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
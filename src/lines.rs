@@ -1,203 +1,834 @@
-use super::camera::CameraTransforms;
-use super::color::{Color, RawColor};
-use super::dynamic_storage::DynamicStorageBuffer;
-use super::util;
-use super::vectors::Vector2;
-use bytemuck::{Pod, Zeroable};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use wgpu::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages,
-    CommandEncoder, Device, Extent3d, ImageSubresourceRange, PrimitiveTopology, RenderPass,
-    RenderPipeline, ShaderModule, ShaderStages, StorageTextureAccess, Texture, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDimension,
-};
-use winit::dpi::PhysicalSize;
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone, Default, Zeroable, Pod)]
-pub struct Line {
-    from: Vector2,
-    to: Vector2,
-    color: RawColor,
-}
-
-impl Line {
-    pub fn new(from: Vector2, to: Vector2, color: Color) -> Self {
-        Self {
-            from,
-            to,
-            color: color.raw_pre_mult(),
-        }
-    }
-}
-
-pub struct LineRenderPipeline {
-    pub line_data: DynamicStorageBuffer<Line>,
-    empty_vertex_buffer: Buffer,
-    render_pipeline: RenderPipeline,
-
-    use_alpha: u32,
-    use_alpha_buffer: Buffer,
-    accum_texture: Texture,
-    accum_texture_view: TextureView,
-    accum_bind_group_layout: BindGroupLayout,
-    accum_bind_group: BindGroup,
-}
-
-impl LineRenderPipeline {
-    fn create_accum_texture(device: &Device, window_size: PhysicalSize<u32>) -> Texture {
-        device.create_texture(&TextureDescriptor {
-            label: None,
-            size: Extent3d {
-                width: window_size.width,
-                height: window_size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba32Float,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING, // | TextureUsages::COPY_DST,
-            view_formats: &[],
-        })
-    }
-
-    fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
-        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::StorageTexture {
-                        access: StorageTextureAccess::ReadWrite,
-                        format: TextureFormat::Rgba32Float,
-                        view_dimension: TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        })
-    }
-
-    fn create_bind_group(
-        device: &Device,
-        layout: &BindGroupLayout,
-        texture_view: &TextureView,
-        use_alpha: &Buffer,
-    ) -> BindGroup {
-        device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: use_alpha.as_entire_binding(),
-                },
-            ],
-        })
-    }
-
-    pub fn new(
-        device: &Device,
-        line_data: DynamicStorageBuffer<Line>,
-        shader: ShaderModule,
-        texture_format: TextureFormat,
-        window_size: PhysicalSize<u32>,
-        use_alpha: bool,
-    ) -> Self {
-        let use_alpha = use_alpha as u32;
-
-        let accum_texture = Self::create_accum_texture(device, window_size);
-        let accum_texture_view = accum_texture.create_view(&Default::default());
-
-        let use_alpha_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: util::cast_thing(&use_alpha),
-            usage: BufferUsages::UNIFORM,
-        });
-
-        let accum_bind_group_layout = Self::create_bind_group_layout(device);
-        let accum_bind_group = Self::create_bind_group(
-            device,
-            &accum_bind_group_layout,
-            &accum_texture_view,
-            &use_alpha_buffer,
-        );
-
-        let pipeline_layout = util::create_pipeline_layout(
-            device,
-            &[
-                line_data.bind_group_layout(),
-                &CameraTransforms::create_bind_group_layout(device),
-                &accum_bind_group_layout,
-            ],
-        );
-
-        let render_pipeline = util::create_no_vertex_render_pipeline(
-            device,
-            &shader,
-            &pipeline_layout,
-            texture_format,
-            PrimitiveTopology::LineList,
-        );
-        Self {
-            line_data,
-            empty_vertex_buffer: util::create_empty_vertex_buffer(device),
-            render_pipeline,
-            accum_texture,
-            accum_texture_view,
-            accum_bind_group_layout,
-            accum_bind_group,
-            use_alpha,
-            use_alpha_buffer,
-        }
-    }
-
-    pub fn resize(&mut self, device: &Device, new_size: PhysicalSize<u32>) {
-        if self.use_alpha > 0 {
-            let new_texture = Self::create_accum_texture(device, new_size);
-            let new_texture_view = new_texture.create_view(&Default::default());
-            let new_bind_group = Self::create_bind_group(
-                device,
-                &self.accum_bind_group_layout,
-                &new_texture_view,
-                &self.use_alpha_buffer,
-            );
-
-            self.accum_texture = new_texture;
-            self.accum_texture_view = new_texture_view;
-            self.accum_bind_group = new_bind_group;
-        }
-    }
-
-    pub fn pre_render(&self, command_encoder: &mut CommandEncoder) {
-        if self.use_alpha > 0 {
-            command_encoder.clear_texture(&self.accum_texture, &ImageSubresourceRange::default());
-        }
-    }
-
-    pub fn render(&self, render_pass: &mut RenderPass, camera_transforms: &CameraTransforms) {
-        render_pass.set_pipeline(&self.render_pipeline);
-        self.line_data.bind_to(render_pass, 0);
-        camera_transforms.bind_group_to(render_pass, 1);
-        render_pass.set_bind_group(2, &self.accum_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.empty_vertex_buffer.slice(..));
-        render_pass.draw(0..2, 0..self.line_data.len());
-    }
-}
+use super::camera::CameraTransforms;
+use super::color::{Color, RawColor};
+use super::compact::{self, CompactInstance, InstanceBuffer};
+use super::rect::Rect;
+use super::util;
+use super::vectors::Vector2;
+use bytemuck::{Pod, Zeroable};
+use std::cell::Cell;
+use wgpu::util::{BufferInitDescriptor, DeviceExt, DrawIndirectArgs};
+use wgpu::{
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
+    BlendFactor, BlendOperation, BlendState, Buffer, BufferBindingType, BufferUsages,
+    CommandEncoder, Device, Extent3d, Features, ImageSubresourceRange, PipelineCache,
+    PipelineLayout, PrimitiveTopology, Queue, RenderPass, RenderPipeline, ShaderModule,
+    ShaderStages, StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureFormatFeatureFlags, TextureUsages, TextureView, TextureViewDimension,
+};
+use winit::dpi::PhysicalSize;
+
+/// A one pixel wide line. The color is stored premultiplied by alpha. Without
+/// [`Renderable::USE_LINE_ALPHA`](crate::Renderable::USE_LINE_ALPHA), translucent lines blend
+/// over what was drawn before them like [`RectOrCircle`](crate::RectOrCircle)s do.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Zeroable, Pod)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "LineData", from = "LineData")
+)]
+pub struct Line {
+    from: Vector2,
+    to: Vector2,
+    color: RawColor,
+    glow: f32,
+    screen_space: u32,
+    _padding: [u32; 2],
+}
+
+impl Line {
+    pub const fn new(from: Vector2, to: Vector2, color: Color) -> Self {
+        Self::new_raw(from, to, color.raw_pre_mult())
+    }
+
+    /// Stores `color` as is, so it should already be premultiplied.
+    pub const fn new_raw(from: Vector2, to: Vector2, color: RawColor) -> Self {
+        Self {
+            from,
+            to,
+            color,
+            glow: 0.0,
+            screen_space: 0,
+            _padding: [0; 2],
+        }
+    }
+
+    /// A line between two points in pixels from the top left of the window, like
+    /// [`WindowAccess::mouse_pos_screen`](crate::WindowAccess::mouse_pos_screen), unaffected by
+    /// the camera. Pixel centers are at half-integers. Screen lines draw in the line stage
+    /// along with world lines, and never glow.
+    pub const fn screen(from: Vector2, to: Vector2, color: Color) -> Self {
+        Self::screen_raw(from, to, color.raw_pre_mult())
+    }
+
+    /// Stores `color` as is, so it should already be premultiplied.
+    pub const fn screen_raw(from: Vector2, to: Vector2, color: RawColor) -> Self {
+        let mut line = Self::new_raw(from, to, color);
+        line.screen_space = 1;
+        line
+    }
+
+    pub const fn is_screen_space(&self) -> bool {
+        self.screen_space != 0
+    }
+
+    /// Whether the line is drawn by the glow pipeline rather than as a hairline.
+    fn glows(&self) -> bool {
+        self.glow > 0.0 && !self.is_screen_space()
+    }
+
+    /// Draws the line as a soft glow fading out over `radius` from its centerline, added onto
+    /// what is beneath it, instead of a hairline. The radius is in world units or pixels, see
+    /// [`LineGlowUnits`]; 0 goes back to a hairline.
+    pub const fn with_glow(mut self, radius: f32) -> Self {
+        self.glow = radius;
+        self
+    }
+
+    pub const fn glow(&self) -> f32 {
+        self.glow
+    }
+
+    pub const fn from(&self) -> Vector2 {
+        self.from
+    }
+
+    pub const fn to(&self) -> Vector2 {
+        self.to
+    }
+
+    /// The color, premultiplied by alpha.
+    pub const fn color(&self) -> RawColor {
+        self.color
+    }
+
+    /// Screen lines stay where they are.
+    pub(crate) fn translated(self, offset: Vector2) -> Self {
+        if self.is_screen_space() {
+            return self;
+        }
+        Self {
+            from: self.from + offset,
+            to: self.to + offset,
+            ..self
+        }
+    }
+
+    /// The axis-aligned box containing the line.
+    pub fn bounds(&self) -> Rect {
+        Rect::from_corners(self.from, self.to)
+    }
+
+    /// Whether any part of the line is in `rect`, which is in pixels for [`Self::screen`]
+    /// lines. Glow doesn't count.
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        rect.intersects_segment(self.from, self.to)
+    }
+}
+
+/// A [`Line`] with its color packed, mirroring `StoredInstance` in lines_compact.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub(crate) struct CompactLine {
+    from: Vector2,
+    to: Vector2,
+    color: u32,
+    glow: f32,
+    screen_space: u32,
+    _padding: u32,
+}
+
+impl CompactInstance for Line {
+    type Compact = CompactLine;
+
+    fn compact(&self) -> CompactLine {
+        CompactLine {
+            from: self.from,
+            to: self.to,
+            color: compact::pack_color(self.color),
+            glow: self.glow,
+            screen_space: self.screen_space,
+            _padding: 0,
+        }
+    }
+}
+
+/// What a [`Line`] serializes as, with its color straight rather than premultiplied.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Line")]
+struct LineData {
+    from: Vector2,
+    to: Vector2,
+    color: Color,
+    #[serde(default)]
+    glow: f32,
+    #[serde(default)]
+    screen_space: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<Line> for LineData {
+    fn from(line: Line) -> Self {
+        Self {
+            from: line.from,
+            to: line.to,
+            color: Color::from_pre_mult(line.color),
+            glow: line.glow,
+            screen_space: line.is_screen_space(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<LineData> for Line {
+    fn from(data: LineData) -> Self {
+        let line = match data.screen_space {
+            true => Self::screen(data.from, data.to, data.color),
+            false => Self::new(data.from, data.to, data.color),
+        };
+        line.with_glow(data.glow)
+    }
+}
+
+/// How [`Renderable::USE_LINE_ALPHA`](crate::Renderable::USE_LINE_ALPHA) is carried out on
+/// the current adapter.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LineAccumulation {
+    /// Translucent lines accumulate in an `Rgba32Float` read-write storage texture.
+    StorageTexture,
+    /// The adapter can't read and write `Rgba32Float` storage textures, so alpha accumulation
+    /// stays off and translucent lines blend like shapes do.
+    Unavailable,
+}
+
+impl LineAccumulation {
+    pub fn detect(adapter: &Adapter, device: &Device) -> Self {
+        let format_features = adapter.get_texture_format_features(TextureFormat::Rgba32Float);
+        let supported = device
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            && format_features
+                .allowed_usages
+                .contains(TextureUsages::STORAGE_BINDING)
+            && format_features
+                .flags
+                .contains(TextureFormatFeatureFlags::STORAGE_READ_WRITE);
+        match supported {
+            true => Self::StorageTexture,
+            false => Self::Unavailable,
+        }
+    }
+}
+
+/// How accumulated line colors are brought into displayable range, after scaling by the
+/// exposure. Only applies with [`Renderable::USE_LINE_ALPHA`](crate::Renderable::USE_LINE_ALPHA).
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineTonemap {
+    /// Anything above 1 is clipped.
+    #[default]
+    Clamp,
+    /// `x / (1 + x)`, which never quite reaches white.
+    Reinhard,
+    /// `log2(1 + x)`, clipped at 1, keeping detail across orders of magnitude of density.
+    Log,
+}
+
+/// How lines combine with what was drawn before them, a cheap alternative to
+/// [`Renderable::USE_LINE_ALPHA`](crate::Renderable::USE_LINE_ALPHA). Every mode's pipeline is
+/// built up front, so switching between them is free.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineBlendMode {
+    /// Lines overwrite what is beneath them, ignoring alpha.
+    Replace,
+    /// Translucent lines blend over what is beneath them like shapes do.
+    #[default]
+    Alpha,
+    /// Colors scaled by alpha add up, for glow and density plots without the accumulation
+    /// texture.
+    Additive,
+    /// Lines tint what is beneath them, scaled by alpha.
+    Multiply,
+}
+
+impl LineBlendMode {
+    const ALL: [Self; 4] = [Self::Replace, Self::Alpha, Self::Additive, Self::Multiply];
+
+    /// The blend state for premultiplied colors, which is what lines store.
+    fn blend_state(self) -> BlendState {
+        match self {
+            Self::Replace => BlendState::REPLACE,
+            Self::Alpha => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            Self::Additive => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            },
+            Self::Multiply => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            },
+        }
+    }
+}
+
+/// The accumulation settings the line fragment shader reads, mirroring `AlphaSettings` in
+/// lines.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+struct AlphaSettings {
+    use_alpha: u32,
+    tonemap: u32,
+    exposure: f32,
+    _padding: u32,
+}
+
+/// What [`Line::with_glow`] radii are measured in.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineGlowUnits {
+    /// The glow scales with the zoom, like the line's length does.
+    #[default]
+    World,
+    /// The glow stays the same size on screen at any zoom.
+    Pixels,
+}
+
+/// Mirrors `GlowSettings` in lines_glow.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+struct GlowSettings {
+    in_pixels: u32,
+    min_dim: f32,
+    _padding: [u32; 2],
+}
+
+impl GlowSettings {
+    fn min_dim(size: PhysicalSize<u32>) -> f32 {
+        size.width.min(size.height).max(1) as f32
+    }
+}
+
+#[cfg_attr(not(feature = "shader-hot-reload"), allow(dead_code))]
+pub struct LineRenderPipeline {
+    pub line_data: InstanceBuffer<Line>,
+    /// One per [`LineBlendMode`], in the order of [`LineBlendMode::ALL`].
+    render_pipelines: Vec<RenderPipeline>,
+    blend_mode: LineBlendMode,
+    /// What the pipelines are built from, so they can be rebuilt with another shader or for
+    /// another format.
+    shader: ShaderModule,
+    pipeline_layout: PipelineLayout,
+    texture_format: TextureFormat,
+    sample_count: u32,
+
+    /// Draws the glowing lines, which the hairline pipelines skip, additively after them.
+    glow_pipeline: RenderPipeline,
+    glow_shader: ShaderModule,
+    glow_pipeline_layout: PipelineLayout,
+    glow_settings: GlowSettings,
+    glow_settings_buffer: Buffer,
+    glow_bind_group: BindGroup,
+    has_glow: bool,
+
+    settings: AlphaSettings,
+    settings_buffer: Buffer,
+    accumulation: LineAccumulation,
+    accum_texture: Option<Texture>,
+    accum_texture_view: Option<TextureView>,
+    accum_bind_group_layout: BindGroupLayout,
+    accum_bind_group: BindGroup,
+    /// Whether lines have accumulated into the texture since it was last cleared or created.
+    accum_dirty: Cell<bool>,
+
+    indirect_args: Option<Buffer>,
+}
+
+impl LineRenderPipeline {
+    fn create_accum_texture(device: &Device, window_size: PhysicalSize<u32>) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("line accumulation texture"),
+            size: Extent3d {
+                width: window_size.width.max(1),
+                height: window_size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING, // | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    /// Without a texture view, the layout and bind group only hold the settings uniform.
+    fn create_bind_group_layout(device: &Device, with_texture: bool) -> BindGroupLayout {
+        let texture_entry = BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::ReadWrite,
+                format: TextureFormat::Rgba32Float,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let settings_entry = BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let entries = match with_texture {
+            true => &[texture_entry, settings_entry][..],
+            false => &[settings_entry][..],
+        };
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("line accumulation bind group layout"),
+            entries,
+        })
+    }
+
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        texture_view: Option<&TextureView>,
+        settings: &Buffer,
+    ) -> BindGroup {
+        let settings_entry = BindGroupEntry {
+            binding: 1,
+            resource: settings.as_entire_binding(),
+        };
+        let entries = match texture_view {
+            Some(texture_view) => vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(texture_view),
+                },
+                settings_entry,
+            ],
+            None => vec![settings_entry],
+        };
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("line accumulation bind group"),
+            layout,
+            entries: &entries,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        line_data: InstanceBuffer<Line>,
+        shader: ShaderModule,
+        glow_shader: ShaderModule,
+        texture_format: TextureFormat,
+        window_size: PhysicalSize<u32>,
+        use_alpha: bool,
+        accumulation: LineAccumulation,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> Self {
+        let accumulate = accumulation == LineAccumulation::StorageTexture;
+        if use_alpha && !accumulate {
+            log::warn!("line alpha accumulation is unavailable on this adapter, turning it off");
+        }
+        let settings = AlphaSettings {
+            use_alpha: (use_alpha && accumulate) as u32,
+            tonemap: LineTonemap::default() as u32,
+            exposure: 1.0,
+            _padding: 0,
+        };
+
+        let accum_texture = accumulate.then(|| Self::create_accum_texture(device, window_size));
+        let accum_texture_view = accum_texture
+            .as_ref()
+            .map(|texture| texture.create_view(&Default::default()));
+
+        let settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("line alpha settings uniform"),
+            contents: util::cast_thing(&settings),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let accum_bind_group_layout = Self::create_bind_group_layout(device, accumulate);
+        let accum_bind_group = Self::create_bind_group(
+            device,
+            &accum_bind_group_layout,
+            accum_texture_view.as_ref(),
+            &settings_buffer,
+        );
+
+        let pipeline_layout = util::create_pipeline_layout(
+            device,
+            "line pipeline layout",
+            &[
+                line_data.bind_group_layout(),
+                &CameraTransforms::create_bind_group_layout(device),
+                &accum_bind_group_layout,
+            ],
+        );
+
+        let render_pipelines = Self::create_render_pipelines(
+            device,
+            &shader,
+            &pipeline_layout,
+            texture_format,
+            sample_count,
+            cache,
+        );
+
+        let glow_settings = GlowSettings {
+            in_pixels: 0,
+            min_dim: GlowSettings::min_dim(window_size),
+            _padding: [0; 2],
+        };
+        let glow_settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("line glow settings uniform"),
+            contents: util::cast_thing(&glow_settings),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let glow_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("line glow bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let glow_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("line glow bind group"),
+            layout: &glow_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: glow_settings_buffer.as_entire_binding(),
+            }],
+        });
+        let glow_pipeline_layout = util::create_pipeline_layout(
+            device,
+            "line glow pipeline layout",
+            &[
+                line_data.bind_group_layout(),
+                &CameraTransforms::create_bind_group_layout(device),
+                &glow_bind_group_layout,
+            ],
+        );
+        let glow_pipeline = Self::create_glow_pipeline(
+            device,
+            &glow_shader,
+            &glow_pipeline_layout,
+            texture_format,
+            sample_count,
+            cache,
+        );
+
+        Self {
+            line_data,
+            render_pipelines,
+            blend_mode: LineBlendMode::default(),
+            shader,
+            pipeline_layout,
+            texture_format,
+            sample_count,
+            glow_pipeline,
+            glow_shader,
+            glow_pipeline_layout,
+            glow_settings,
+            glow_settings_buffer,
+            glow_bind_group,
+            has_glow: false,
+            accumulation,
+            accum_texture,
+            accum_texture_view,
+            accum_bind_group_layout,
+            accum_bind_group,
+            accum_dirty: Cell::new(false),
+            settings,
+            settings_buffer,
+            indirect_args: None,
+        }
+    }
+
+    /// The hairline pipelines, one per [`LineBlendMode`].
+    fn create_render_pipelines(
+        device: &Device,
+        shader: &ShaderModule,
+        pipeline_layout: &PipelineLayout,
+        texture_format: TextureFormat,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> Vec<RenderPipeline> {
+        LineBlendMode::ALL
+            .into_iter()
+            .map(|blend_mode| {
+                util::create_no_vertex_render_pipeline(
+                    device,
+                    &format!("line pipeline ({blend_mode:?})"),
+                    shader,
+                    pipeline_layout,
+                    texture_format,
+                    PrimitiveTopology::LineList,
+                    blend_mode.blend_state(),
+                    sample_count,
+                    cache,
+                )
+            })
+            .collect()
+    }
+
+    fn create_glow_pipeline(
+        device: &Device,
+        glow_shader: &ShaderModule,
+        glow_pipeline_layout: &PipelineLayout,
+        texture_format: TextureFormat,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> RenderPipeline {
+        util::create_no_vertex_render_pipeline(
+            device,
+            "line glow pipeline",
+            glow_shader,
+            glow_pipeline_layout,
+            texture_format,
+            PrimitiveTopology::TriangleList,
+            LineBlendMode::Additive.blend_state(),
+            sample_count,
+            cache,
+        )
+    }
+
+    /// Rebuilds the hairline pipelines with `shader`, keeping the old ones if that fails.
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn set_shader(
+        &mut self,
+        device: &Device,
+        shader: ShaderModule,
+        cache: Option<&PipelineCache>,
+    ) -> Result<(), wgpu::Error> {
+        self.render_pipelines = util::catch_validation_error(device, || {
+            Self::create_render_pipelines(
+                device,
+                &shader,
+                &self.pipeline_layout,
+                self.texture_format,
+                self.sample_count,
+                cache,
+            )
+        })?;
+        self.shader = shader;
+        Ok(())
+    }
+
+    /// Rebuilds every pipeline to draw into `texture_format`.
+    pub fn set_texture_format(
+        &mut self,
+        device: &Device,
+        texture_format: TextureFormat,
+        cache: Option<&PipelineCache>,
+    ) {
+        self.texture_format = texture_format;
+        self.render_pipelines = Self::create_render_pipelines(
+            device,
+            &self.shader,
+            &self.pipeline_layout,
+            texture_format,
+            self.sample_count,
+            cache,
+        );
+        self.glow_pipeline = Self::create_glow_pipeline(
+            device,
+            &self.glow_shader,
+            &self.glow_pipeline_layout,
+            texture_format,
+            self.sample_count,
+            cache,
+        );
+    }
+
+    /// Switches to drawing as many lines as a GPU buffer of [`DrawIndirectArgs`] says,
+    /// starting at 0, so the count can be written by a shader. Only the first chunk of
+    /// `line_data` is drawn, and glowing lines aren't drawn at all.
+    pub fn enable_indirect(&mut self, device: &Device) {
+        let args = DrawIndirectArgs {
+            vertex_count: 2,
+            instance_count: 0,
+            first_vertex: 0,
+            first_instance: 0,
+        };
+        self.indirect_args = Some(util::create_indirect_buffer(device, args.as_bytes()));
+    }
+
+    pub fn indirect_args(&self) -> Option<&Buffer> {
+        self.indirect_args.as_ref()
+    }
+
+    /// Switches alpha accumulation on or off from the next frame on, see
+    /// [`Renderable::USE_LINE_ALPHA`](crate::Renderable::USE_LINE_ALPHA).
+    pub fn set_use_alpha(&mut self, queue: &Queue, use_alpha: bool) {
+        if use_alpha && self.accumulation == LineAccumulation::Unavailable {
+            log::warn!("line alpha accumulation is unavailable on this adapter");
+            return;
+        }
+        let use_alpha = use_alpha as u32;
+        if use_alpha != self.settings.use_alpha {
+            self.settings.use_alpha = use_alpha;
+            self.write_settings(queue);
+        }
+    }
+
+    /// Scales accumulated colors by `exposure` before tonemapping them. Only a uniform is
+    /// rewritten, so this is cheap to change every frame.
+    pub fn set_exposure(&mut self, queue: &Queue, exposure: f32) {
+        if exposure != self.settings.exposure {
+            self.settings.exposure = exposure;
+            self.write_settings(queue);
+        }
+    }
+
+    pub fn set_tonemap(&mut self, queue: &Queue, tonemap: LineTonemap) {
+        let tonemap = tonemap as u32;
+        if tonemap != self.settings.tonemap {
+            self.settings.tonemap = tonemap;
+            self.write_settings(queue);
+        }
+    }
+
+    fn write_settings(&self, queue: &Queue) {
+        queue.write_buffer(&self.settings_buffer, 0, util::cast_thing(&self.settings));
+    }
+
+    /// Uploads `lines`, noting whether any of them glow.
+    pub fn set_lines(&mut self, device: &Device, queue: &Queue, lines: &[Line]) {
+        self.has_glow = lines.iter().any(Line::glows);
+        self.line_data.set_new_data(device, queue, lines);
+    }
+
+    pub fn set_glow_units(&mut self, queue: &Queue, units: LineGlowUnits) {
+        let in_pixels = (units == LineGlowUnits::Pixels) as u32;
+        if in_pixels != self.glow_settings.in_pixels {
+            self.glow_settings.in_pixels = in_pixels;
+            self.write_glow_settings(queue);
+        }
+    }
+
+    fn write_glow_settings(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.glow_settings_buffer,
+            0,
+            util::cast_thing(&self.glow_settings),
+        );
+    }
+
+    /// Takes effect from the next [`Self::render`] on.
+    pub fn set_blend_mode(&mut self, blend_mode: LineBlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn accumulation(&self) -> LineAccumulation {
+        self.accumulation
+    }
+
+    /// Resizes the accumulation texture even while accumulation is off, so it is ready to be
+    /// switched on.
+    pub fn resize(&mut self, device: &Device, queue: &Queue, new_size: PhysicalSize<u32>) {
+        self.glow_settings.min_dim = GlowSettings::min_dim(new_size);
+        self.write_glow_settings(queue);
+
+        if self.accum_texture.is_none() {
+            return;
+        }
+
+        let new_texture = Self::create_accum_texture(device, new_size);
+        let new_texture_view = new_texture.create_view(&Default::default());
+        let new_bind_group = Self::create_bind_group(
+            device,
+            &self.accum_bind_group_layout,
+            Some(&new_texture_view),
+            &self.settings_buffer,
+        );
+
+        self.accum_texture = Some(new_texture);
+        self.accum_texture_view = Some(new_texture_view);
+        self.accum_bind_group = new_bind_group;
+        // new textures start out zeroed
+        self.accum_dirty.set(false);
+    }
+
+    /// Clears the accumulation texture, if lines are about to accumulate into it and earlier
+    /// frames left something behind. Call after uploading the frame's lines. With MSAA the
+    /// accumulation still happens per pixel in the multisampled pass, before the resolve, at
+    /// the surface's resolution.
+    pub fn pre_render(&self, command_encoder: &mut CommandEncoder) {
+        let may_draw = self.indirect_args.is_some() || !self.line_data.is_empty();
+        if let Some(accum_texture) = &self.accum_texture {
+            if self.settings.use_alpha > 0 && may_draw && self.accum_dirty.replace(false) {
+                command_encoder.clear_texture(accum_texture, &ImageSubresourceRange::default());
+            }
+        }
+    }
+
+    /// Returns the number of draw calls issued.
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_transforms: &CameraTransforms,
+    ) -> u32 {
+        if self.indirect_args.is_none() && self.line_data.is_empty() {
+            return 0;
+        }
+
+        render_pass.set_pipeline(&self.render_pipelines[self.blend_mode as usize]);
+        camera_transforms.bind_group_to(render_pass, 1);
+        render_pass.set_bind_group(2, &self.accum_bind_group, &[]);
+        if self.settings.use_alpha > 0 {
+            self.accum_dirty.set(true);
+        }
+        if let Some(indirect_args) = &self.indirect_args {
+            self.line_data.bind_to(render_pass, 0);
+            render_pass.draw_indirect(indirect_args, 0);
+            return 1;
+        }
+        let mut draw_calls = 0;
+        for (bind_group, instances) in self.line_data.chunks() {
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..2, instances);
+            draw_calls += 1;
+        }
+        if self.has_glow {
+            render_pass.set_pipeline(&self.glow_pipeline);
+            render_pass.set_bind_group(2, &self.glow_bind_group, &[]);
+            for (bind_group, instances) in self.line_data.chunks() {
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(0..6, instances);
+                draw_calls += 1;
+            }
+        }
+        draw_calls
+    }
+
+    /// GPU memory held by the accumulation texture, in bytes.
+    pub fn accum_texture_bytes(&self) -> u64 {
+        self.accum_texture.as_ref().map_or(0, |texture| {
+            let size = texture.size();
+            // Rgba32Float
+            size.width as u64 * size.height as u64 * 16
+        })
+    }
+}
@@ -8,8 +8,19 @@ struct InstanceData {
     start: vec2<f32>,
     end: vec2<f32>,
     color: vec4<f32>,
+    glow: f32,
+    screen_space: u32,
 }
 
+// instance layout
+// how `Line` is stored, replaced by lines_compact.wgsl with compact colors
+alias StoredInstance = InstanceData;
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return stored;
+}
+// end of instance layout
+
 struct Camera {
     aim: vec2<f32>,
     zoom: f32,
@@ -21,24 +32,59 @@ var<uniform> camera: Camera;
 @group(1) @binding(1)
 var<uniform> aspect_transform: vec2<f32>;
 
+@group(1) @binding(2)
+var<uniform> viewport_size: vec2<f32>;
+
 @group(0) @binding(0)
-var<storage, read> instance_data: array<InstanceData>;
+var<storage, read> instance_data: array<StoredInstance>;
 
 @group(2) @binding(0)
 var accum_texture: texture_storage_2d<rgba32float, read_write>;
 
+struct AlphaSettings {
+    use_alpha: u32,
+    tonemap: u32,
+    exposure: f32,
+}
+
 @group(2) @binding(1)
-var<uniform> use_alpha: u32;
+var<uniform> settings: AlphaSettings;
+
+const TONEMAP_REINHARD: u32 = 1;
+const TONEMAP_LOG: u32 = 2;
+
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    let exposed = color * settings.exposure;
+    switch settings.tonemap {
+        case TONEMAP_REINHARD: {
+            return exposed / (1.0 + exposed);
+        }
+        case TONEMAP_LOG: {
+            return min(log2(1.0 + exposed), vec3<f32>(1.0));
+        }
+        default: {
+            return min(exposed, vec3<f32>(1.0));
+        }
+    }
+}
 
 @vertex
 fn vs_main(
     @builtin(vertex_index) v_index: u32,
     @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
-    let inst_data = instance_data[instance_index];
+    let inst_data = load_instance(instance_data[instance_index]);
+    if inst_data.glow > 0.0 && inst_data.screen_space == 0 {
+        // glowing lines are drawn by lines_glow.wgsl; outside the clip volume, this is culled
+        return VertexOutput(vec4<f32>(2.0, 2.0, 2.0, 1.0), vec2<f32>(0.0), instance_index);
+    }
     let position = select(inst_data.end, inst_data.start, v_index == 0);
 
-    let screen_position = (position - camera.aim) * camera.zoom * aspect_transform;
+    var screen_position = (position - camera.aim) * camera.zoom * aspect_transform;
+    if inst_data.screen_space > 0 {
+        // pixels from the top left, y down
+        screen_position = position / viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    }
     return VertexOutput(
         vec4<f32>(screen_position, 0.0, 1.0),
         position,
@@ -49,15 +95,17 @@ fn vs_main(
 @fragment
 fn fs_main(vertex_data: VertexOutput) -> @location(0) vec4<f32> {
     let index = vertex_data.instance_index;
-    let inst_data = instance_data[index];
+    let inst_data = load_instance(instance_data[index]);
 
-    if use_alpha > 0 {
+    if settings.use_alpha > 0 {
         let pixel = vec2<u32>(vertex_data.screen_position.xy);
         let accum_at: vec4<f32> = textureLoad(accum_texture, pixel);
         let new_accum = accum_at * (1 - inst_data.color.a) + inst_data.color;
         textureStore(accum_texture, pixel, new_accum);
-        return vec4<f32>(new_accum.rgb * new_accum.a, 1.0);
+        // the accumulated color is premultiplied already, so it isn't scaled by alpha again
+        return vec4<f32>(tonemap(new_accum.rgb), 1.0);
     } else {
+        // instance colors are premultiplied, like the pipeline's blending expects
         return inst_data.color;
     }
 }
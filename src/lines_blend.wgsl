@@ -0,0 +1,71 @@
+struct VertexOutput {
+    @builtin(position) screen_position: vec4<f32>,
+    @location(0) position: vec2<f32>,
+    @location(1) @interpolate(flat) instance_index: u32,
+}
+
+struct InstanceData {
+    start: vec2<f32>,
+    end: vec2<f32>,
+    color: vec4<f32>,
+    glow: f32,
+    screen_space: u32,
+}
+
+// instance layout
+// how `Line` is stored, replaced by lines_compact.wgsl with compact colors
+alias StoredInstance = InstanceData;
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return stored;
+}
+// end of instance layout
+
+struct Camera {
+    aim: vec2<f32>,
+    zoom: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(1)
+var<uniform> aspect_transform: vec2<f32>;
+
+@group(1) @binding(2)
+var<uniform> viewport_size: vec2<f32>;
+
+@group(0) @binding(0)
+var<storage, read> instance_data: array<StoredInstance>;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) v_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    let inst_data = load_instance(instance_data[instance_index]);
+    if inst_data.glow > 0.0 && inst_data.screen_space == 0 {
+        // glowing lines are drawn by lines_glow.wgsl; outside the clip volume, this is culled
+        return VertexOutput(vec4<f32>(2.0, 2.0, 2.0, 1.0), vec2<f32>(0.0), instance_index);
+    }
+    let position = select(inst_data.end, inst_data.start, v_index == 0);
+
+    var screen_position = (position - camera.aim) * camera.zoom * aspect_transform;
+    if inst_data.screen_space > 0 {
+        // pixels from the top left, y down
+        screen_position = position / viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    }
+    return VertexOutput(
+        vec4<f32>(screen_position, 0.0, 1.0),
+        position,
+        instance_index,
+    );
+}
+
+// used instead of lines.wgsl where `Rgba32Float` can't be a read-write storage texture, so
+// lines always blend like shapes do
+@fragment
+fn fs_main(vertex_data: VertexOutput) -> @location(0) vec4<f32> {
+    // instance colors are premultiplied, like the pipeline's blending expects
+    return load_instance(instance_data[vertex_data.instance_index]).color;
+}
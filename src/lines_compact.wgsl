@@ -0,0 +1,29 @@
+// instance layout
+// how `Line` is stored with compact colors, its color packed into sRGB bytes
+struct StoredInstance {
+    start: vec2<f32>,
+    end: vec2<f32>,
+    color: u32,
+    glow: f32,
+    screen_space: u32,
+}
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return InstanceData(
+        stored.start,
+        stored.end,
+        unpack_color(stored.color),
+        stored.glow,
+        stored.screen_space,
+    );
+}
+
+// straight sRGB bytes back to linear, premultiplied like the full layout's colors
+fn unpack_color(packed: u32) -> vec4<f32> {
+    let srgb = unpack4x8unorm(packed);
+    let low = srgb.rgb / 12.92;
+    let high = pow((srgb.rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    let linear = select(high, low, srgb.rgb <= vec3<f32>(0.04045));
+    return vec4<f32>(linear * srgb.a, srgb.a);
+}
+// end of instance layout
@@ -0,0 +1,109 @@
+struct VertexOutput {
+    @builtin(position) screen_position: vec4<f32>,
+    @location(0) position: vec2<f32>,
+    @location(1) @interpolate(flat) instance_index: u32,
+    @location(2) @interpolate(flat) radius: f32,
+}
+
+struct InstanceData {
+    start: vec2<f32>,
+    end: vec2<f32>,
+    color: vec4<f32>,
+    glow: f32,
+    screen_space: u32,
+}
+
+// instance layout
+// how `Line` is stored, replaced by lines_compact.wgsl with compact colors
+alias StoredInstance = InstanceData;
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return stored;
+}
+// end of instance layout
+
+struct Camera {
+    aim: vec2<f32>,
+    zoom: f32,
+}
+
+struct GlowSettings {
+    in_pixels: u32,
+    // the smaller of the target's width and height, in pixels
+    min_dim: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(1)
+var<uniform> aspect_transform: vec2<f32>;
+
+@group(1) @binding(2)
+var<uniform> viewport_size: vec2<f32>;
+
+@group(0) @binding(0)
+var<storage, read> instance_data: array<StoredInstance>;
+
+@group(2) @binding(0)
+var<uniform> settings: GlowSettings;
+
+// how many e-folds the falloff drops by at the glow radius
+const FALLOFF: f32 = 4.0;
+
+// each glowing line covers a quad around its capsule, as two triangles
+@vertex
+fn vs_main(
+    @builtin(vertex_index) v_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    let inst_data = load_instance(instance_data[instance_index]);
+    if inst_data.glow <= 0.0 || inst_data.screen_space > 0 {
+        // hairlines are drawn by the line pipeline; outside the clip volume, this is culled
+        return VertexOutput(vec4<f32>(2.0, 2.0, 2.0, 1.0), vec2<f32>(0.0), instance_index, 0.0);
+    }
+
+    let radius = select(inst_data.glow, inst_data.glow * 2.0 / (camera.zoom * settings.min_dim), settings.in_pixels > 0);
+    let along = inst_data.end - inst_data.start;
+    let len = length(along);
+    let dir = select(vec2<f32>(1.0, 0.0), along / len, len > 0.0);
+    let normal = vec2<f32>(-dir.y, dir.x);
+
+    // corners as (end or start, side), in the order 0 1 2, 0 2 3
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[v_index];
+    let base = select(inst_data.start - dir * radius, inst_data.end + dir * radius, corner.x > 0.0);
+    let position = base + normal * radius * corner.y;
+
+    let screen_position = (position - camera.aim) * camera.zoom * aspect_transform;
+    return VertexOutput(
+        vec4<f32>(screen_position, 0.0, 1.0),
+        position,
+        instance_index,
+        radius,
+    );
+}
+
+// distance from `point` to the segment from `start` to `end`, the capsule SDF without its radius
+fn segment_distance(point: vec2<f32>, start: vec2<f32>, end: vec2<f32>) -> f32 {
+    let along = end - start;
+    let length_sqr = dot(along, along);
+    let t = select(0.0, clamp(dot(point - start, along) / length_sqr, 0.0, 1.0), length_sqr > 0.0);
+    return distance(point, start + along * t);
+}
+
+@fragment
+fn fs_main(vertex_data: VertexOutput) -> @location(0) vec4<f32> {
+    let inst_data = load_instance(instance_data[vertex_data.instance_index]);
+    let dist = segment_distance(vertex_data.position, inst_data.start, inst_data.end);
+    let scaled = dist / vertex_data.radius;
+    // instance colors are premultiplied, so fading them fades alpha along with them
+    return inst_data.color * exp(-FALLOFF * scaled * scaled);
+}
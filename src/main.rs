@@ -1,22 +1,69 @@
+use std::time::Duration;
 use wgpu_rendering::{
-    run, Color, ElementState, KeyCode, Line, RectOrCircle, RenderController, RenderStage,
-    Renderable, Vector2, WindowAccess,
+    diagnostics, run, Color, ElementState, FullscreenMode, KeyCode, Line, RectOrCircle,
+    RenderController, RenderStage, Renderable, Vector2, WindowAccess,
 };
 
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
 fn main() {
-    run(TestApp::default());
+    if std::env::args().any(|arg| arg == "--print-gpu-info") {
+        for adapter in diagnostics() {
+            println!("{adapter}");
+        }
+        return;
+    }
+    run(TestApp::default()).unwrap();
 }
 
 #[derive(Default)]
 struct TestApp {
     debug_queued: bool,
     mouse_pos: Option<Vector2>,
+    /// Circles drawn around the origin, set by the slider with the `egui` feature.
+    circle_count: u32,
 }
 
 impl Renderable for TestApp {
+    const EXIT_KEY: Option<KeyCode> = Some(KeyCode::Escape);
+
     fn tick(&mut self, access: &WindowAccess) {
         self.mouse_pos = Some(access.mouse_pos_world());
 
+        if access.is_key_pressed(KeyCode::F11) {
+            access.set_fullscreen(match access.fullscreen_mode() {
+                FullscreenMode::Windowed => FullscreenMode::Borderless { monitor: None },
+                _ => FullscreenMode::Windowed,
+            });
+        }
+
+        if access.is_key_pressed(KeyCode::F2) {
+            println!("{}", access.gpu_report());
+        }
+
+        if access.is_key_pressed(KeyCode::F3) {
+            access.set_debug_overlay(!access.is_debug_overlay_shown());
+        }
+
+        for (slot, key) in (1..).zip(BOOKMARK_KEYS) {
+            if access.is_key_pressed(key) {
+                match access.modifiers().ctrl() {
+                    true => access.save_bookmark(slot),
+                    false => _ = access.restore_bookmark(slot, Duration::from_millis(400)),
+                }
+            }
+        }
+
         if self.debug_queued {
             println!("screen: {:?}", access.mouse_pos_screen());
             println!("world: {:?}", access.mouse_pos_world());
@@ -33,6 +80,13 @@ impl Renderable for TestApp {
             render.add_rect_or_circle(RectOrCircle::circle(mouse_pos, 0.1, Color::RED))
         }
 
+        render.try_add_stage(RenderStage::RectsAndCircles);
+        for i in 0..self.circle_count {
+            let angle = i as f32 / self.circle_count as f32 * std::f32::consts::TAU;
+            let pos = Vector2::new(angle.cos(), angle.sin()) * 0.5;
+            render.add_rect_or_circle(RectOrCircle::circle(pos, 0.05, Color::BLUE));
+        }
+
         render.try_add_stage(RenderStage::Line);
         for i in -10..=10 {
             for j in -10..=10 {
@@ -49,4 +103,13 @@ impl Renderable for TestApp {
             }
         }
     }
+
+    #[cfg(feature = "egui")]
+    fn ui(&mut self, context: &wgpu_rendering::egui::Context) {
+        use wgpu_rendering::egui;
+
+        egui::Window::new("Circles").show(context, |ui| {
+            ui.add(egui::Slider::new(&mut self.circle_count, 0..=64).text("count"));
+        });
+    }
 }
@@ -0,0 +1,333 @@
+use super::camera::CameraTransforms;
+use super::color::Color;
+use super::compact::{self, InstanceBuffer};
+use super::dynamic_storage::UploadMethod;
+use super::lines::Line;
+use super::util;
+use super::vectors::Vector2;
+use super::RenderController;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use wgpu::{
+    BlendState, CommandEncoder, Device, LoadOp, Operations, PrimitiveTopology, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, StoreOp, TextureFormat,
+    TextureView,
+};
+
+/// Frames shown by the frame time graph.
+const GRAPH_FRAMES: usize = 120;
+const GRAPH_HEIGHT: f32 = 40.0;
+/// The frame time the graph is scaled to at least, so a steady frame rate doesn't fill it.
+const GRAPH_MIN_SCALE: Duration = Duration::from_millis(33);
+/// Screen pixels per glyph pixel.
+const GLYPH_SCALE: f32 = 2.0;
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const CHAR_ADVANCE: f32 = (GLYPH_WIDTH + 1) as f32 * GLYPH_SCALE;
+const LINE_ADVANCE: f32 = (GLYPH_HEIGHT + 2) as f32 * GLYPH_SCALE;
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const WIDTH: f32 = GRAPH_FRAMES as f32 * 2.0;
+
+/// Frame rate, frame times, shape counts and memory in the top left corner, drawn with
+/// screen space lines over the finished frame, after the post effects and the UI, so nothing
+/// covers or alters it.
+///
+/// The lines live in a buffer of their own and are drawn by a pipeline of their own, which
+/// always blends premultiplied alpha and never accumulates, whatever the line stage is set to.
+pub(crate) struct DebugOverlay {
+    pub enabled: bool,
+    frame_times: VecDeque<Duration>,
+    last_present: Option<Instant>,
+    lines: Vec<Line>,
+    line_data: InstanceBuffer<Line>,
+    render_pipeline: RenderPipeline,
+}
+
+impl DebugOverlay {
+    /// `texture_format` is that of the views [`Self::render`] draws onto.
+    pub fn new(
+        device: &Device,
+        enabled: bool,
+        compact_colors: bool,
+        texture_format: TextureFormat,
+    ) -> Self {
+        let line_data = InstanceBuffer::with_label(
+            device,
+            "debug overlay instance buffer",
+            1024,
+            UploadMethod::default(),
+            compact_colors,
+        );
+        let shader = device.create_shader_module(compact::shader(
+            "lines_blend.wgsl",
+            include_str!("lines_blend.wgsl"),
+            compact_colors.then_some(include_str!("lines_compact.wgsl")),
+        ));
+        let pipeline_layout = util::create_pipeline_layout(
+            device,
+            "debug overlay pipeline layout",
+            &[
+                line_data.bind_group_layout(),
+                &CameraTransforms::create_bind_group_layout(device),
+            ],
+        );
+        let render_pipeline = util::create_no_vertex_render_pipeline(
+            device,
+            "debug overlay pipeline",
+            &shader,
+            &pipeline_layout,
+            texture_format,
+            PrimitiveTopology::LineList,
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            1,
+            None,
+        );
+
+        Self {
+            enabled,
+            frame_times: VecDeque::with_capacity(GRAPH_FRAMES),
+            last_present: None,
+            lines: Vec::new(),
+            line_data,
+            render_pipeline,
+        }
+    }
+
+    /// Records a presented frame. Called even while hidden, so the graph is full once shown.
+    pub fn record_present(&mut self) {
+        let now = Instant::now();
+        if let Some(last_present) = self.last_present.replace(now) {
+            if self.frame_times.len() == GRAPH_FRAMES {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(now - last_present);
+        }
+    }
+
+    /// Lays out the overlay for the frame in `render` and uploads it.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        render: &RenderController,
+        fps: u32,
+        memory_bytes: u64,
+    ) {
+        self.lines.clear();
+        if !self.enabled {
+            self.line_data.clear();
+            return;
+        }
+
+        let frame_time = self.frame_times.back().copied().unwrap_or_default();
+        let text = [
+            format!("FPS {fps}"),
+            format!("FRAME {:.2} MS", frame_time.as_secs_f64() * 1000.0),
+            format!("LINES {}", render.lines.len()),
+            format!("SHAPES {}", render.rects.len()),
+            format!("MEMORY {:.2} MB", memory_bytes as f64 / (1024.0 * 1024.0)),
+        ];
+
+        let height = text.len() as f32 * LINE_ADVANCE + GRAPH_HEIGHT + 2.0 * PADDING;
+        let background = Color::BLACK.with_alpha(0.7);
+        for row in 0..(height as u32) {
+            let y = MARGIN + row as f32 + 0.5;
+            let from = Vector2::new(MARGIN, y);
+            let to = Vector2::new(MARGIN + WIDTH + 2.0 * PADDING, y);
+            self.lines.push(Line::screen(from, to, background));
+        }
+
+        let mut origin = Vector2::same(MARGIN + PADDING);
+        for text in &text {
+            push_text(&mut self.lines, text, origin, Color::WHITE);
+            origin.y += LINE_ADVANCE;
+        }
+        self.push_graph(origin);
+
+        self.line_data.set_new_data(device, queue, &self.lines);
+    }
+
+    /// The frame time sparkline, with its top left corner at `origin`.
+    fn push_graph(&mut self, origin: Vector2) {
+        let baseline = origin.y + GRAPH_HEIGHT;
+        let scale = (self.frame_times.iter().max().copied())
+            .unwrap_or_default()
+            .max(GRAPH_MIN_SCALE)
+            .as_secs_f32();
+        let gray = Color::GRAY.with_alpha(0.5);
+        let target = baseline - GRAPH_HEIGHT * (1.0 / 60.0) / scale;
+        self.lines.push(Line::screen(
+            Vector2::new(origin.x, target),
+            Vector2::new(origin.x + WIDTH, target),
+            gray,
+        ));
+
+        let skipped = GRAPH_FRAMES - self.frame_times.len();
+        let points: Vec<Vector2> = (skipped..)
+            .zip(&self.frame_times)
+            .map(|(index, time)| {
+                let x = origin.x + index as f32 * WIDTH / (GRAPH_FRAMES - 1) as f32;
+                Vector2::new(x, baseline - GRAPH_HEIGHT * time.as_secs_f32() / scale)
+            })
+            .collect();
+        for pair in points.windows(2) {
+            self.lines
+                .push(Line::screen(pair[0], pair[1], Color::GREEN));
+        }
+    }
+
+    /// Draws the overlay over what `view` holds, in a pass of its own. Returns the number of
+    /// draw calls issued.
+    pub fn render(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        view: &TextureView,
+        camera_transforms: &CameraTransforms,
+    ) -> u32 {
+        if self.line_data.is_empty() {
+            return 0;
+        }
+
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("debug overlay render pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        camera_transforms.bind_group_to(&mut render_pass, 1);
+        let mut draw_calls = 0;
+        for (bind_group, instances) in self.line_data.chunks() {
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..2, instances);
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+}
+
+/// Adds `text` in the 5x7 font with the top left corner of its first glyph at `origin`, one
+/// line per row of consecutive lit pixels.
+fn push_text(lines: &mut Vec<Line>, text: &str, origin: Vector2, color: Color) {
+    for (index, char) in text.chars().enumerate() {
+        let left = origin.x + index as f32 * CHAR_ADVANCE;
+        for (row, bits) in glyph(char).into_iter().enumerate() {
+            for (start, end) in runs(bits) {
+                for sub_row in 0..GLYPH_SCALE as usize {
+                    let y = origin.y + (row as f32 * GLYPH_SCALE) + sub_row as f32 + 0.5;
+                    let from = Vector2::new(left + start as f32 * GLYPH_SCALE, y);
+                    let to = Vector2::new(left + end as f32 * GLYPH_SCALE, y);
+                    lines.push(Line::screen(from, to, color));
+                }
+            }
+        }
+    }
+}
+
+/// The column ranges of lit pixels in a glyph row, left to right.
+fn runs(bits: u8) -> impl Iterator<Item = (usize, usize)> {
+    let lit = move |column: usize| bits >> (GLYPH_WIDTH - 1 - column) & 1 == 1;
+    let mut column = 0;
+    std::iter::from_fn(move || {
+        while column < GLYPH_WIDTH && !lit(column) {
+            column += 1;
+        }
+        let start = column;
+        while column < GLYPH_WIDTH && lit(column) {
+            column += 1;
+        }
+        (start < column).then_some((start, column))
+    })
+}
+
+/// The rows of `char` in a 5x7 font, top to bottom, with the leftmost column in bit 4.
+/// Letters are upper case only; characters outside the font are blank.
+fn glyph(char: char) -> [u8; GLYPH_HEIGHT] {
+    match char.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph, push_text, runs, GLYPH_SCALE};
+    use crate::{Color, Vector2};
+
+    #[test]
+    fn rows_split_into_runs_of_lit_pixels() {
+        assert_eq!(runs(0b10001).collect::<Vec<_>>(), [(0, 1), (4, 5)]);
+        assert_eq!(runs(0b01110).collect::<Vec<_>>(), [(1, 4)]);
+        assert_eq!(runs(0b11111).collect::<Vec<_>>(), [(0, 5)]);
+        assert_eq!(runs(0).count(), 0);
+    }
+
+    #[test]
+    fn glyphs_fit_five_columns() {
+        for char in ('0'..='9').chain('A'..='Z').chain(".:-/".chars()) {
+            assert!(glyph(char).iter().all(|&row| row < 1 << 5), "{char}");
+            assert!(glyph(char).iter().any(|&row| row != 0), "{char}");
+        }
+        assert_eq!(glyph('e'), glyph('E'));
+        assert_eq!(glyph('?'), [0; 7]);
+    }
+
+    #[test]
+    fn text_is_drawn_in_screen_space() {
+        let mut lines = Vec::new();
+        push_text(&mut lines, "L", Vector2::ZERO, Color::WHITE);
+        // six rows with one pixel, one full row
+        assert_eq!(lines.len(), 7 * GLYPH_SCALE as usize);
+        assert!(lines.iter().all(|line| line.is_screen_space()));
+        let bottom = lines.last().unwrap();
+        assert_eq!(bottom.to().x - bottom.from().x, 5.0 * GLYPH_SCALE);
+    }
+}
@@ -0,0 +1,201 @@
+//! The CSS named colors, from the CSS Color Module Level 4. Each constant is named after
+//! the CSS keyword in upper case, and converted from sRGB like [`Color::srgb`].
+//!
+//! Some share a name with a constant on [`Color`] but not its value: CSS `GREEN` is
+//! `Color::srgb(0, 128, 0)`, while [`Color::GREEN`] is pure green, CSS `LIME`.
+
+use super::color::Color;
+
+macro_rules! css_colors {
+    (
+        $($name: ident $css_name: literal $hex: literal)*
+    ) => {
+        $(
+        pub const $name: Color = Color::from_hex($hex);
+        )*
+
+        /// Every named color with its CSS keyword, in alphabetical order.
+        pub const NAMED: &[(&str, Color)] = &[$(($css_name, $name)),*];
+    };
+}
+
+css_colors! {
+    ALICEBLUE            "aliceblue"            0xF0F8FF
+    ANTIQUEWHITE         "antiquewhite"         0xFAEBD7
+    AQUA                 "aqua"                 0x00FFFF
+    AQUAMARINE           "aquamarine"           0x7FFFD4
+    AZURE                "azure"                0xF0FFFF
+    BEIGE                "beige"                0xF5F5DC
+    BISQUE               "bisque"               0xFFE4C4
+    BLACK                "black"                0x000000
+    BLANCHEDALMOND       "blanchedalmond"       0xFFEBCD
+    BLUE                 "blue"                 0x0000FF
+    BLUEVIOLET           "blueviolet"           0x8A2BE2
+    BROWN                "brown"                0xA52A2A
+    BURLYWOOD            "burlywood"            0xDEB887
+    CADETBLUE            "cadetblue"            0x5F9EA0
+    CHARTREUSE           "chartreuse"           0x7FFF00
+    CHOCOLATE            "chocolate"            0xD2691E
+    CORAL                "coral"                0xFF7F50
+    CORNFLOWERBLUE       "cornflowerblue"       0x6495ED
+    CORNSILK             "cornsilk"             0xFFF8DC
+    CRIMSON              "crimson"              0xDC143C
+    CYAN                 "cyan"                 0x00FFFF
+    DARKBLUE             "darkblue"             0x00008B
+    DARKCYAN             "darkcyan"             0x008B8B
+    DARKGOLDENROD        "darkgoldenrod"        0xB8860B
+    DARKGRAY             "darkgray"             0xA9A9A9
+    DARKGREEN            "darkgreen"            0x006400
+    DARKGREY             "darkgrey"             0xA9A9A9
+    DARKKHAKI            "darkkhaki"            0xBDB76B
+    DARKMAGENTA          "darkmagenta"          0x8B008B
+    DARKOLIVEGREEN       "darkolivegreen"       0x556B2F
+    DARKORANGE           "darkorange"           0xFF8C00
+    DARKORCHID           "darkorchid"           0x9932CC
+    DARKRED              "darkred"              0x8B0000
+    DARKSALMON           "darksalmon"           0xE9967A
+    DARKSEAGREEN         "darkseagreen"         0x8FBC8F
+    DARKSLATEBLUE        "darkslateblue"        0x483D8B
+    DARKSLATEGRAY        "darkslategray"        0x2F4F4F
+    DARKSLATEGREY        "darkslategrey"        0x2F4F4F
+    DARKTURQUOISE        "darkturquoise"        0x00CED1
+    DARKVIOLET           "darkviolet"           0x9400D3
+    DEEPPINK             "deeppink"             0xFF1493
+    DEEPSKYBLUE          "deepskyblue"          0x00BFFF
+    DIMGRAY              "dimgray"              0x696969
+    DIMGREY              "dimgrey"              0x696969
+    DODGERBLUE           "dodgerblue"           0x1E90FF
+    FIREBRICK            "firebrick"            0xB22222
+    FLORALWHITE          "floralwhite"          0xFFFAF0
+    FORESTGREEN          "forestgreen"          0x228B22
+    FUCHSIA              "fuchsia"              0xFF00FF
+    GAINSBORO            "gainsboro"            0xDCDCDC
+    GHOSTWHITE           "ghostwhite"           0xF8F8FF
+    GOLD                 "gold"                 0xFFD700
+    GOLDENROD            "goldenrod"            0xDAA520
+    GRAY                 "gray"                 0x808080
+    GREEN                "green"                0x008000
+    GREENYELLOW          "greenyellow"          0xADFF2F
+    GREY                 "grey"                 0x808080
+    HONEYDEW             "honeydew"             0xF0FFF0
+    HOTPINK              "hotpink"              0xFF69B4
+    INDIANRED            "indianred"            0xCD5C5C
+    INDIGO               "indigo"               0x4B0082
+    IVORY                "ivory"                0xFFFFF0
+    KHAKI                "khaki"                0xF0E68C
+    LAVENDER             "lavender"             0xE6E6FA
+    LAVENDERBLUSH        "lavenderblush"        0xFFF0F5
+    LAWNGREEN            "lawngreen"            0x7CFC00
+    LEMONCHIFFON         "lemonchiffon"         0xFFFACD
+    LIGHTBLUE            "lightblue"            0xADD8E6
+    LIGHTCORAL           "lightcoral"           0xF08080
+    LIGHTCYAN            "lightcyan"            0xE0FFFF
+    LIGHTGOLDENRODYELLOW "lightgoldenrodyellow" 0xFAFAD2
+    LIGHTGRAY            "lightgray"            0xD3D3D3
+    LIGHTGREEN           "lightgreen"           0x90EE90
+    LIGHTGREY            "lightgrey"            0xD3D3D3
+    LIGHTPINK            "lightpink"            0xFFB6C1
+    LIGHTSALMON          "lightsalmon"          0xFFA07A
+    LIGHTSEAGREEN        "lightseagreen"        0x20B2AA
+    LIGHTSKYBLUE         "lightskyblue"         0x87CEFA
+    LIGHTSLATEGRAY       "lightslategray"       0x778899
+    LIGHTSLATEGREY       "lightslategrey"       0x778899
+    LIGHTSTEELBLUE       "lightsteelblue"       0xB0C4DE
+    LIGHTYELLOW          "lightyellow"          0xFFFFE0
+    LIME                 "lime"                 0x00FF00
+    LIMEGREEN            "limegreen"            0x32CD32
+    LINEN                "linen"                0xFAF0E6
+    MAGENTA              "magenta"              0xFF00FF
+    MAROON               "maroon"               0x800000
+    MEDIUMAQUAMARINE     "mediumaquamarine"     0x66CDAA
+    MEDIUMBLUE           "mediumblue"           0x0000CD
+    MEDIUMORCHID         "mediumorchid"         0xBA55D3
+    MEDIUMPURPLE         "mediumpurple"         0x9370DB
+    MEDIUMSEAGREEN       "mediumseagreen"       0x3CB371
+    MEDIUMSLATEBLUE      "mediumslateblue"      0x7B68EE
+    MEDIUMSPRINGGREEN    "mediumspringgreen"    0x00FA9A
+    MEDIUMTURQUOISE      "mediumturquoise"      0x48D1CC
+    MEDIUMVIOLETRED      "mediumvioletred"      0xC71585
+    MIDNIGHTBLUE         "midnightblue"         0x191970
+    MINTCREAM            "mintcream"            0xF5FFFA
+    MISTYROSE            "mistyrose"            0xFFE4E1
+    MOCCASIN             "moccasin"             0xFFE4B5
+    NAVAJOWHITE          "navajowhite"          0xFFDEAD
+    NAVY                 "navy"                 0x000080
+    OLDLACE              "oldlace"              0xFDF5E6
+    OLIVE                "olive"                0x808000
+    OLIVEDRAB            "olivedrab"            0x6B8E23
+    ORANGE               "orange"               0xFFA500
+    ORANGERED            "orangered"            0xFF4500
+    ORCHID               "orchid"               0xDA70D6
+    PALEGOLDENROD        "palegoldenrod"        0xEEE8AA
+    PALEGREEN            "palegreen"            0x98FB98
+    PALETURQUOISE        "paleturquoise"        0xAFEEEE
+    PALEVIOLETRED        "palevioletred"        0xDB7093
+    PAPAYAWHIP           "papayawhip"           0xFFEFD5
+    PEACHPUFF            "peachpuff"            0xFFDAB9
+    PERU                 "peru"                 0xCD853F
+    PINK                 "pink"                 0xFFC0CB
+    PLUM                 "plum"                 0xDDA0DD
+    POWDERBLUE           "powderblue"           0xB0E0E6
+    PURPLE               "purple"               0x800080
+    REBECCAPURPLE        "rebeccapurple"        0x663399
+    RED                  "red"                  0xFF0000
+    ROSYBROWN            "rosybrown"            0xBC8F8F
+    ROYALBLUE            "royalblue"            0x4169E1
+    SADDLEBROWN          "saddlebrown"          0x8B4513
+    SALMON               "salmon"               0xFA8072
+    SANDYBROWN           "sandybrown"           0xF4A460
+    SEAGREEN             "seagreen"             0x2E8B57
+    SEASHELL             "seashell"             0xFFF5EE
+    SIENNA               "sienna"               0xA0522D
+    SILVER               "silver"               0xC0C0C0
+    SKYBLUE              "skyblue"              0x87CEEB
+    SLATEBLUE            "slateblue"            0x6A5ACD
+    SLATEGRAY            "slategray"            0x708090
+    SLATEGREY            "slategrey"            0x708090
+    SNOW                 "snow"                 0xFFFAFA
+    SPRINGGREEN          "springgreen"          0x00FF7F
+    STEELBLUE            "steelblue"            0x4682B4
+    TAN                  "tan"                  0xD2B48C
+    TEAL                 "teal"                 0x008080
+    THISTLE              "thistle"              0xD8BFD8
+    TOMATO               "tomato"               0xFF6347
+    TURQUOISE            "turquoise"            0x40E0D0
+    VIOLET               "violet"               0xEE82EE
+    WHEAT                "wheat"                0xF5DEB3
+    WHITE                "white"                0xFFFFFF
+    WHITESMOKE           "whitesmoke"           0xF5F5F5
+    YELLOW               "yellow"               0xFFFF00
+    YELLOWGREEN          "yellowgreen"          0x9ACD32
+}
+
+impl Color {
+    /// Looks up a CSS named color, ignoring case.
+    pub fn by_name(name: &str) -> Option<Self> {
+        NAMED
+            .iter()
+            .find(|(css_name, _)| css_name.eq_ignore_ascii_case(name))
+            .map(|&(_, color)| color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_by_name() {
+        assert_eq!(NAMED.len(), 148);
+        assert_eq!(Color::by_name("teal"), Some(TEAL));
+        assert_eq!(Color::by_name("Crimson"), Some(Color::srgb(220, 20, 60)));
+        assert_eq!(Color::by_name("SLATEGRAY"), Color::by_name("slategrey"));
+        assert_eq!(Color::by_name("green"), Some(Color::srgb(0, 128, 0)));
+        assert_eq!(Color::by_name("not a color"), None);
+    }
+
+    #[test]
+    fn names_are_sorted_and_unique() {
+        assert!(NAMED.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use wgpu::{AdapterInfo, Device, PipelineCache, PipelineCacheDescriptor};
+
+/// A pipeline cache loaded from and saved back to a file in a directory, named after the
+/// adapter so caches for different GPUs and drivers don't overwrite each other.
+pub(crate) struct DiskPipelineCache {
+    cache: PipelineCache,
+    path: PathBuf,
+}
+
+impl DiskPipelineCache {
+    /// Requires `Features::PIPELINE_CACHE` on `device`. Returns `None` for adapters wgpu has
+    /// no cache key for, which currently is every non-Vulkan one.
+    pub fn load(device: &Device, adapter_info: &AdapterInfo, dir: &Path) -> Option<Self> {
+        let path = dir.join(wgpu::util::pipeline_cache_key(adapter_info)?);
+        let data = fs::read(&path).ok();
+        if data.is_some() {
+            log::info!("loaded pipeline cache from {path:?}");
+        }
+
+        // SAFETY: the file is only ever written by `store` with data from
+        // `PipelineCache::get_data`, and `fallback` discards data the driver can't use
+        let cache = unsafe {
+            device.create_pipeline_cache(&PipelineCacheDescriptor {
+                label: Some("pipeline cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        Some(Self { cache, path })
+    }
+
+    pub fn cache(&self) -> &PipelineCache {
+        &self.cache
+    }
+
+    /// Writes the cache back through a temporary file, so a crash can't leave a truncated one.
+    pub fn store(&self) {
+        let Some(data) = self.cache.get_data() else {
+            return;
+        };
+        let temp_path = self.path.with_extension("tmp");
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&temp_path, data))
+            .and_then(|()| fs::rename(&temp_path, &self.path));
+        if let Err(err) = result {
+            log::warn!("failed to save pipeline cache to {:?}: {err}", self.path);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use super::camera::Camera;
+use super::color::Color;
+use super::lines::Line;
+use super::rect::Rect;
+use super::vectors::Vector2;
+use super::{RenderController, RenderStage};
+use winit::dpi::PhysicalSize;
+
+/// The most ticks put on one axis, in case the camera is zoomed out absurdly far.
+const MAX_TICKS: usize = 1000;
+
+/// How data values map to world coordinates along an axis.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum AxisScale {
+    #[default]
+    Linear,
+    /// World coordinates are the base 10 logarithm of values, so each world unit is a decade.
+    Log10,
+}
+
+impl AxisScale {
+    /// Where `value` goes in world space. Non-positive values on a log scale go to negative
+    /// infinity.
+    pub fn to_world(self, value: f32) -> f32 {
+        match self {
+            Self::Linear => value,
+            Self::Log10 => value.log10(),
+        }
+    }
+
+    /// The inverse of [`Self::to_world`].
+    pub fn from_world(self, world: f32) -> f32 {
+        match self {
+            Self::Linear => world,
+            Self::Log10 => 10f32.powf(world),
+        }
+    }
+
+    /// World positions of the ticks within `min..=max`, about `spacing` world units apart.
+    fn ticks(self, min: f32, max: f32, spacing: f32) -> Vec<f32> {
+        match self {
+            Self::Linear => steps(min, max, nice_step(spacing)).collect(),
+            // a world unit is a decade, so its pixels decide how much of each decade is ticked
+            Self::Log10 if spacing > 0.5 => steps(min, max, nice_step(spacing).max(1.0)).collect(),
+            Self::Log10 => {
+                let mantissas: &[f32] = match spacing {
+                    _ if spacing > 0.2 => &[1.0, 2.0, 5.0],
+                    _ => &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0],
+                };
+                steps(min.floor(), max, 1.0)
+                    .flat_map(|decade| mantissas.iter().map(move |m| decade + m.log10()))
+                    .filter(|&tick| tick >= min && tick <= max)
+                    .take(MAX_TICKS)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The smallest 1, 2 or 5 times a power of ten that's at least `raw`.
+fn nice_step(raw: f32) -> f32 {
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let nice = match raw / magnitude {
+        fraction if fraction <= 1.0 => 1.0,
+        fraction if fraction <= 2.0 => 2.0,
+        fraction if fraction <= 5.0 => 5.0,
+        _ => 10.0,
+    };
+    nice * magnitude
+}
+
+/// The multiples of `step` within `min..=max`.
+fn steps(min: f32, max: f32, step: f32) -> impl Iterator<Item = f32> {
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    let valid = step.is_finite() && step > 0.0 && first <= last;
+    (first..=last)
+        .filter(move |_| valid)
+        .take(MAX_TICKS)
+        .map(move |index| index as f32 * step)
+}
+
+/// How [`Axes`] look. Lengths are in pixels, so they stay the same at any zoom.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxesStyle {
+    pub axis_color: Color,
+    pub tick_color: Color,
+    /// Grid lines across the plot at every tick, if any.
+    pub grid_color: Option<Color>,
+    pub tick_length: f32,
+    /// The closest ticks may get; they are further apart when a nicer spacing needs it.
+    pub min_tick_spacing: f32,
+}
+
+impl Default for AxesStyle {
+    fn default() -> Self {
+        Self {
+            axis_color: Color::WHITE,
+            tick_color: Color::WHITE,
+            grid_color: Some(Color::GRAY.with_alpha(0.3)),
+            tick_length: 6.0,
+            min_tick_spacing: 40.0,
+        }
+    }
+}
+
+/// Axes along the bottom and left edges of a world space rectangle, with ticks and grid lines
+/// spaced 1, 2 or 5 times a power of ten apart, depending on the zoom.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Axes {
+    pub rect: Rect,
+    pub x_scale: AxisScale,
+    pub y_scale: AxisScale,
+    pub style: AxesStyle,
+}
+
+impl Axes {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            x_scale: AxisScale::Linear,
+            y_scale: AxisScale::Linear,
+            style: AxesStyle::default(),
+        }
+    }
+
+    pub fn with_scales(mut self, x_scale: AxisScale, y_scale: AxisScale) -> Self {
+        self.x_scale = x_scale;
+        self.y_scale = y_scale;
+        self
+    }
+
+    pub fn with_style(mut self, style: AxesStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// The world positions of the visible ticks on the x and y axes, when seen through
+    /// `camera` in a window of `window_size`, e.g. [`WindowAccess::camera`] and
+    /// [`WindowAccess::window_size`].
+    ///
+    /// [`WindowAccess::camera`]: crate::WindowAccess::camera
+    /// [`WindowAccess::window_size`]: crate::WindowAccess::window_size
+    pub fn ticks(&self, camera: Camera, window_size: PhysicalSize<u32>) -> [Vec<f32>; 2] {
+        let pixels_per_unit = pixels_per_unit(camera, window_size);
+        let spacing = self.style.min_tick_spacing / pixels_per_unit;
+        let Some(visible) = visible_rect(camera, window_size).intersection(&self.rect) else {
+            return [Vec::new(), Vec::new()];
+        };
+        [
+            self.x_scale.ticks(visible.min.x, visible.max.x, spacing),
+            self.y_scale.ticks(visible.min.y, visible.max.y, spacing),
+        ]
+    }
+
+    /// Adds the axes to the line stage of `render`, like [`Self::ticks`].
+    pub fn render(
+        &self,
+        render: &mut RenderController,
+        camera: Camera,
+        window_size: PhysicalSize<u32>,
+    ) {
+        let style = &self.style;
+        let (min, max) = (self.rect.min, self.rect.max);
+        let tick_length = style.tick_length / pixels_per_unit(camera, window_size);
+        let [x_ticks, y_ticks] = self.ticks(camera, window_size);
+
+        render.try_add_stage(RenderStage::Line);
+        if let Some(grid_color) = style.grid_color {
+            for &x in &x_ticks {
+                let from = Vector2::new(x, min.y);
+                render.add_line(Line::new(from, Vector2::new(x, max.y), grid_color));
+            }
+            for &y in &y_ticks {
+                let from = Vector2::new(min.x, y);
+                render.add_line(Line::new(from, Vector2::new(max.x, y), grid_color));
+            }
+        }
+        for &x in &x_ticks {
+            let from = Vector2::new(x, min.y);
+            let to = from - Vector2::new(0.0, tick_length);
+            render.add_line(Line::new(from, to, style.tick_color));
+        }
+        for &y in &y_ticks {
+            let from = Vector2::new(min.x, y);
+            let to = from - Vector2::new(tick_length, 0.0);
+            render.add_line(Line::new(from, to, style.tick_color));
+        }
+        render.add_line(Line::new(min, Vector2::new(max.x, min.y), style.axis_color));
+        render.add_line(Line::new(min, Vector2::new(min.x, max.y), style.axis_color));
+    }
+}
+
+fn pixels_per_unit(camera: Camera, window_size: PhysicalSize<u32>) -> f32 {
+    let min_dim = window_size.width.min(window_size.height).max(1) as f32;
+    camera.zoom * min_dim / 2.0
+}
+
+fn visible_rect(camera: Camera, window_size: PhysicalSize<u32>) -> Rect {
+    let size = Vector2::new(window_size.width as f32, window_size.height as f32);
+    let half_size = size / 2.0 / pixels_per_unit(camera, window_size);
+    Rect::from_corners(camera.target - half_size, camera.target + half_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nice_step, Axes, AxisScale};
+    use crate::{Camera, Rect, Vector2};
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn steps_are_one_two_or_five() {
+        assert_eq!(nice_step(0.7), 1.0);
+        assert_eq!(nice_step(1.5), 2.0);
+        assert_eq!(nice_step(3.0), 5.0);
+        assert_eq!(nice_step(6.0), 10.0);
+        assert!((nice_step(0.013) - 0.02).abs() < 1e-6);
+    }
+
+    #[test]
+    fn linear_ticks_follow_the_zoom() {
+        let axes = Axes::new(Rect::from_corners(
+            Vector2::same(-100.0),
+            Vector2::same(100.0),
+        ));
+        let size = PhysicalSize::new(400, 400);
+
+        // 200 pixels per unit, so 40 pixels is 0.2 units
+        let [x_ticks, _] = axes.ticks(Camera::default(), size);
+        assert_eq!(x_ticks.len(), 11);
+        assert!((x_ticks[1] - x_ticks[0] - 0.2).abs() < 1e-6);
+
+        // zoomed out 10 times, ticks are 10 times further apart
+        let [x_ticks, _] = axes.ticks(Camera::new(Vector2::ZERO, 0.1), size);
+        assert_eq!(x_ticks.len(), 11);
+        assert!((x_ticks[1] - x_ticks[0] - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ticks_stay_inside_the_plot() {
+        let axes = Axes::new(Rect::from_corners(Vector2::ZERO, Vector2::same(0.5)));
+        let [x_ticks, y_ticks] = axes.ticks(Camera::default(), PhysicalSize::new(400, 400));
+        assert_eq!(x_ticks, [0.0, 0.2, 0.4]);
+        assert_eq!(y_ticks, x_ticks);
+    }
+
+    #[test]
+    fn log_ticks_fill_decades_when_zoomed_in() {
+        let scale = AxisScale::Log10;
+        assert_eq!(scale.to_world(1000.0), 3.0);
+        assert_eq!(scale.from_world(2.0), 100.0);
+
+        // a decade spans 10 times the minimum spacing: every mantissa gets a tick
+        assert_eq!(scale.ticks(0.0, 0.99, 0.1).len(), 9);
+        // 4 times: 1, 2 and 5
+        let ticks = scale.ticks(0.0, 1.0, 0.25);
+        let values: Vec<f32> = ticks.iter().map(|&t| scale.from_world(t).round()).collect();
+        assert_eq!(values, [1.0, 2.0, 5.0, 10.0]);
+        // less than a decade: whole decades, skipping some when needed
+        assert_eq!(
+            scale.ticks(0.0, 6.0, 0.8),
+            [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+        assert_eq!(scale.ticks(0.0, 6.0, 1.5), [0.0, 2.0, 4.0, 6.0]);
+    }
+}
@@ -0,0 +1,541 @@
+use super::stages::validate_shader;
+use super::util;
+use std::borrow::Cow;
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
+    CommandEncoder, Device, Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayout, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StoreOp, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDimension, VertexState,
+};
+use winit::dpi::PhysicalSize;
+
+/// What the stages draw into when there are post effects, keeping colors brighter than white
+/// for bloom to pick out.
+pub(crate) const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// A fullscreen pass over the finished frame, see
+/// [`WindowConfig::post_effects`](crate::WindowConfig::post_effects). Lengths are in pixels.
+#[derive(Debug, Clone)]
+pub enum PostEffect {
+    /// Adds a blur of whatever is brighter than `threshold` on top, `intensity` times over,
+    /// reaching about `radius` pixels.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    /// Darkens towards the corners by up to `strength`, starting `radius` of the way out from
+    /// the middle, where 1 is the corners.
+    Vignette { strength: f32, radius: f32 },
+    /// Multiplies colors by `exposure`, then raises them to the power of `1 / gamma`.
+    Exposure { exposure: f32, gamma: f32 },
+    /// A shader of one's own, with a `vs_main` vertex entry point drawn as a single triangle of
+    /// three vertices, meant to cover the screen, and an `fs_main` fragment entry point
+    /// writing to an `rgba16float` target. It may use these bind groups:
+    /// - group 0, binding 0: the frame so far, as a `texture_2d<f32>` of linear colors that
+    ///   may be brighter than 1.
+    /// - group 0, binding 1: a linear `sampler` clamping to the edges.
+    /// - group 0, binding 2: `uniform`, as a uniform buffer, which keeps its size.
+    ///
+    /// WGSL that fails to compile or lacks an entry point is skipped, logging the error.
+    Custom {
+        shader: ShaderModuleDescriptor<'static>,
+        uniform: Vec<u8>,
+    },
+}
+
+impl PostEffect {
+    /// The contents of the effect's uniform buffer.
+    fn uniform(&self) -> Cow<'_, [u8]> {
+        let params = match *self {
+            Self::Bloom {
+                threshold,
+                intensity,
+                radius,
+            } => [threshold, intensity, radius, 0.0],
+            Self::Vignette { strength, radius } => [strength, radius, 0.0, 0.0],
+            Self::Exposure { exposure, gamma } => [exposure, gamma, 0.0, 0.0],
+            Self::Custom { ref uniform, .. } => return Cow::Borrowed(uniform),
+        };
+        Cow::Owned(bytemuck::cast_slice(&params).to_vec())
+    }
+
+    /// Whether `self` runs the same shader as `other`, so switching between them only takes
+    /// writing the uniform.
+    fn same_shader(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom { shader, .. }, Self::Custom { shader: other, .. }) => {
+                match (&shader.source, &other.source) {
+                    (ShaderSource::Wgsl(source), ShaderSource::Wgsl(other)) => source == other,
+                    _ => false,
+                }
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// A [`PostEffect`] with its pipelines created.
+struct PostPass {
+    effect: PostEffect,
+    /// For bloom, the threshold, the two blurs and the composite, in the order they run.
+    /// Empty when a custom shader was invalid.
+    pipelines: Vec<RenderPipeline>,
+    uniform_buffer: Buffer,
+}
+
+/// Runs the [`PostEffect`]s over a frame drawn into [`Self::scene_view`], then copies the
+/// result to the surface.
+pub(crate) struct PostChain {
+    passes: Vec<PostPass>,
+    /// The frame so far, a sampler and a uniform; bloom's composite adds the blurred bright
+    /// parts.
+    effect_layout: BindGroupLayout,
+    bloom_layout: BindGroupLayout,
+    effect_pipeline_layout: PipelineLayout,
+    bloom_pipeline_layout: PipelineLayout,
+    blit_layout: BindGroupLayout,
+    blit_pipeline: RenderPipeline,
+    sampler: Sampler,
+    /// The first is what the stages draw into, and the passes alternate between the two.
+    targets: [TextureView; 2],
+    /// Half size, for bloom to threshold and blur in.
+    bloom_targets: [TextureView; 2],
+}
+
+impl PostChain {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        output_format: TextureFormat,
+        size: PhysicalSize<u32>,
+        effects: Vec<PostEffect>,
+    ) -> Self {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+        let uniform_entry = BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = |label, entries: &[BindGroupLayoutEntry]| {
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries,
+            })
+        };
+        let effect_layout = layout(
+            "post effect bind group layout",
+            &[texture_entry(0), sampler_entry, uniform_entry],
+        );
+        let bloom_layout = layout(
+            "bloom composite bind group layout",
+            &[
+                texture_entry(0),
+                sampler_entry,
+                uniform_entry,
+                texture_entry(3),
+            ],
+        );
+        let blit_layout = layout(
+            "post blit bind group layout",
+            &[texture_entry(0), sampler_entry],
+        );
+
+        let blit_pipeline_layout =
+            util::create_pipeline_layout(device, "post blit pipeline layout", &[&blit_layout]);
+        let blit_shader =
+            device.create_shader_module(built_in("post_blit.wgsl", include_str!("post_blit.wgsl")));
+        let blit_pipeline = create_pipeline(
+            device,
+            "post blit pipeline",
+            &blit_shader,
+            "fs_main",
+            &blit_pipeline_layout,
+            output_format,
+        );
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("post effect sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut chain = Self {
+            passes: Vec::new(),
+            effect_pipeline_layout: util::create_pipeline_layout(
+                device,
+                "post effect pipeline layout",
+                &[&effect_layout],
+            ),
+            bloom_pipeline_layout: util::create_pipeline_layout(
+                device,
+                "bloom composite pipeline layout",
+                &[&bloom_layout],
+            ),
+            effect_layout,
+            bloom_layout,
+            blit_layout,
+            blit_pipeline,
+            sampler,
+            targets: Self::create_targets(device, "post effect target", size),
+            bloom_targets: Self::create_targets(device, "bloom target", Self::half(size)),
+        };
+        chain.set_effects(device, queue, effects);
+        chain
+    }
+
+    fn half(size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        PhysicalSize::new(size.width.div_ceil(2), size.height.div_ceil(2))
+    }
+
+    fn create_targets(device: &Device, label: &str, size: PhysicalSize<u32>) -> [TextureView; 2] {
+        [0, 1].map(|_| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: size.width.max(1),
+                    height: size.height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            texture.create_view(&Default::default())
+        })
+    }
+
+    pub fn resize(&mut self, device: &Device, new_size: PhysicalSize<u32>) {
+        self.targets = Self::create_targets(device, "post effect target", new_size);
+        self.bloom_targets = Self::create_targets(device, "bloom target", Self::half(new_size));
+    }
+
+    /// Replaces the effects, keeping the pipelines of the ones that run the same shader at the
+    /// same place in the chain, so adjusting an effect only rewrites its uniform.
+    pub fn set_effects(&mut self, device: &Device, queue: &Queue, effects: Vec<PostEffect>) {
+        let mut old = std::mem::take(&mut self.passes).into_iter();
+        let passes = effects
+            .into_iter()
+            .enumerate()
+            .map(|(index, effect)| match old.next() {
+                Some(mut pass) if pass.effect.same_shader(&effect) => {
+                    let uniform = effect.uniform();
+                    let len = uniform.len().min(pass.uniform_buffer.size() as usize);
+                    queue.write_buffer(&pass.uniform_buffer, 0, &uniform[..len]);
+                    drop(uniform);
+                    pass.effect = effect;
+                    pass
+                }
+                _ => self.create_pass(device, queue, index, effect),
+            })
+            .collect();
+        self.passes = passes;
+    }
+
+    fn create_pass(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        index: usize,
+        effect: PostEffect,
+    ) -> PostPass {
+        // uniform buffers must be bindable, which an empty or unaligned one isn't
+        let uniform = effect.uniform();
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("post effect uniform"),
+            size: (uniform.len().max(16).next_multiple_of(16)) as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&uniform_buffer, 0, &uniform);
+        drop(uniform);
+
+        let effect_pipeline = |shader: &ShaderModule, entry_point| {
+            create_pipeline(
+                device,
+                "post effect pipeline",
+                shader,
+                entry_point,
+                &self.effect_pipeline_layout,
+                HDR_FORMAT,
+            )
+        };
+        let pipelines = match &effect {
+            PostEffect::Bloom { .. } => {
+                let shader = device.create_shader_module(built_in(
+                    "post_bloom.wgsl",
+                    include_str!("post_bloom.wgsl"),
+                ));
+                let composite = create_pipeline(
+                    device,
+                    "bloom composite pipeline",
+                    &shader,
+                    "fs_main",
+                    &self.bloom_pipeline_layout,
+                    HDR_FORMAT,
+                );
+                vec![
+                    effect_pipeline(&shader, "fs_threshold"),
+                    effect_pipeline(&shader, "fs_blur_x"),
+                    effect_pipeline(&shader, "fs_blur_y"),
+                    composite,
+                ]
+            }
+            PostEffect::Vignette { .. } => {
+                let shader = device.create_shader_module(built_in(
+                    "post_vignette.wgsl",
+                    include_str!("post_vignette.wgsl"),
+                ));
+                vec![effect_pipeline(&shader, "fs_main")]
+            }
+            PostEffect::Exposure { .. } => {
+                let shader = device.create_shader_module(built_in(
+                    "post_exposure.wgsl",
+                    include_str!("post_exposure.wgsl"),
+                ));
+                vec![effect_pipeline(&shader, "fs_main")]
+            }
+            PostEffect::Custom { shader, .. } => {
+                let pipeline = validate_shader(shader).and_then(|()| {
+                    util::catch_validation_error(device, || {
+                        effect_pipeline(&device.create_shader_module(shader.clone()), "fs_main")
+                    })
+                    .map_err(|err| err.to_string())
+                });
+                match pipeline {
+                    Ok(pipeline) => vec![pipeline],
+                    Err(error) => {
+                        log::error!("post effect {index} is invalid, skipping it:\n{error}");
+                        Vec::new()
+                    }
+                }
+            }
+        };
+
+        PostPass {
+            effect,
+            pipelines,
+            uniform_buffer,
+        }
+    }
+
+    /// What the stages draw into.
+    pub fn scene_view(&self) -> &TextureView {
+        &self.targets[0]
+    }
+
+    fn bind_group(
+        &self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        textures: &[&TextureView],
+        uniform: Option<&Buffer>,
+    ) -> BindGroup {
+        let texture = |binding, view| BindGroupEntry {
+            binding,
+            resource: BindingResource::TextureView(view),
+        };
+        let mut entries = vec![
+            texture(0, textures[0]),
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&self.sampler),
+            },
+        ];
+        if let Some(uniform) = uniform {
+            entries.push(BindGroupEntry {
+                binding: 2,
+                resource: uniform.as_entire_binding(),
+            });
+        }
+        if let Some(&extra) = textures.get(1) {
+            entries.push(texture(3, extra));
+        }
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("post effect bind group"),
+            layout,
+            entries: &entries,
+        })
+    }
+
+    /// Encodes the effects over what the stages drew into [`Self::scene_view`], and the copy
+    /// of the result to `output`.
+    pub fn run(&self, device: &Device, command_encoder: &mut CommandEncoder, output: &TextureView) {
+        let draw = |command_encoder: &mut CommandEncoder,
+                    pipeline: &RenderPipeline,
+                    bind_group: &BindGroup,
+                    target: &TextureView| {
+            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("post effect pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(wgpu::Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        };
+
+        let mut current = 0;
+        for pass in self.passes.iter().filter(|pass| !pass.pipelines.is_empty()) {
+            let input = &self.targets[current];
+            let output = &self.targets[1 - current];
+            let uniform = Some(&pass.uniform_buffer);
+            let layout = &self.effect_layout;
+            match pass.pipelines.as_slice() {
+                [threshold, blur_x, blur_y, composite] => {
+                    let [bright, blurred] = &self.bloom_targets;
+                    let steps = [
+                        (threshold, input, bright),
+                        (blur_x, bright, blurred),
+                        (blur_y, blurred, bright),
+                    ];
+                    for (pipeline, from, to) in steps {
+                        let bind_group = self.bind_group(device, layout, &[from], uniform);
+                        draw(command_encoder, pipeline, &bind_group, to);
+                    }
+                    let bind_group =
+                        self.bind_group(device, &self.bloom_layout, &[input, bright], uniform);
+                    draw(command_encoder, composite, &bind_group, output);
+                }
+                [pipeline] => {
+                    let bind_group = self.bind_group(device, layout, &[input], uniform);
+                    draw(command_encoder, pipeline, &bind_group, output);
+                }
+                _ => unreachable!(),
+            }
+            current = 1 - current;
+        }
+
+        let bind_group =
+            self.bind_group(device, &self.blit_layout, &[&self.targets[current]], None);
+        draw(command_encoder, &self.blit_pipeline, &bind_group, output);
+    }
+}
+
+/// A built-in effect, after the vertex shader and bindings they share.
+fn built_in(label: &'static str, source: &str) -> ShaderModuleDescriptor<'static> {
+    ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Wgsl(format!("{}\n{source}", include_str!("post.wgsl")).into()),
+    }
+}
+
+/// A fullscreen pipeline drawing with `entry_point`, replacing what was in the target.
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    shader: &ShaderModule,
+    entry_point: &str,
+    pipeline_layout: &PipelineLayout,
+    format: TextureFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(pipeline_layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point,
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{built_in, PostEffect};
+    use crate::stages::validate_shader;
+    use wgpu::include_wgsl;
+
+    #[test]
+    fn built_in_effects_validate() {
+        for (label, source) in [
+            ("post_blit.wgsl", include_str!("post_blit.wgsl")),
+            ("post_bloom.wgsl", include_str!("post_bloom.wgsl")),
+            ("post_vignette.wgsl", include_str!("post_vignette.wgsl")),
+            ("post_exposure.wgsl", include_str!("post_exposure.wgsl")),
+        ] {
+            validate_shader(&built_in(label, source)).unwrap();
+        }
+    }
+
+    #[test]
+    fn adjusting_an_effect_keeps_its_shader() {
+        let bloom = |threshold| PostEffect::Bloom {
+            threshold,
+            intensity: 1.0,
+            radius: 8.0,
+        };
+        let vignette = PostEffect::Vignette {
+            strength: 0.5,
+            radius: 0.5,
+        };
+        assert!(bloom(1.0).same_shader(&bloom(0.5)));
+        assert!(!bloom(1.0).same_shader(&vignette));
+
+        let custom = |shader| PostEffect::Custom {
+            shader,
+            uniform: vec![1, 2, 3],
+        };
+        let shader = include_wgsl!("post_blit.wgsl");
+        assert!(custom(shader.clone()).same_shader(&custom(shader)));
+        let other = include_wgsl!("post_vignette.wgsl");
+        assert!(!custom(include_wgsl!("post_blit.wgsl")).same_shader(&custom(other)));
+        assert_eq!(bloom(2.0).uniform().len(), 16);
+    }
+}
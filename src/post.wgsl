@@ -0,0 +1,23 @@
+// shared by the built-in post effects, which are appended to it
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // from 0 at the top left to 1 at the bottom right
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var input: texture_2d<f32>;
+
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+// a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return VertexOutput(
+        vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0),
+        vec2<f32>(corner.x, 1.0 - corner.y),
+    );
+}
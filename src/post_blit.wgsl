@@ -0,0 +1,5 @@
+// copies the last pass onto the surface, which encodes it as sRGB
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(input, input_sampler, in.uv);
+}
@@ -0,0 +1,57 @@
+struct Bloom {
+    threshold: f32,
+    intensity: f32,
+    // how far the blur reaches, in full size pixels
+    radius: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Bloom;
+
+// the blurred bright parts, only bound for fs_main
+@group(0) @binding(3)
+var bloom: texture_2d<f32>;
+
+// the parts brighter than the threshold, drawn at half size
+@fragment
+fn fs_threshold(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input, input_sampler, in.uv).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let excess = max(brightness - params.threshold, 0.0) / max(brightness, 1e-4);
+    return vec4<f32>(color * excess, 1.0);
+}
+
+// one direction of a gaussian blur, at half size
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction / vec2<f32>(textureDimensions(input));
+    let reach = params.radius * 0.5;
+    let taps = min(i32(ceil(reach)), 32);
+    let sigma = max(reach / 3.0, 0.5);
+
+    var sum = vec3<f32>(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i++) {
+        let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        sum += textureSampleLevel(input, input_sampler, uv + step * f32(i), 0.0).rgb * weight;
+        total += weight;
+    }
+    return vec4<f32>(sum / total, 1.0);
+}
+
+@fragment
+fn fs_blur_x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_y(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+// the frame with the blurred bright parts added on top
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input, input_sampler, in.uv);
+    let glow = textureSample(bloom, input_sampler, in.uv).rgb;
+    return vec4<f32>(color.rgb + glow * params.intensity, color.a);
+}
@@ -0,0 +1,14 @@
+struct Exposure {
+    exposure: f32,
+    gamma: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Exposure;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input, input_sampler, in.uv);
+    let exposed = max(color.rgb * params.exposure, vec3<f32>(0.0));
+    return vec4<f32>(pow(exposed, vec3<f32>(1.0 / params.gamma)), color.a);
+}
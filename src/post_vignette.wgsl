@@ -0,0 +1,17 @@
+struct Vignette {
+    strength: f32,
+    // how far out the darkening starts, where 1 is the corners
+    radius: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> params: Vignette;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input, input_sampler, in.uv);
+    // 0 in the middle, 1 in the corners
+    let distance = length(in.uv - 0.5) * sqrt(2.0);
+    let darkening = params.strength * smoothstep(params.radius, 1.0, distance);
+    return vec4<f32>(color.rgb * (1.0 - darkening), color.a);
+}
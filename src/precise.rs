@@ -0,0 +1,73 @@
+use super::color::Color;
+use super::lines::Line;
+use super::rect::Rect;
+use super::rect_circle::RectOrCircle;
+use super::vectors::{DVector2, Vector2};
+
+/// A [`Line`] with double precision endpoints, converted relative to the camera on the CPU
+/// before upload. See [`RenderController::add_line_64`](crate::RenderController::add_line_64).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Line64 {
+    from: DVector2,
+    to: DVector2,
+    color: Color,
+}
+
+impl Line64 {
+    pub const fn new(from: DVector2, to: DVector2, color: Color) -> Self {
+        Self { from, to, color }
+    }
+
+    /// The line with its endpoints relative to `origin`.
+    pub fn relative_to(&self, origin: DVector2) -> Line {
+        Line::new(
+            self.from.relative_to(origin),
+            self.to.relative_to(origin),
+            self.color,
+        )
+    }
+
+    /// The axis-aligned box containing the line, in single precision.
+    pub fn bounds(&self) -> Rect {
+        Rect::from_corners(self.from.as_vector2(), self.to.as_vector2())
+    }
+}
+
+/// A [`RectOrCircle`] with a double precision center. Sizes stay single precision, as they
+/// don't suffer from being far from the origin.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectOrCircle64 {
+    center: DVector2,
+    size: Vector2,
+    color: Color,
+}
+
+impl RectOrCircle64 {
+    pub const fn circle(center: DVector2, radius: f32, color: Color) -> Self {
+        Self {
+            center,
+            size: Vector2::new(radius, 0.0),
+            color,
+        }
+    }
+
+    /// `size` is the half size, like [`RectOrCircle::rectangle`] takes.
+    pub const fn rectangle(center: DVector2, size: Vector2, color: Color) -> Self {
+        Self {
+            center,
+            size,
+            color,
+        }
+    }
+
+    /// The shape with its center relative to `origin`.
+    pub fn relative_to(&self, origin: DVector2) -> RectOrCircle {
+        // a zero height makes both constructors produce a circle
+        RectOrCircle::rectangle(self.center.relative_to(origin), self.size, self.color)
+    }
+
+    /// The axis-aligned box containing the shape, in single precision.
+    pub fn bounds(&self) -> Rect {
+        self.relative_to(DVector2::ZERO).bounds()
+    }
+}
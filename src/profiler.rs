@@ -0,0 +1,249 @@
+use super::RenderStage;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device, Features,
+    Maintain, MapMode, QuerySet, QuerySetDescriptor, QueryType, Queue, QUERY_SIZE,
+};
+
+/// Features needed to time stages from inside the render pass.
+pub(crate) const PROFILING_FEATURES: Features =
+    Features::TIMESTAMP_QUERY.union(Features::TIMESTAMP_QUERY_INSIDE_PASSES);
+
+/// Number of readback buffers timestamps are copied into while earlier ones are still being
+/// mapped.
+const READBACK_BUFFERS: usize = 3;
+
+/// A query before and after each of the stages.
+const QUERY_COUNT: u32 = 2 * RenderStage::ALL.len() as u32;
+
+type MapResult = Arc<Mutex<Option<Result<(), BufferAsyncError>>>>;
+
+struct InFlight {
+    buffer: Buffer,
+    stages: Vec<RenderStage>,
+    mapped: MapResult,
+}
+
+/// Times each stage of the render pass with timestamp queries. Results are read back
+/// asynchronously and arrive a frame or two after the frame they describe.
+pub(crate) struct GpuProfiler {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    free: Vec<Buffer>,
+    resolved: Option<InFlight>,
+    in_flight: VecDeque<InFlight>,
+    stage_times: Option<Vec<(RenderStage, Duration)>>,
+}
+
+impl GpuProfiler {
+    /// Requires [`PROFILING_FEATURES`] on `device`.
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let size = (QUERY_COUNT * QUERY_SIZE) as u64;
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("stage timestamps"),
+            ty: QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("stage timestamp resolve buffer"),
+            size,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let free = (0..READBACK_BUFFERS)
+            .map(|_| {
+                device.create_buffer(&BufferDescriptor {
+                    label: Some("stage timestamp readback buffer"),
+                    size,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        Self {
+            query_set,
+            resolve_buffer,
+            period: queue.get_timestamp_period(),
+            free,
+            resolved: None,
+            in_flight: VecDeque::new(),
+            stage_times: None,
+        }
+    }
+
+    /// The query set stages write their timestamps into this frame, or `None` when every
+    /// readback buffer is still in use and the frame goes untimed.
+    pub fn query_set(&self) -> Option<&QuerySet> {
+        (!self.free.is_empty()).then_some(&self.query_set)
+    }
+
+    /// Encodes copying this frame's timestamps for `stages` out of the query set. Must follow
+    /// a render pass that was given [`query_set`](Self::query_set).
+    pub fn resolve(&mut self, encoder: &mut CommandEncoder, stages: &[RenderStage]) {
+        if stages.is_empty() {
+            return;
+        }
+        let Some(buffer) = self.free.pop() else {
+            return;
+        };
+
+        let count = 2 * stages.len() as u32;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &buffer,
+            0,
+            (count * QUERY_SIZE) as u64,
+        );
+
+        self.resolved = Some(InFlight {
+            buffer,
+            stages: stages.to_vec(),
+            mapped: MapResult::default(),
+        });
+    }
+
+    /// Starts mapping the timestamps resolved this frame. Must be called after submitting.
+    pub fn after_submit(&mut self) {
+        if let Some(frame) = self.resolved.take() {
+            let mapped = frame.mapped.clone();
+            frame
+                .buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    *mapped.lock().unwrap() = Some(result);
+                });
+            self.in_flight.push_back(frame);
+        }
+    }
+
+    /// Picks up every frame whose timestamps have finished mapping, keeping the newest.
+    pub fn collect(&mut self, device: &Device) {
+        device.poll(Maintain::Poll);
+
+        while let Some(result) = self
+            .in_flight
+            .front()
+            .and_then(|frame| frame.mapped.lock().unwrap().take())
+        {
+            let frame = self.in_flight.pop_front().unwrap();
+            match result {
+                Ok(()) => {
+                    let stage_times = self.read_stage_times(&frame);
+                    frame.buffer.unmap();
+                    self.stage_times = Some(stage_times);
+                }
+                Err(err) => log::warn!("failed to map stage timestamps: {err}"),
+            }
+            self.free.push(frame.buffer);
+        }
+    }
+
+    /// GPU time spent in each stage of the newest frame read back, in render order.
+    pub fn stage_times(&self) -> Option<&[(RenderStage, Duration)]> {
+        self.stage_times.as_deref()
+    }
+
+    fn read_stage_times(&self, frame: &InFlight) -> Vec<(RenderStage, Duration)> {
+        let mapped = frame.buffer.slice(..).get_mapped_range();
+        let timestamps: &[u64] = bytemuck::cast_slice(&mapped);
+        frame
+            .stages
+            .iter()
+            .zip(timestamps.chunks_exact(2))
+            .map(|(&stage, pair)| {
+                // timestamps aren't guaranteed to be monotonic across a pass
+                let ticks = pair[1].saturating_sub(pair[0]);
+                let nanos = ticks as f64 * self.period as f64;
+                (stage, Duration::from_nanos(nanos as u64))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GpuProfiler, PROFILING_FEATURES};
+    use crate::{setup, util, RenderStage};
+    use std::iter;
+    use wgpu::{
+        Extent3d, Limits, LoadOp, Maintain, Operations, RenderPassColorAttachment,
+        RenderPassDescriptor, StoreOp, TextureDescriptor, TextureDimension, TextureFormat,
+        TextureUsages,
+    };
+
+    #[test]
+    fn every_stage_is_timed() {
+        let Some((device, queue)) = setup::test_device_with(PROFILING_FEATURES, &Limits::default())
+        else {
+            return;
+        };
+        let mut profiler = GpuProfiler::new(&device, &queue);
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        let mut errors = Vec::new();
+        let mut command_encoder = device.create_command_encoder(&Default::default());
+        util::catch_gpu_errors(
+            &device,
+            &mut errors,
+            0,
+            || "profiling".to_string(),
+            || {
+                {
+                    let mut render_pass =
+                        command_encoder.begin_render_pass(&RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[Some(RenderPassColorAttachment {
+                                view: &view,
+                                resolve_target: None,
+                                ops: Operations {
+                                    load: LoadOp::Clear(wgpu::Color::BLACK),
+                                    store: StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: None,
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+                    // the writes the stages make, with every one of them drawn
+                    let query_set = profiler.query_set().unwrap();
+                    for index in 0..RenderStage::ALL.len() as u32 {
+                        render_pass.write_timestamp(query_set, 2 * index);
+                        render_pass.write_timestamp(query_set, 2 * index + 1);
+                    }
+                }
+                profiler.resolve(&mut command_encoder, &RenderStage::ALL);
+                queue.submit(iter::once(command_encoder.finish()));
+            },
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+
+        profiler.after_submit();
+        device.poll(Maintain::Wait);
+        profiler.collect(&device);
+        let stages: Vec<_> = (profiler.stage_times().unwrap().iter())
+            .map(|&(stage, _)| stage)
+            .collect();
+        assert_eq!(stages, RenderStage::ALL);
+    }
+}
@@ -0,0 +1,178 @@
+use super::vectors::Vector2;
+
+/// An axis-aligned rectangle in world space, from `min` to `max` inclusive. The
+/// constructors keep `min` at or below `max` on both axes.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub min: Vector2,
+    pub max: Vector2,
+}
+
+impl Rect {
+    /// The rectangle between two opposite corners, in any order.
+    pub fn from_corners(a: Vector2, b: Vector2) -> Self {
+        debug_assert!(!a.x.is_nan() && !a.y.is_nan(), "NaN corner {a:?}");
+        debug_assert!(!b.x.is_nan() && !b.y.is_nan(), "NaN corner {b:?}");
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// `size` is the full width and height, unlike the half size [`RectOrCircle`] stores.
+    ///
+    /// [`RectOrCircle`]: crate::RectOrCircle
+    pub fn from_center_size(center: Vector2, size: Vector2) -> Self {
+        let half_size = size.abs() / 2.0;
+        Self::from_corners(center - half_size, center + half_size)
+    }
+
+    pub fn center(&self) -> Vector2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vector2 {
+        self.max - self.min
+    }
+
+    /// Whether `point` is inside or on the edge.
+    pub fn contains(&self, point: Vector2) -> bool {
+        debug_assert!(
+            !point.x.is_nan() && !point.y.is_nan(),
+            "NaN point {point:?}"
+        );
+        point.clamp(self.min, self.max) == point
+    }
+
+    /// Whether the rectangles overlap, counting touching edges.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    /// Whether any point of the segment from `from` to `to` is inside or on the edge, even if
+    /// both ends are outside.
+    pub fn intersects_segment(&self, from: Vector2, to: Vector2) -> bool {
+        let delta = to - from;
+        // the part of the segment, as a fraction along it, within both slabs of the rectangle
+        let (mut enter, mut exit) = (0.0_f32, 1.0_f32);
+        for (start, delta, min, max) in [
+            (from.x, delta.x, self.min.x, self.max.x),
+            (from.y, delta.y, self.min.y, self.max.y),
+        ] {
+            if delta == 0.0 {
+                if start < min || start > max {
+                    return false;
+                }
+            } else {
+                let (a, b) = ((min - start) / delta, (max - start) / delta);
+                enter = enter.max(a.min(b));
+                exit = exit.min(a.max(b));
+            }
+        }
+        enter <= exit
+    }
+
+    /// Whether the circle overlaps the rectangle, counting touching edges.
+    pub fn intersects_circle(&self, center: Vector2, radius: f32) -> bool {
+        (center.clamp(self.min, self.max) - center).length_squared() <= radius * radius
+    }
+
+    /// The smallest rectangle containing both.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// The overlapping part, or `None` if the rectangles don't intersect.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.intersects(other).then(|| Self {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        })
+    }
+
+    /// Grows each side outwards by `margin`. Negative margins shrink it, down to its center.
+    pub fn expand(&self, margin: f32) -> Self {
+        debug_assert!(!margin.is_nan(), "NaN margin");
+        let center = self.center();
+        let offset = Vector2::same(margin);
+        Self {
+            min: (self.min - offset).min(center),
+            max: (self.max + offset).max(center),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rect;
+    use crate::Vector2;
+
+    #[test]
+    fn constructors_normalize() {
+        let rect = Rect::from_corners(Vector2::new(2.0, -1.0), Vector2::new(-2.0, 3.0));
+        assert_eq!(rect.min, Vector2::new(-2.0, -1.0));
+        assert_eq!(rect.max, Vector2::new(2.0, 3.0));
+        assert_eq!(rect.center(), Vector2::new(0.0, 1.0));
+        assert_eq!(rect.size(), Vector2::new(4.0, 4.0));
+        assert_eq!(
+            Rect::from_center_size(Vector2::new(0.0, 1.0), Vector2::new(-4.0, 4.0)),
+            rect
+        );
+    }
+
+    #[test]
+    fn containment_and_overlap() {
+        let a = Rect::from_corners(Vector2::ZERO, Vector2::same(2.0));
+        let b = Rect::from_corners(Vector2::same(1.0), Vector2::same(3.0));
+        let far = Rect::from_corners(Vector2::same(5.0), Vector2::same(6.0));
+
+        assert!(a.contains(Vector2::same(2.0)));
+        assert!(!a.contains(Vector2::new(2.5, 1.0)));
+        assert!(a.intersects(&b) && b.intersects(&a));
+        assert!(!a.intersects(&far));
+
+        assert_eq!(
+            a.intersection(&b),
+            Some(Rect::from_corners(Vector2::same(1.0), Vector2::same(2.0)))
+        );
+        assert_eq!(a.intersection(&far), None);
+        assert_eq!(
+            a.union(&far),
+            Rect::from_corners(Vector2::ZERO, Vector2::same(6.0))
+        );
+
+        // touching edges count as overlapping, in a zero-width intersection
+        let right = Rect::from_corners(Vector2::new(2.0, 0.0), Vector2::new(4.0, 2.0));
+        assert_eq!(
+            a.intersection(&right).unwrap().size(),
+            Vector2::new(0.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn expanding() {
+        let rect = Rect::from_corners(Vector2::ZERO, Vector2::new(4.0, 2.0));
+        assert_eq!(
+            rect.expand(1.0),
+            Rect::from_corners(Vector2::same(-1.0), Vector2::new(5.0, 3.0))
+        );
+        assert_eq!(
+            rect.expand(-1.5),
+            Rect::from_corners(Vector2::new(1.5, 1.0), Vector2::new(2.5, 1.0))
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN")]
+    fn nan_corners_are_rejected() {
+        Rect::from_corners(Vector2::new(f32::NAN, 0.0), Vector2::ZERO);
+    }
+}
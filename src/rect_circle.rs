@@ -1,75 +1,332 @@
 use super::camera::CameraTransforms;
 use super::color::{Color, RawColor};
-use super::dynamic_storage::DynamicStorageBuffer;
+use super::compact::{self, CompactInstance, InstanceBuffer};
+use super::culling::GpuCulling;
+use super::rect::Rect;
 use super::util;
 use super::vectors::Vector2;
 use bytemuck::{cast_slice, Pod, Zeroable};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirectArgs};
 use wgpu::{
-    Buffer, BufferUsages, Device, IndexFormat, PrimitiveTopology, RenderPass, RenderPipeline,
-    ShaderModule, TextureFormat,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendState, Buffer, BufferBindingType, BufferUsages,
+    CommandEncoder, Device, IndexFormat, PipelineCache, PipelineLayout, PrimitiveTopology, Queue,
+    RenderPass, RenderPipeline, ShaderModule, ShaderStages, TextureFormat,
 };
 
+/// A filled rectangle or circle. Translucent colors blend over whatever was drawn before
+/// them, so where translucent shapes overlap, the result depends on their order in the
+/// submitted slice and on the order of the stages.
+///
+/// A border, if any, is drawn inside the outline in its own color, covering the whole shape
+/// when it is at least as wide as the shape's half size.
+///
+/// Like [`Line`](crate::Line), the colors are stored premultiplied by alpha.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, Zeroable, Pod)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "ShapeData", from = "ShapeData")
+)]
 pub struct RectOrCircle {
     pub center: Vector2,
     pub size: Vector2,
     pub color: RawColor,
+    pub border_color: RawColor,
+    /// In world units, like `size`. Zero for no border.
+    pub border_width: f32,
+    _padding: [u32; 3],
 }
 
 impl RectOrCircle {
     pub const fn circle(center: Vector2, radius: f32, color: Color) -> Self {
+        Self::circle_raw(center, radius, color.raw_pre_mult())
+    }
+
+    pub const fn rectangle(center: Vector2, size: Vector2, color: Color) -> Self {
+        Self::rectangle_raw(center, size, color.raw_pre_mult())
+    }
+
+    /// A circle filled with `fill`, with a `border_width` wide rim of `border` inside the
+    /// radius.
+    pub const fn circle_with_border(
+        center: Vector2,
+        radius: f32,
+        fill: Color,
+        border: Color,
+        border_width: f32,
+    ) -> Self {
+        Self::circle(center, radius, fill).with_border_raw(border.raw_pre_mult(), border_width)
+    }
+
+    /// A rectangle filled with `fill`, with a `border_width` wide rim of `border` inside its
+    /// edges.
+    pub const fn rectangle_with_border(
+        center: Vector2,
+        size: Vector2,
+        fill: Color,
+        border: Color,
+        border_width: f32,
+    ) -> Self {
+        Self::rectangle(center, size, fill).with_border_raw(border.raw_pre_mult(), border_width)
+    }
+
+    /// A rectangle filling `rect`.
+    pub fn rectangle_from(rect: Rect, color: Color) -> Self {
+        Self::rectangle(rect.center(), rect.size() / 2.0, color)
+    }
+
+    /// Stores `color` as is, so it should already be premultiplied.
+    pub const fn circle_raw(center: Vector2, radius: f32, color: RawColor) -> Self {
         Self {
             center,
             size: Vector2::new(radius, 0.0),
-            color: color.raw(),
+            color,
+            border_color: color,
+            border_width: 0.0,
+            _padding: [0; 3],
         }
     }
 
-    pub const fn rectangle(center: Vector2, size: Vector2, color: Color) -> Self {
+    /// Stores `color` as is, so it should already be premultiplied.
+    pub const fn rectangle_raw(center: Vector2, size: Vector2, color: RawColor) -> Self {
         Self {
             center,
             size,
-            color: color.raw(),
+            color,
+            border_color: color,
+            border_width: 0.0,
+            _padding: [0; 3],
+        }
+    }
+
+    /// The shape with a border of `border_color`, which should already be premultiplied.
+    pub const fn with_border_raw(mut self, border_color: RawColor, border_width: f32) -> Self {
+        self.border_color = border_color;
+        self.border_width = border_width;
+        self
+    }
+
+    pub const fn is_circle(&self) -> bool {
+        self.size.y == 0.0
+    }
+
+    /// The axis-aligned box containing the shape.
+    pub fn bounds(&self) -> Rect {
+        let half_size = match self.is_circle() {
+            true => Vector2::same(self.size.x),
+            false => self.size,
+        };
+        Rect::from_center_size(self.center, half_size * 2.0)
+    }
+
+    /// Whether the shape overlaps `rect`, counting touching edges. Circles only count where
+    /// their outline reaches, not their whole bounds.
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        match self.is_circle() {
+            true => rect.intersects_circle(self.center, self.size.x.abs()),
+            false => rect.intersects(&self.bounds()),
+        }
+    }
+}
+
+/// A [`RectOrCircle`] with its colors packed, mirroring `StoredInstance` in
+/// rect_circle_compact.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub(crate) struct CompactRectOrCircle {
+    center: Vector2,
+    size: Vector2,
+    color: u32,
+    border_color: u32,
+    border_width: f32,
+    _padding: u32,
+}
+
+impl CompactInstance for RectOrCircle {
+    type Compact = CompactRectOrCircle;
+
+    fn compact(&self) -> CompactRectOrCircle {
+        CompactRectOrCircle {
+            center: self.center,
+            size: self.size,
+            color: compact::pack_color(self.color),
+            border_color: compact::pack_color(self.border_color),
+            border_width: self.border_width,
+            _padding: 0,
+        }
+    }
+}
+
+/// What a [`RectOrCircle`] serializes as, saying which shape it is instead of relying on a
+/// zero height, with its colors straight rather than premultiplied.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "RectOrCircle")]
+enum ShapeData {
+    Circle {
+        center: Vector2,
+        radius: f32,
+        color: Color,
+        #[serde(default)]
+        border: Option<BorderData>,
+    },
+    Rectangle {
+        center: Vector2,
+        size: Vector2,
+        color: Color,
+        #[serde(default)]
+        border: Option<BorderData>,
+    },
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Border")]
+struct BorderData {
+    color: Color,
+    width: f32,
+}
+
+#[cfg(feature = "serde")]
+impl From<RectOrCircle> for ShapeData {
+    fn from(shape: RectOrCircle) -> Self {
+        let color = Color::from_pre_mult(shape.color);
+        let border = (shape.border_width > 0.0).then(|| BorderData {
+            color: Color::from_pre_mult(shape.border_color),
+            width: shape.border_width,
+        });
+        match shape.is_circle() {
+            true => Self::Circle {
+                center: shape.center,
+                radius: shape.size.x,
+                color,
+                border,
+            },
+            false => Self::Rectangle {
+                center: shape.center,
+                size: shape.size,
+                color,
+                border,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ShapeData> for RectOrCircle {
+    fn from(data: ShapeData) -> Self {
+        let (shape, border) = match data {
+            ShapeData::Circle {
+                center,
+                radius,
+                color,
+                border,
+            } => (Self::circle(center, radius, color), border),
+            ShapeData::Rectangle {
+                center,
+                size,
+                color,
+                border,
+            } => (Self::rectangle(center, size, color), border),
+        };
+        match border {
+            Some(border) => shape.with_border_raw(border.color.raw_pre_mult(), border.width),
+            None => shape,
         }
     }
 }
 
+/// Mirrors `ShapeSettings` in rect_circle.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+struct ShapeSettings {
+    feather: f32,
+    _padding: [u32; 3],
+}
+
+#[cfg_attr(not(feature = "shader-hot-reload"), allow(dead_code))]
 pub struct RectCircleRenderPipeline {
-    pub instance_data: DynamicStorageBuffer<RectOrCircle>,
+    pub instance_data: InstanceBuffer<RectOrCircle>,
     render_pipeline: RenderPipeline,
+    /// What the pipeline is built from, so it can be rebuilt with another shader or for
+    /// another format.
+    shader: ShaderModule,
+    pipeline_layout: PipelineLayout,
+    texture_format: TextureFormat,
+    sample_count: u32,
 
-    empty_vertex_buffer: Buffer,
     index_buffer: Buffer,
+    indirect_args: Option<Buffer>,
+    culling: Option<GpuCulling>,
+    settings: ShapeSettings,
+    settings_buffer: Buffer,
+    settings_bind_group: BindGroup,
 }
 
 impl RectCircleRenderPipeline {
     pub fn new(
         device: &Device,
-        instance_data: DynamicStorageBuffer<RectOrCircle>,
+        instance_data: InstanceBuffer<RectOrCircle>,
         shader: ShaderModule,
         texture_format: TextureFormat,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
     ) -> Self {
+        let settings = ShapeSettings {
+            feather: 1.0,
+            _padding: [0; 3],
+        };
+        let settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("rect_circle settings uniform"),
+            contents: util::cast_thing(&settings),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let settings_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("rect_circle settings bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let settings_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("rect_circle settings bind group"),
+            layout: &settings_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
         let pipeline_layout = util::create_pipeline_layout(
             device,
+            "rect_circle pipeline layout",
             &[
                 instance_data.bind_group_layout(),
                 &CameraTransforms::create_bind_group_layout(device),
+                &settings_layout,
             ],
         );
 
-        let render_pipeline = util::create_no_vertex_render_pipeline(
+        let render_pipeline = Self::create_render_pipeline(
             device,
             &shader,
             &pipeline_layout,
             texture_format,
-            PrimitiveTopology::TriangleList,
+            sample_count,
+            cache,
         );
 
         const INDEX_BUFFER_CONTENTS: &[u16] = &[0, 1, 2, 0, 2, 3];
         let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("rc index buffer"),
+            label: Some("rect_circle index buffer"),
             contents: cast_slice(INDEX_BUFFER_CONTENTS),
             usage: BufferUsages::INDEX,
         });
@@ -77,17 +334,188 @@ impl RectCircleRenderPipeline {
         Self {
             instance_data,
             render_pipeline,
-            empty_vertex_buffer: util::create_empty_vertex_buffer(device),
+            shader,
+            pipeline_layout,
+            texture_format,
+            sample_count,
             index_buffer,
+            indirect_args: None,
+            culling: None,
+            settings,
+            settings_buffer,
+            settings_bind_group,
         }
     }
 
-    pub fn render(&self, render_pass: &mut RenderPass, camera_transforms: &CameraTransforms) {
+    fn create_render_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        pipeline_layout: &PipelineLayout,
+        texture_format: TextureFormat,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> RenderPipeline {
+        util::create_no_vertex_render_pipeline(
+            device,
+            "rect_circle pipeline",
+            shader,
+            pipeline_layout,
+            texture_format,
+            PrimitiveTopology::TriangleList,
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            sample_count,
+            cache,
+        )
+    }
+
+    /// Rebuilds the pipeline with `shader`, keeping the old one if that fails.
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn set_shader(
+        &mut self,
+        device: &Device,
+        shader: ShaderModule,
+        cache: Option<&PipelineCache>,
+    ) -> Result<(), wgpu::Error> {
+        self.render_pipeline = util::catch_validation_error(device, || {
+            Self::create_render_pipeline(
+                device,
+                &shader,
+                &self.pipeline_layout,
+                self.texture_format,
+                self.sample_count,
+                cache,
+            )
+        })?;
+        self.shader = shader;
+        Ok(())
+    }
+
+    /// Rebuilds the pipeline to draw into `texture_format`.
+    pub fn set_texture_format(
+        &mut self,
+        device: &Device,
+        texture_format: TextureFormat,
+        cache: Option<&PipelineCache>,
+    ) {
+        self.texture_format = texture_format;
+        self.render_pipeline = Self::create_render_pipeline(
+            device,
+            &self.shader,
+            &self.pipeline_layout,
+            texture_format,
+            self.sample_count,
+            cache,
+        );
+    }
+
+    /// Smooths shape edges over `pixels`, see
+    /// [`Renderable::SHAPE_FEATHER`](crate::Renderable::SHAPE_FEATHER).
+    pub fn set_feather(&mut self, queue: &Queue, pixels: f32) {
+        let pixels = pixels.max(0.0);
+        if pixels != self.settings.feather {
+            self.settings.feather = pixels;
+            queue.write_buffer(&self.settings_buffer, 0, util::cast_thing(&self.settings));
+        }
+    }
+
+    /// Switches to drawing as many instances as a GPU buffer of [`DrawIndexedIndirectArgs`]
+    /// says, starting at 0, so the count can be written by a shader. Only the first chunk of
+    /// `instance_data` is drawn.
+    pub fn enable_indirect(&mut self, device: &Device) {
+        let args = DrawIndexedIndirectArgs {
+            index_count: 6,
+            instance_count: 0,
+            first_index: 0,
+            base_vertex: 0,
+            first_instance: 0,
+        };
+        self.indirect_args = Some(util::create_indirect_buffer(device, args.as_bytes()));
+    }
+
+    pub fn indirect_args(&self) -> Option<&Buffer> {
+        self.indirect_args.as_ref()
+    }
+
+    /// Draws only the instances that [`GpuCulling`] finds may be visible, out of as many as
+    /// the indirect args say. Requires [`Self::enable_indirect`] first.
+    pub fn enable_culling(&mut self, device: &Device) {
+        let source_args = (self.indirect_args.as_ref()).expect("culling needs indirect draws");
+        let capacity = self.instance_data.capacity();
+        let compact_colors = self.instance_data.is_compact();
+        let instance_layout = self.instance_data.enable_read_write(device);
+        self.culling = Some(GpuCulling::new(
+            device,
+            instance_layout,
+            compact_colors,
+            source_args,
+            capacity,
+        ));
+    }
+
+    /// Encodes the culling pass, if culling is enabled. Goes after whatever writes the
+    /// instances or their count.
+    pub fn cull(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        camera_transforms: &CameraTransforms,
+    ) {
+        let (Some(culling), Some(source_args), Some(instances)) = (
+            &mut self.culling,
+            &self.indirect_args,
+            self.instance_data.read_write_bind_group(),
+        ) else {
+            return;
+        };
+        culling.run(
+            device,
+            command_encoder,
+            instances,
+            source_args,
+            self.instance_data.capacity(),
+            camera_transforms,
+        );
+    }
+
+    pub fn culling(&self) -> Option<&GpuCulling> {
+        self.culling.as_ref()
+    }
+
+    pub fn culling_mut(&mut self) -> Option<&mut GpuCulling> {
+        self.culling.as_mut()
+    }
+
+    /// Returns the number of draw calls issued.
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_transforms: &CameraTransforms,
+    ) -> u32 {
+        if self.indirect_args.is_none() && self.instance_data.is_empty() {
+            return 0;
+        }
+
         render_pass.set_pipeline(&self.render_pipeline);
-        self.instance_data.bind_to(render_pass, 0);
         camera_transforms.bind_group_to(render_pass, 1);
-        render_pass.set_vertex_buffer(0, self.empty_vertex_buffer.slice(..));
+        render_pass.set_bind_group(2, &self.settings_bind_group, &[]);
+        if let Some(culling) = &self.culling {
+            self.instance_data.bind_to(render_pass, 0);
+            render_pass.set_index_buffer(culling.index_buffer().slice(..), IndexFormat::Uint32);
+            render_pass.draw_indexed_indirect(culling.culled_args(), 0);
+            return 1;
+        }
         render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-        render_pass.draw_indexed(0..6, 0, 0..self.instance_data.len());
+        if let Some(indirect_args) = &self.indirect_args {
+            self.instance_data.bind_to(render_pass, 0);
+            render_pass.draw_indexed_indirect(indirect_args, 0);
+            return 1;
+        }
+        let mut draw_calls = 0;
+        for (bind_group, instances) in self.instance_data.chunks() {
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw_indexed(0..6, 0, instances);
+            draw_calls += 1;
+        }
+        draw_calls
     }
 }
@@ -7,9 +7,20 @@ struct VertexOutput {
 struct InstanceData {
     offset: vec2<f32>,
     size: vec2<f32>,
-    color: vec3<f32>,
+    color: vec4<f32>,
+    border_color: vec4<f32>,
+    border_width: f32,
 }
 
+// instance layout
+// how `RectOrCircle` is stored, replaced by rect_circle_compact.wgsl with compact colors
+alias StoredInstance = InstanceData;
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return stored;
+}
+// end of instance layout
+
 struct Camera {
     aim: vec2<f32>,
     zoom: f32,
@@ -21,27 +32,42 @@ var<uniform> camera: Camera;
 @group(1) @binding(1)
 var<uniform> aspect_transform: vec2<f32>;
 
+@group(1) @binding(2)
+var<uniform> viewport_size: vec2<f32>;
+
+struct ShapeSettings {
+    // how many pixels edges are smoothed over, 0 for hard edges
+    feather: f32,
+}
+
+@group(2) @binding(0)
+var<uniform> settings: ShapeSettings;
+
 @vertex
 fn vs_main(
     @builtin(vertex_index) in_vertex_index: u32,
     @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
-    let in_position = get_vertex(in_vertex_index);
-    let inst_data = instance_data[instance_index];
-
-    let position = in_position
-        * vec2<f32>(
-            inst_data.size.x,
-            select(inst_data.size.y, inst_data.size.x, is_circle(inst_data))
-        )
+    // culled draws index four vertices per instance, as a single instance
+    let index = instance_index + in_vertex_index / 4u;
+    let in_position = get_vertex(in_vertex_index % 4u);
+    let inst_data = load_instance(instance_data[index]);
+
+    // grown by the feather width, as the smoothing reaches outside the outline
+    let pixel = 2.0 / (viewport_size * camera.zoom * abs(aspect_transform));
+    let unclamped = in_position * (half_size(inst_data) + settings.feather * pixel)
         + inst_data.offset;
-        
-    let screen_position = (position - camera.aim) * camera.zoom * aspect_transform;
+
+    // cut down to the screen, so zoomed in shapes don't rasterize far past it, and ones
+    // entirely off screen collapse to an edge of it with no area
+    let screen_scale = camera.zoom * aspect_transform;
+    let screen_position = clamp((unclamped - camera.aim) * screen_scale, vec2(-1.0), vec2(1.0));
+    let position = screen_position / screen_scale + camera.aim;
 
     return VertexOutput(
         vec4<f32>(screen_position, 0.0, 1.0),
         position,
-        instance_index,
+        index,
     );
 }
 
@@ -50,23 +76,54 @@ fn is_circle(inst_data: InstanceData) -> bool {
     return inst_data.size.y == 0.0;
 }
 
+fn half_size(inst_data: InstanceData) -> vec2<f32> {
+    return abs(select(inst_data.size, inst_data.size.xx, is_circle(inst_data)));
+}
+
+// how much of a pixel `distance` from an edge is inside it, smoothed over `feather`
+fn coverage(distance: f32, feather: f32) -> f32 {
+    if feather <= 0.0 {
+        return select(0.0, 1.0, distance <= 0.0);
+    }
+    return clamp(0.5 - distance / feather, 0.0, 1.0);
+}
+
 @group(0) @binding(0)
-var<storage, read> instance_data: array<InstanceData>;
+var<storage, read> instance_data: array<StoredInstance>;
 
 @fragment
 fn fs_main(vertex_data: VertexOutput) -> @location(0) vec4<f32> {
     let index = vertex_data.instance_index;
-    let inst_data = instance_data[index];
+    let inst_data = load_instance(instance_data[index]);
 
+    // signed distance to the outline, negative inside
+    let half_size = half_size(inst_data);
+    let offset = vertex_data.position - inst_data.offset;
+    var distance: f32;
     if is_circle(inst_data) {
-        let offset = vertex_data.position - inst_data.offset;
-        let dist_sqr = dot(offset, offset);
-        let radius = inst_data.size.x;
-        if dist_sqr > radius * radius {
-            discard;
-        }
+        distance = length(offset) - half_size.x;
+    } else {
+        let outside = abs(offset) - half_size;
+        distance = max(outside.x, outside.y);
+    }
+
+    // world units per pixel, the same along both axes
+    let feather = settings.feather * fwidth(vertex_data.position.x);
+    let alpha = coverage(distance, feather);
+    if alpha <= 0.0 {
+        discard;
+    }
+
+    // the fill is the shape shrunk by the border width, if anything is left of it
+    var fill = 1.0;
+    let border_width = inst_data.border_width;
+    if border_width >= min(half_size.x, half_size.y) {
+        fill = 0.0;
+    } else if border_width > 0.0 {
+        fill = coverage(distance + border_width, feather);
     }
-    return vec4<f32>(inst_data.color, 1.0);
+    // the colors are premultiplied, so coverage scales all of them
+    return mix(inst_data.border_color, inst_data.color, fill) * alpha;
 }
 
 // this is a workaround to not being able to use const arrays
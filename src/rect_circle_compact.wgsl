@@ -0,0 +1,29 @@
+// instance layout
+// how `RectOrCircle` is stored with compact colors, its colors packed into sRGB bytes
+struct StoredInstance {
+    offset: vec2<f32>,
+    size: vec2<f32>,
+    color: u32,
+    border_color: u32,
+    border_width: f32,
+}
+
+fn load_instance(stored: StoredInstance) -> InstanceData {
+    return InstanceData(
+        stored.offset,
+        stored.size,
+        unpack_color(stored.color),
+        unpack_color(stored.border_color),
+        stored.border_width,
+    );
+}
+
+// straight sRGB bytes back to linear, premultiplied like the full layout's colors
+fn unpack_color(packed: u32) -> vec4<f32> {
+    let srgb = unpack4x8unorm(packed);
+    let low = srgb.rgb / 12.92;
+    let high = pow((srgb.rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    let linear = select(high, low, srgb.rgb <= vec3<f32>(0.04045));
+    return vec4<f32>(linear * srgb.a, srgb.a);
+}
+// end of instance layout
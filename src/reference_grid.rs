@@ -0,0 +1,314 @@
+use super::camera::CameraTransforms;
+use super::color::{Color, RawColor};
+use super::util;
+use super::vectors::{DVector2, Vector2};
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendState, Buffer, BufferBindingType, BufferUsages, Device,
+    PipelineCache, PipelineLayout, PrimitiveTopology, Queue, RenderPass, RenderPipeline,
+    ShaderModule, ShaderStages, TextureFormat,
+};
+use winit::dpi::PhysicalSize;
+
+/// How [`RenderStage::Grid`](crate::RenderStage::Grid) looks. Lines are `spacing` times a
+/// power of `subdivisions` apart, picked so the finest are at least `min_spacing` pixels
+/// apart. They fade in as zooming in spreads them, and every `subdivisions`th line is drawn in
+/// `major_color`. Lengths other than `spacing` are in pixels, so they stay the same at any
+/// zoom.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridStyle {
+    /// In world units.
+    pub spacing: f32,
+    /// Lines per major line, at least 2.
+    pub subdivisions: u32,
+    pub minor_color: Color,
+    pub major_color: Color,
+    /// The line `y = 0`, or `None` to draw it like the others.
+    pub x_axis_color: Option<Color>,
+    /// The line `x = 0`, or `None` to draw it like the others.
+    pub y_axis_color: Option<Color>,
+    pub line_width: f32,
+    pub min_spacing: f32,
+}
+
+impl Default for GridStyle {
+    fn default() -> Self {
+        Self {
+            spacing: 1.0,
+            subdivisions: 10,
+            minor_color: Color::GRAY.with_alpha(0.2),
+            major_color: Color::GRAY.with_alpha(0.5),
+            x_axis_color: Some(Color::RED.with_alpha(0.8)),
+            y_axis_color: Some(Color::GREEN.with_alpha(0.8)),
+            line_width: 1.0,
+            min_spacing: 8.0,
+        }
+    }
+}
+
+/// Mirrors `GridSettings` in reference_grid.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Zeroable, Pod)]
+struct GridSettings {
+    offset: Vector2,
+    axes: Vector2,
+    spacing: f32,
+    subdivisions: f32,
+    pixel_size: f32,
+    line_width: f32,
+    viewport_size: Vector2,
+    _padding: [u32; 2],
+    level_colors: [RawColor; 3],
+    x_axis_color: RawColor,
+    y_axis_color: RawColor,
+}
+
+impl GridSettings {
+    /// The levels of detail of `style` around `target` at `zoom`, worked out in double
+    /// precision so the lines stay on their world positions however far the camera is from
+    /// the origin.
+    fn new(style: &GridStyle, target: DVector2, zoom: f32, size: PhysicalSize<u32>) -> Self {
+        let min_dim = size.width.min(size.height).max(1) as f64;
+        let pixels_per_unit = zoom as f64 * min_dim / 2.0;
+        let subdivisions = style.subdivisions.max(2) as f64;
+
+        // the finest level with lines at least `min_spacing` apart, faded in by how far past
+        // that they are, reaching full strength as they get as far apart as the next level
+        let min_spacing = style.min_spacing.max(1.0) as f64 / pixels_per_unit;
+        let level = (min_spacing / style.spacing as f64).log(subdivisions);
+        if !level.is_finite() {
+            return Self::default();
+        }
+        let finest = level.ceil();
+        let fade = (finest - level) as f32;
+        let spacing = style.spacing as f64 * subdivisions.powf(finest);
+        let period = style.spacing as f64 * subdivisions.powf(finest + 2.0);
+
+        let axis_color =
+            |color: Option<Color>| color.map_or(RawColor::default(), Color::raw_pre_mult);
+        let minor = style.minor_color;
+        Self {
+            offset: Vector2::new(
+                target.x.rem_euclid(period) as f32,
+                target.y.rem_euclid(period) as f32,
+            ),
+            axes: -target.as_vector2(),
+            spacing: spacing as f32,
+            subdivisions: subdivisions as f32,
+            pixel_size: pixels_per_unit.recip() as f32,
+            line_width: style.line_width,
+            viewport_size: Vector2::new(size.width as f32, size.height as f32),
+            _padding: [0; 2],
+            level_colors: [
+                minor.with_alpha(minor.alpha() * fade).raw_pre_mult(),
+                minor.lerp(style.major_color, fade).raw_pre_mult(),
+                style.major_color.raw_pre_mult(),
+            ],
+            x_axis_color: axis_color(style.x_axis_color),
+            y_axis_color: axis_color(style.y_axis_color),
+        }
+    }
+}
+
+/// Draws [`RenderStage::Grid`](crate::RenderStage::Grid) with one triangle over the screen,
+/// working out the lines per pixel, so it costs the same at any zoom.
+pub struct GridRenderPipeline {
+    render_pipeline: RenderPipeline,
+    /// What the pipeline is built from, so it can be rebuilt for another format.
+    shader: ShaderModule,
+    pipeline_layout: PipelineLayout,
+    sample_count: u32,
+    size: PhysicalSize<u32>,
+    settings: GridSettings,
+    settings_buffer: Buffer,
+    settings_bind_group: BindGroup,
+}
+
+impl GridRenderPipeline {
+    pub fn new(
+        device: &Device,
+        texture_format: TextureFormat,
+        size: PhysicalSize<u32>,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> Self {
+        let settings = GridSettings::default();
+        let settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("grid settings uniform"),
+            contents: util::cast_thing(&settings),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let settings_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("grid settings bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let settings_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("grid settings bind group"),
+            layout: &settings_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout =
+            util::create_pipeline_layout(device, "grid pipeline layout", &[&settings_layout]);
+        let shader = device.create_shader_module(wgpu::include_wgsl!("reference_grid.wgsl"));
+        let render_pipeline = Self::create_render_pipeline(
+            device,
+            &shader,
+            &pipeline_layout,
+            texture_format,
+            sample_count,
+            cache,
+        );
+
+        Self {
+            render_pipeline,
+            shader,
+            pipeline_layout,
+            sample_count,
+            size,
+            settings,
+            settings_buffer,
+            settings_bind_group,
+        }
+    }
+
+    fn create_render_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        pipeline_layout: &PipelineLayout,
+        texture_format: TextureFormat,
+        sample_count: u32,
+        cache: Option<&PipelineCache>,
+    ) -> RenderPipeline {
+        util::create_no_vertex_render_pipeline(
+            device,
+            "grid pipeline",
+            shader,
+            pipeline_layout,
+            texture_format,
+            PrimitiveTopology::TriangleList,
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            sample_count,
+            cache,
+        )
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.size = new_size;
+    }
+
+    /// Rebuilds the pipeline to draw into `texture_format`.
+    pub fn set_texture_format(
+        &mut self,
+        device: &Device,
+        texture_format: TextureFormat,
+        cache: Option<&PipelineCache>,
+    ) {
+        self.render_pipeline = Self::create_render_pipeline(
+            device,
+            &self.shader,
+            &self.pipeline_layout,
+            texture_format,
+            self.sample_count,
+            cache,
+        );
+    }
+
+    /// Uploads the lines `style` draws through `camera_transforms`, if they changed.
+    pub fn prepare(
+        &mut self,
+        queue: &Queue,
+        style: &GridStyle,
+        camera_transforms: &CameraTransforms,
+    ) {
+        let settings = GridSettings::new(
+            style,
+            camera_transforms.target_64(),
+            camera_transforms.camera.zoom,
+            self.size,
+        );
+        if settings != self.settings {
+            self.settings = settings;
+            queue.write_buffer(&self.settings_buffer, 0, util::cast_thing(&settings));
+        }
+    }
+
+    /// Returns the number of draw calls issued.
+    pub fn render(&self, render_pass: &mut RenderPass) -> u32 {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.settings_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GridSettings, GridStyle};
+    use crate::{Color, DVector2};
+    use winit::dpi::PhysicalSize;
+
+    const SIZE: PhysicalSize<u32> = PhysicalSize::new(400, 400);
+
+    #[test]
+    fn finer_lines_fade_in_while_zooming_in() {
+        let style = GridStyle::default();
+
+        // 200 pixels per unit: lines 0.1 apart are 20 pixels apart, and 0.01 only 2
+        let settings = GridSettings::new(&style, DVector2::ZERO, 1.0, SIZE);
+        assert!((settings.spacing - 0.1).abs() < 1e-6);
+        assert_eq!(settings.pixel_size, 1.0 / 200.0);
+        let fade = settings.level_colors[0].alpha() / style.minor_color.alpha();
+        assert!((fade - (8f32 / 20.0).log10().abs()).abs() < 1e-5, "{fade}");
+
+        // 8.5 pixels apart, the finest lines have only just appeared, and the next ones up
+        // are still minor
+        let settings = GridSettings::new(&style, DVector2::ZERO, 0.425, SIZE);
+        assert!((settings.spacing - 0.1).abs() < 1e-6);
+        assert!(settings.level_colors[0].alpha() < style.minor_color.alpha() * 0.05);
+        let minor_alpha = style.minor_color.alpha();
+        assert!((settings.level_colors[1].alpha() - minor_alpha).abs() < 0.01);
+        assert_eq!(settings.level_colors[2], style.major_color.raw_pre_mult());
+    }
+
+    #[test]
+    fn far_away_lines_stay_on_world_multiples() {
+        let style = GridStyle {
+            x_axis_color: None,
+            y_axis_color: Some(Color::BLUE),
+            ..GridStyle::default()
+        };
+        let target = DVector2::new(1e9 + 0.25, -3.5);
+        let settings = GridSettings::new(&style, target, 1.0, SIZE);
+        // lines 10 units apart are the coarsest drawn
+        assert_eq!(settings.offset.x, 0.25);
+        assert_eq!(settings.offset.y, 6.5);
+        assert_eq!(settings.x_axis_color.alpha(), 0.0);
+        assert_eq!(settings.y_axis_color, Color::BLUE.raw_pre_mult());
+    }
+
+    #[test]
+    fn degenerate_grids_draw_nothing() {
+        let style = GridStyle::default();
+        for (spacing, zoom) in [(0.0, 1.0), (1.0, 0.0), (f32::NAN, 1.0)] {
+            let style = GridStyle { spacing, ..style };
+            let settings = GridSettings::new(&style, DVector2::ZERO, zoom, SIZE);
+            assert_eq!(settings, GridSettings::default());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+struct GridSettings {
+    // the camera target modulo the coarsest spacing drawn, so lines land on world multiples
+    offset: vec2<f32>,
+    // where the world axes cross, relative to the camera target
+    axes: vec2<f32>,
+    // of the finest lines drawn, in world units
+    spacing: f32,
+    subdivisions: f32,
+    // world units per pixel
+    pixel_size: f32,
+    // in pixels
+    line_width: f32,
+    viewport_size: vec2<f32>,
+    // premultiplied, finest first, with the finest faded by how close together its lines are
+    level_colors: array<vec4<f32>, 3>,
+    x_axis_color: vec4<f32>,
+    y_axis_color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> settings: GridSettings;
+
+// a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let corner = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// how much of a pixel `distance` pixels from the middle of a line is covered by it
+fn coverage(distance: f32) -> f32 {
+    return clamp(settings.line_width * 0.5 + 0.5 - distance, 0.0, 1.0);
+}
+
+// coverage of the lines `spacing` apart along both axes
+fn lines(position: vec2<f32>, spacing: f32) -> f32 {
+    let distance = abs(position - spacing * round(position / spacing)) / settings.pixel_size;
+    return coverage(min(distance.x, distance.y));
+}
+
+// premultiplied `top` blended over `bottom`
+fn over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    return top + bottom * (1.0 - top.a);
+}
+
+@fragment
+fn fs_main(@builtin(position) pixel: vec4<f32>) -> @location(0) vec4<f32> {
+    // world units from the camera target, with y up
+    let offset = (pixel.xy - settings.viewport_size * 0.5) * vec2<f32>(1.0, -1.0)
+        * settings.pixel_size;
+    let position = offset + settings.offset;
+
+    // coarser lines are also finer ones, so drawing them over each other leaves the coarsest
+    // color on each line
+    var color = vec4<f32>(0.0);
+    var spacing = settings.spacing;
+    for (var level = 0; level < 3; level++) {
+        color = over(settings.level_colors[level] * lines(position, spacing), color);
+        spacing *= settings.subdivisions;
+    }
+
+    let axes = abs(offset - settings.axes) / settings.pixel_size;
+    color = over(settings.y_axis_color * coverage(axes.x), color);
+    color = over(settings.x_axis_color * coverage(axes.y), color);
+    return color;
+}
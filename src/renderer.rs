@@ -0,0 +1,1245 @@
+use super::camera::{clamp_zoom, scroll_zoom, CameraTransforms};
+use super::capture::FrameRecorder;
+use super::compute::ComputeRunner;
+use super::diagnostics::{AdapterReport, GpuReport};
+#[cfg(feature = "shader-hot-reload")]
+use super::hot_reload::ShaderWatcher;
+use super::input::{self, GestureTracker, InputTracker};
+use super::lines::LineAccumulation;
+use super::overlay::DebugOverlay;
+use super::pipeline_cache::DiskPipelineCache;
+use super::post::{PostChain, PostEffect, HDR_FORMAT};
+use super::profiler::{GpuProfiler, PROFILING_FEATURES};
+use super::stages::StagePipelines;
+use super::stats::RenderStats;
+use super::timing::{FrameTiming, TickTimer};
+#[cfg(feature = "egui")]
+use super::ui::UiLayer;
+use super::util::profile_span;
+use super::{
+    setup, util, Camera, CameraBookmarks, CameraTransition, FullscreenMode, GpuContext, GpuError,
+    GpuErrorKind, InputMap, Modifiers, RenderController, RenderError, RenderStage, Renderable,
+    UpdateMode, Vector2, WindowAccess, WindowCommand, WindowConfig,
+};
+use std::cell::RefCell;
+use std::iter;
+use std::mem::replace;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use wgpu::{
+    AdapterInfo, CommandEncoder, CommandEncoderDescriptor, CompositeAlphaMode, Device,
+    DeviceLostReason, Features, InstanceDescriptor, LoadOp, Operations, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, StoreOp, Surface, SurfaceConfiguration,
+    SurfaceError, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceEvent, ElementState, MouseButton, WindowEvent};
+use winit::event_loop::ControlFlow;
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{CursorGrabMode, Window};
+
+const MOVE_DIRS: [(KeyCode, Vector2); 4] = [
+    (KeyCode::KeyW, Vector2::UP),
+    (KeyCode::KeyA, Vector2::LEFT),
+    (KeyCode::KeyS, Vector2::DOWN),
+    (KeyCode::KeyD, Vector2::RIGHT),
+];
+
+/// Everything [`run`](crate::run) drives, for applications that own their event loop.
+///
+/// Feed it every [`WindowEvent`] of its window through [`Self::handle_event`], raw mouse
+/// motion through [`Self::handle_device_event`], and call [`Self::tick`] once per loop
+/// iteration (on `AboutToWait`). Afterwards, apply [`Self::control_flow`] and exit once
+/// [`Self::exit_requested`] is set. Redraws happen on `RedrawRequested`, which `tick` requests.
+pub struct Renderer<'w> {
+    window: &'w Window,
+    config: WindowConfig,
+
+    surface: Surface<'w>,
+    surface_config: SurfaceConfiguration,
+    texture_format: TextureFormat,
+    device: Device,
+    queue: Queue,
+    device_lost: Arc<Mutex<Option<String>>>,
+    adapter_info: AdapterInfo,
+    adapter_features: Features,
+    gpu_report: GpuReport,
+    /// What the stages draw in: the surface format, or [`HDR_FORMAT`] with post effects.
+    stage_format: TextureFormat,
+    sample_count: u32,
+    msaa_view: Option<TextureView>,
+    post_chain: Option<PostChain>,
+    camera_transforms: CameraTransforms,
+    camera_transition: Option<CameraTransition>,
+    bookmarks: CameraBookmarks,
+    stages: StagePipelines,
+    profiler: Option<GpuProfiler>,
+    compute_stages: Vec<ComputeRunner>,
+    pipeline_cache: Option<DiskPipelineCache>,
+    command_encoder: CommandEncoder,
+    encoder_label: String,
+    render_stats: RenderStats,
+    pass_label: String,
+    render_controller: RenderController,
+    debug_overlay: DebugOverlay,
+
+    frame_timing: FrameTiming,
+    tick_timer: TickTimer,
+    last_update: Instant,
+    /// In reactive mode, whether something happened that the app hasn't updated for yet.
+    wake_pending: bool,
+    control_flow: ControlFlow,
+    exit_requested: bool,
+    suspended: bool,
+    paused: bool,
+    step_pending: bool,
+
+    input: InputTracker,
+    modifiers: Modifiers,
+    gestures: GestureTracker,
+    input_map: InputMap,
+    mouse_pos_screen: Vector2,
+    mouse_pos_world: Vector2,
+    // the world position is only tracked while there's a cursor to track
+    cursor_inside: bool,
+    /// Whether the cursor has moved over the window yet, so its position means something.
+    cursor_seen: bool,
+    focused: bool,
+    cursor_grab: CursorGrabMode,
+
+    inner_size: PhysicalSize<u32>,
+    scale_factor: f64,
+    fullscreen_mode: FullscreenMode,
+    /// Whether the surface can be copied from, which recording depends on.
+    can_record: bool,
+    recorder: Option<FrameRecorder>,
+    #[cfg(feature = "egui")]
+    ui: UiLayer,
+    #[cfg(feature = "shader-hot-reload")]
+    shader_watcher: ShaderWatcher,
+}
+
+impl<'w> Renderer<'w> {
+    /// Sets up rendering to `window`, which is configured according to `config` where that
+    /// applies to an existing window (fullscreen and IME), and calls [`Renderable::init`].
+    pub fn new<A: Renderable>(
+        window: &'w Window,
+        config: WindowConfig,
+        application: &mut A,
+    ) -> Result<Self, RenderError> {
+        let fullscreen_mode = config.fullscreen;
+        if fullscreen_mode != FullscreenMode::Windowed {
+            window.set_fullscreen(fullscreen_mode.to_winit(window));
+        }
+        if config.ime_allowed {
+            window.set_ime_allowed(true);
+        }
+
+        let instance = wgpu::Instance::new(InstanceDescriptor {
+            backends: config.backends,
+            flags: config.instance_flags,
+            dx12_shader_compiler: config.dx12_shader_compiler.clone(),
+            gles_minor_version: config.gles_minor_version,
+        });
+
+        let surface = instance.create_surface(window)?;
+        let (adapter, fallback_adapter) = setup::request_adapter(
+            &instance,
+            config.power_preference,
+            config.allow_fallback_adapter,
+            Some(&surface),
+        )?;
+        let adapter_info = adapter.get_info();
+        let adapter_features = adapter.features();
+
+        let profiling = config.gpu_profiling && adapter_features.contains(PROFILING_FEATURES);
+        if config.gpu_profiling && !profiling {
+            log::warn!(
+                "{} lacks timestamp queries, GPU profiling is off",
+                adapter_info.name
+            );
+        }
+        let caching = config.pipeline_cache_dir.is_some()
+            && adapter_features.contains(Features::PIPELINE_CACHE);
+        let mut features = config.required_features;
+        if profiling {
+            features |= PROFILING_FEATURES;
+        }
+        if caching {
+            features |= Features::PIPELINE_CACHE;
+        }
+        let (device, queue) = setup::request_device(
+            &adapter,
+            features,
+            &config.required_limits,
+            config.trace_dir.as_deref(),
+        )?;
+
+        let device_lost = Arc::new(Mutex::new(None));
+        {
+            let device_lost = device_lost.clone();
+            device.set_device_lost_callback(move |reason, message| {
+                if let DeviceLostReason::Unknown | DeviceLostReason::Destroyed = reason {
+                    *device_lost.lock().unwrap() = Some(message);
+                }
+            });
+        }
+
+        let capability = surface.get_capabilities(&adapter);
+        let texture_format = capability
+            .formats
+            .iter()
+            .copied()
+            .find(TextureFormat::is_srgb)
+            .ok_or(RenderError::NoSrgbSurfaceFormat)?;
+
+        let gpu_report = GpuReport {
+            adapter: AdapterReport::new(&adapter),
+            fallback_adapter,
+            surface_formats: capability.formats.clone(),
+            present_modes: capability.present_modes,
+            surface_format: texture_format,
+            present_mode: config.present_mode,
+        };
+        log::info!("rendering with {gpu_report}");
+
+        let can_record = capability.usages.contains(TextureUsages::COPY_SRC)
+            && FrameRecorder::supports_format(texture_format);
+
+        let size = window.inner_size();
+        let surface_config = SurfaceConfiguration {
+            usage: match can_record {
+                true => TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                false => TextureUsages::RENDER_ATTACHMENT,
+            },
+            format: texture_format,
+            width: size.width,
+            height: size.height,
+            present_mode: config.present_mode,
+            alpha_mode: CompositeAlphaMode::Auto,
+            desired_maximum_frame_latency: 2,
+            view_formats: Vec::new(),
+        };
+
+        // some platforms (Wayland, minimized windows) start out zero-sized; the surface is then
+        // configured by the first non-zero resize
+        let suspended = size.width == 0 || size.height == 0;
+        if !suspended {
+            surface.configure(&device, &surface_config);
+        }
+
+        // without effects to start with, the stages draw straight into the surface
+        let stage_format = match config.post_effects.is_empty() {
+            true => texture_format,
+            false => HDR_FORMAT,
+        };
+        let supported_sample_counts = adapter
+            .get_texture_format_features(stage_format)
+            .flags
+            .supported_sample_counts();
+        let sample_count = supported_sample_counts
+            .into_iter()
+            .filter(|&count| count <= config.msaa_samples.max(1))
+            .max()
+            .unwrap_or(1);
+        if sample_count != config.msaa_samples {
+            log::warn!(
+                "{}x MSAA is unsupported, using {sample_count}x",
+                config.msaa_samples
+            );
+        }
+        let msaa_view = util::create_msaa_view(&device, stage_format, size, sample_count);
+        let post_chain = (!config.post_effects.is_empty()).then(|| {
+            let effects = config.post_effects.clone();
+            PostChain::new(&device, &queue, texture_format, size, effects)
+        });
+
+        application.init(&GpuContext::new(
+            &device,
+            &queue,
+            texture_format,
+            &adapter_info,
+        ));
+
+        let mut camera_transforms = CameraTransforms::new(&device, size);
+        camera_transforms.camera = application.initial_camera();
+        camera_transforms.double_precision = config.double_precision;
+
+        let pipeline_cache = config
+            .pipeline_cache_dir
+            .as_deref()
+            .filter(|_| caching)
+            .and_then(|dir| DiskPipelineCache::load(&device, &adapter_info, dir));
+        let cache = pipeline_cache.as_ref().map(DiskPipelineCache::cache);
+
+        let mut stages = StagePipelines::new(
+            &device,
+            stage_format,
+            size,
+            A::USE_LINE_ALPHA,
+            LineAccumulation::detect(&adapter, &device),
+            sample_count,
+            config.initial_line_capacity,
+            config.initial_shape_capacity,
+            config.compact_colors,
+            &config.shaders,
+            cache,
+        );
+        stages.line_render.set_blend_mode(A::LINE_BLEND_MODE);
+        stages
+            .rect_circle_render
+            .set_feather(&queue, A::SHAPE_FEATHER);
+        stages.set_growth_strategy(config.growth_strategy);
+
+        stages.set_shrink_policy(config.shrink_policy);
+        stages.set_buffer_count(&device, config.instance_buffers);
+        if config.indirect_draws {
+            stages.enable_indirect(&device);
+            if config.gpu_culling {
+                stages.enable_culling(&device);
+            }
+        } else if config.gpu_culling {
+            log::warn!("gpu_culling needs indirect_draws, drawing everything");
+        }
+
+        let compute_stages = application
+            .compute_stages()
+            .into_iter()
+            .map(|stage| ComputeRunner::new(&device, stage, &mut stages, cache))
+            .collect();
+        let profiler = profiling.then(|| GpuProfiler::new(&device, &queue));
+        let label_prefix = config.label_prefix.as_deref().unwrap_or_default();
+        let encoder_label = format!("{label_prefix}frame encoder");
+        let pass_label = format!("{label_prefix}frame render pass");
+        let command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some(&encoder_label),
+        });
+        let debug_overlay = DebugOverlay::new(
+            &device,
+            config.debug_overlay,
+            config.compact_colors,
+            texture_format,
+        );
+        let gestures = GestureTracker::new(config.double_click_interval, config.drag_threshold);
+        #[cfg(feature = "egui")]
+        let ui = UiLayer::new(&device, texture_format);
+
+        Ok(Self {
+            window,
+            surface,
+            surface_config,
+            texture_format,
+            device,
+            queue,
+            device_lost,
+            adapter_info,
+            adapter_features,
+            gpu_report,
+            stage_format,
+            sample_count,
+            msaa_view,
+            post_chain,
+            camera_transforms,
+            camera_transition: None,
+            bookmarks: CameraBookmarks::new(),
+            stages,
+            profiler,
+            compute_stages,
+            pipeline_cache,
+            command_encoder,
+            encoder_label,
+            render_stats: RenderStats::default(),
+            pass_label,
+            render_controller: RenderController::new(),
+            debug_overlay,
+
+            frame_timing: FrameTiming::new(),
+            tick_timer: TickTimer::new(A::TICK_RATE, A::MAX_CATCH_UP_TICKS),
+            last_update: Instant::now(),
+            wake_pending: true,
+            control_flow: ControlFlow::Poll,
+            exit_requested: false,
+            suspended,
+            paused: false,
+            step_pending: false,
+
+            input: InputTracker::default(),
+            modifiers: Modifiers::default(),
+            gestures,
+            input_map: application.input_map(),
+            mouse_pos_screen: Vector2::default(),
+            mouse_pos_world: Vector2::default(),
+            cursor_inside: false,
+            cursor_seen: false,
+            focused: true,
+            cursor_grab: CursorGrabMode::None,
+
+            inner_size: size,
+            scale_factor: window.scale_factor(),
+            fullscreen_mode,
+            can_record,
+            recorder: None,
+            #[cfg(feature = "egui")]
+            ui,
+            #[cfg(feature = "shader-hot-reload")]
+            shader_watcher: ShaderWatcher::new(&config.shader_paths),
+            config,
+        })
+    }
+
+    pub fn window(&self) -> &'w Window {
+        self.window
+    }
+
+    /// How the event loop should wait before the next iteration.
+    pub fn control_flow(&self) -> ControlFlow {
+        self.control_flow
+    }
+
+    /// Whether the window should close, through a close request the application allowed,
+    /// [`WindowAccess::request_exit`] or [`Renderable::EXIT_KEY`].
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    fn check_device(&self) -> Result<(), RenderError> {
+        match self.device_lost.lock().unwrap().take() {
+            Some(message) => Err(RenderError::DeviceLost(message)),
+            None => Ok(()),
+        }
+    }
+
+    fn access(&self, delta_seconds: f32) -> WindowAccess<'_> {
+        WindowAccess {
+            input: &self.input,
+            modifiers: self.modifiers,
+            gestures: &self.gestures,
+            input_map: &self.input_map,
+            camera_transforms: &self.camera_transforms,
+            bookmarks: &self.bookmarks,
+            mouse_pos_screen: self.mouse_pos_screen,
+            mouse_pos_world: self.mouse_pos_world,
+            cursor_inside: self.cursor_inside,
+            focused: self.focused,
+            frame_timing: &self.frame_timing,
+            delta_seconds,
+            window_size: self.inner_size,
+            scale_factor: self.scale_factor,
+            fullscreen_mode: self.fullscreen_mode,
+            surface_format: self.texture_format,
+            device: &self.device,
+            queue: &self.queue,
+            adapter_info: &self.adapter_info,
+            adapter_features: self.adapter_features,
+            gpu_report: &self.gpu_report,
+            stages: &self.stages,
+            render_stats: self.render_stats,
+            gpu_stage_times: self.profiler.as_ref().and_then(GpuProfiler::stage_times),
+            paused: self.paused,
+            debug_overlay: self.debug_overlay.enabled,
+            recording: self
+                .recorder
+                .as_ref()
+                .map(|recorder| (recorder.captured_frames(), recorder.dropped_frames())),
+            commands: RefCell::default(),
+        }
+    }
+
+    fn msaa_texture_bytes(&self) -> u64 {
+        if self.msaa_view.is_none() {
+            return 0;
+        }
+        let pixels = self.inner_size.width.max(1) as u64 * self.inner_size.height.max(1) as u64;
+        let pixel_bytes = self.stage_format.block_copy_size(None).unwrap_or(4) as u64;
+        pixels * self.sample_count as u64 * pixel_bytes
+    }
+
+    fn clear_input_edges(&mut self) {
+        self.input.clear_edges();
+        self.gestures.clear_edges();
+    }
+
+    fn update_mouse_world(&mut self) {
+        self.mouse_pos_world = self
+            .camera_transforms
+            .screen_to_world(self.mouse_pos_screen, self.inner_size);
+    }
+
+    /// Tells `application` about a button with where the cursor is, through the camera as
+    /// it is now.
+    fn mouse_button<A: Renderable>(
+        &self,
+        application: &mut A,
+        button: MouseButton,
+        state: ElementState,
+    ) {
+        application.on_mouse_event(button, state);
+        let (screen, world) = match self.cursor_seen {
+            true => {
+                let world = self
+                    .camera_transforms
+                    .screen_to_world(self.mouse_pos_screen, self.inner_size);
+                (Some(self.mouse_pos_screen), Some(world))
+            }
+            false => (None, None),
+        };
+        application.on_mouse_button(button, state, screen, world);
+    }
+
+    fn move_camera(&mut self, camera: Camera) {
+        self.camera_transforms.set_camera(camera);
+        self.camera_transforms.update_camera(&self.queue);
+        if self.cursor_inside {
+            self.update_mouse_world();
+        }
+    }
+
+    /// Creates the post effect chain the config started out without, moving the stages over
+    /// to draw into its [`HDR_FORMAT`] target.
+    fn start_post_chain(&mut self, effects: Vec<PostEffect>) {
+        let cache = self.pipeline_cache.as_ref().map(DiskPipelineCache::cache);
+        self.stage_format = HDR_FORMAT;
+        self.stages
+            .set_texture_format(&self.device, self.stage_format, cache);
+        self.msaa_view = util::create_msaa_view(
+            &self.device,
+            self.stage_format,
+            self.inner_size,
+            self.sample_count,
+        );
+        self.post_chain = Some(PostChain::new(
+            &self.device,
+            &self.queue,
+            self.texture_format,
+            self.inner_size,
+            effects,
+        ));
+    }
+
+    /// Moves the camera, runs however many ticks are due, applies what they requested and
+    /// asks for a redraw. Call once per event loop iteration.
+    pub fn tick<A: Renderable>(&mut self, application: &mut A) -> Result<(), RenderError> {
+        self.check_device()?;
+
+        #[cfg(feature = "shader-hot-reload")]
+        if self.shader_watcher.poll(
+            &self.device,
+            &mut self.stages,
+            self.pipeline_cache.as_ref().map(DiskPipelineCache::cache),
+        ) {
+            self.wake_pending = true;
+        }
+
+        let reactive = matches!(self.config.update_mode, UpdateMode::Reactive { .. });
+        if let UpdateMode::Reactive { max_wait } = self.config.update_mode {
+            let now = Instant::now();
+            let camera_moving = self.camera_transition.is_some()
+                || MOVE_DIRS
+                    .iter()
+                    .any(|&(code, _)| self.input.is_key_down(code));
+            if !self.wake_pending && !camera_moving && now < self.last_update + max_wait {
+                self.control_flow = ControlFlow::WaitUntil(self.last_update + max_wait);
+                return Ok(());
+            }
+
+            self.wake_pending = false;
+            self.last_update = now;
+            self.control_flow = match camera_moving {
+                true => ControlFlow::Poll,
+                false => ControlFlow::WaitUntil(now + max_wait),
+            };
+        }
+
+        self.frame_timing.begin_frame(self.config.max_delta);
+        let delta_seconds = match A::TICK_RATE {
+            Some(tick_rate) if !reactive => tick_rate.recip() as f32,
+            _ => self.frame_timing.delta().as_secs_f32(),
+        };
+
+        {
+            let mut any = false;
+            let camera = &mut self.camera_transforms.camera;
+            for &(_, dir) in MOVE_DIRS
+                .iter()
+                .filter(|&&(code, _)| self.input.is_key_down(code))
+            {
+                let speed_mult = match self.modifiers.shift() {
+                    true => A::SHIFT_SPEED_MULT,
+                    false => 1.0,
+                };
+
+                camera.target += dir * A::CAMERA_MOVE_SPEED / camera.zoom * speed_mult;
+                any = true;
+            }
+
+            if any {
+                self.camera_transition = None;
+            } else if let Some(transition) = &self.camera_transition {
+                let (camera, done) = transition.sample(Instant::now());
+                self.camera_transforms.set_camera(camera);
+                if done {
+                    self.camera_transition = None;
+                }
+            }
+
+            if (any || self.camera_transition.is_some()) && self.cursor_inside {
+                self.update_mouse_world();
+            }
+        }
+
+        if self.config.double_precision {
+            self.camera_transforms.rebase();
+        }
+        self.camera_transforms.update_camera(&self.queue);
+
+        let key_pressed =
+            |key: Option<KeyCode>| key.is_some_and(|key| self.input.is_key_pressed(key));
+        let exit_key_pressed = key_pressed(A::EXIT_KEY);
+        let pause_key_pressed = key_pressed(A::PAUSE_KEY);
+        let step_key_pressed = key_pressed(A::STEP_KEY);
+        if pause_key_pressed {
+            self.paused = !self.paused;
+        }
+        self.step_pending |= self.paused && step_key_pressed;
+
+        // input edges are kept until a tick has seen them
+        let mut commands = Vec::new();
+        let ticking = !self.suspended || self.config.tick_while_minimized;
+        let mut tick_count = match ticking {
+            false => 0,
+            true if reactive => 1,
+            true => self.tick_timer.advance(),
+        };
+        if self.paused && ticking {
+            tick_count = u32::from(replace(&mut self.step_pending, false));
+            if tick_count == 0 {
+                // nothing will see these edges, and they shouldn't fire on resume
+                self.clear_input_edges();
+            }
+        }
+        for _ in 0..tick_count {
+            profile_span!("tick");
+            let access = self.access(delta_seconds);
+            application.tick(&access);
+            commands.extend(access.commands.into_inner());
+
+            self.clear_input_edges();
+        }
+
+        let mut exit = exit_key_pressed;
+        for command in commands {
+            match command {
+                WindowCommand::Exit => exit = true,
+                WindowCommand::SetFullscreen(mode) => {
+                    self.window.set_fullscreen(mode.to_winit(self.window));
+                    self.fullscreen_mode = mode;
+                }
+                WindowCommand::RequestRedraw if reactive => {
+                    self.wake_pending = true;
+                    self.control_flow = ControlFlow::Poll;
+                }
+                WindowCommand::RequestRedraw => {}
+                WindowCommand::SetPaused(paused) => self.paused = paused,
+                WindowCommand::StepOnce => {
+                    self.paused = true;
+                    self.step_pending = true;
+                }
+                WindowCommand::SetCursorVisible(visible) => self.window.set_cursor_visible(visible),
+                WindowCommand::SetCursorIcon(icon) => self.window.set_cursor_icon(icon),
+                WindowCommand::SetCursorGrab(mode) => match self.window.set_cursor_grab(mode) {
+                    Ok(()) => self.cursor_grab = mode,
+                    Err(err) => log::error!("failed to set cursor grab to {mode:?}: {err}"),
+                },
+                WindowCommand::StartRecording(sink) => {
+                    if !self.can_record {
+                        log::error!(
+                            "recording is unsupported for {:?} surfaces",
+                            self.texture_format
+                        );
+                        continue;
+                    }
+                    if let Some(recorder) = self.recorder.replace(FrameRecorder::new(sink)) {
+                        recorder.finish(&self.device, self.texture_format);
+                    }
+                }
+                WindowCommand::StopRecording => {
+                    if let Some(recorder) = self.recorder.take() {
+                        recorder.finish(&self.device, self.texture_format);
+                    }
+                }
+                WindowCommand::SetComputeUniform(index, data) => {
+                    match self.compute_stages.get(index) {
+                        Some(stage) => stage.set_uniform(&self.queue, &data),
+                        None => log::error!("there's no compute stage {index}"),
+                    }
+                }
+                WindowCommand::SetComputeWorkgroups(index, workgroups) => {
+                    match self.compute_stages.get_mut(index) {
+                        Some(stage) => stage.set_workgroups(workgroups),
+                        None => log::error!("there's no compute stage {index}"),
+                    }
+                }
+                WindowCommand::SetLineAlpha(use_alpha) => {
+                    self.stages
+                        .line_render
+                        .set_use_alpha(&self.queue, use_alpha);
+                }
+                WindowCommand::SetLineBlendMode(blend_mode) => {
+                    self.stages.line_render.set_blend_mode(blend_mode);
+                }
+                WindowCommand::SetLineGlowUnits(units) => {
+                    self.stages.line_render.set_glow_units(&self.queue, units);
+                }
+                WindowCommand::SetLineExposure(exposure) => {
+                    self.stages.line_render.set_exposure(&self.queue, exposure);
+                }
+                WindowCommand::SetLineTonemap(tonemap) => {
+                    self.stages.line_render.set_tonemap(&self.queue, tonemap);
+                }
+                WindowCommand::SetShapeFeather(pixels) => {
+                    self.stages
+                        .rect_circle_render
+                        .set_feather(&self.queue, pixels);
+                }
+                WindowCommand::SetPostEffects(effects) => match &mut self.post_chain {
+                    Some(post_chain) => post_chain.set_effects(&self.device, &self.queue, effects),
+                    None if effects.is_empty() => {}
+                    None => self.start_post_chain(effects),
+                },
+                WindowCommand::SetDebugOverlay(shown) => self.debug_overlay.enabled = shown,
+                WindowCommand::SetInputMap(input_map) => self.input_map = input_map,
+                WindowCommand::SetCamera(mut camera) => {
+                    camera.zoom = clamp_zoom(
+                        camera.zoom,
+                        A::ZOOM_RANGE,
+                        self.camera_transforms.camera.zoom,
+                    );
+                    self.camera_transition = None;
+                    self.move_camera(camera);
+                }
+                WindowCommand::TransitionCamera(mut camera, duration) if duration.is_zero() => {
+                    camera.zoom = clamp_zoom(
+                        camera.zoom,
+                        A::ZOOM_RANGE,
+                        self.camera_transforms.camera.zoom,
+                    );
+                    self.camera_transition = None;
+                    self.move_camera(camera);
+                }
+                WindowCommand::TransitionCamera(mut camera, duration) => {
+                    camera.zoom = clamp_zoom(
+                        camera.zoom,
+                        A::ZOOM_RANGE,
+                        self.camera_transforms.camera.zoom,
+                    );
+                    let from = self.camera_transforms.world_camera();
+                    self.camera_transition = Some(CameraTransition::new(from, camera, duration));
+                    if reactive {
+                        self.wake_pending = true;
+                        self.control_flow = ControlFlow::Poll;
+                    }
+                }
+                WindowCommand::SaveBookmark(slot) => {
+                    let camera = self.camera_transforms.world_camera();
+                    self.bookmarks.save(slot, camera);
+                }
+                WindowCommand::SetBookmarks(bookmarks) => self.bookmarks = bookmarks,
+                WindowCommand::SetCameraTarget64(target) => {
+                    self.camera_transforms.set_target_64(target);
+                    self.camera_transforms.update_camera(&self.queue);
+                    if self.cursor_inside {
+                        self.update_mouse_world();
+                    }
+                }
+            }
+        }
+
+        if exit && (!self.config.confirm_exit_requests || application.on_close_requested()) {
+            self.exit_requested = true;
+        } else if !self.suspended {
+            self.window.request_redraw();
+        }
+        Ok(())
+    }
+
+    /// Updates input state and forwards the event to `application`. Redraws on
+    /// `RedrawRequested`.
+    pub fn handle_event<A: Renderable>(
+        &mut self,
+        application: &mut A,
+        event: &WindowEvent,
+    ) -> Result<(), RenderError> {
+        self.check_device()?;
+
+        if *event != WindowEvent::RedrawRequested {
+            self.wake_pending = true;
+        }
+
+        let consumed = application.on_raw_event(event);
+        let essential = matches!(
+            event,
+            WindowEvent::Resized(_)
+                | WindowEvent::ScaleFactorChanged { .. }
+                | WindowEvent::RedrawRequested
+        );
+        if consumed && !essential {
+            return Ok(());
+        }
+
+        #[cfg(feature = "egui")]
+        if self.ui.on_window_event(event, self.scale_factor) {
+            return Ok(());
+        }
+
+        match event {
+            &WindowEvent::Resized(new_size) => {
+                self.inner_size = new_size;
+                self.suspended = new_size.width == 0 || new_size.height == 0;
+                if self.suspended {
+                    return Ok(());
+                }
+
+                self.surface_config.width = new_size.width;
+                self.surface_config.height = new_size.height;
+                self.surface.configure(&self.device, &self.surface_config);
+
+                self.camera_transforms
+                    .update_aspect_ratio(&self.queue, new_size);
+
+                self.stages.resize(&self.device, &self.queue, new_size);
+                self.msaa_view = util::create_msaa_view(
+                    &self.device,
+                    self.stage_format,
+                    new_size,
+                    self.sample_count,
+                );
+                if let Some(post_chain) = &mut self.post_chain {
+                    post_chain.resize(&self.device, new_size);
+                }
+
+                if self.cursor_inside {
+                    self.update_mouse_world();
+                }
+
+                application.on_resize(new_size, self.scale_factor);
+            }
+            &WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = scale_factor;
+                if !self.suspended {
+                    application.on_resize(self.inner_size, scale_factor);
+                }
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.cursor_inside = true;
+                application.on_cursor_inside_changed(true);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_inside = false;
+                application.on_cursor_inside_changed(false);
+            }
+            &WindowEvent::Focused(focused) => {
+                self.focused = focused;
+                if !focused {
+                    // releases are never delivered once focus is gone
+                    let (keys, buttons) = self.input.release_all();
+                    for code in keys {
+                        application.on_key_event(code, ElementState::Released, false);
+                    }
+                    for button in buttons {
+                        self.mouse_button(application, button, ElementState::Released);
+                        self.gestures.release(button, self.mouse_pos_screen);
+                    }
+                    self.modifiers = Modifiers::default();
+                }
+                application.on_focus_changed(focused);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_inside = true;
+                self.cursor_seen = true;
+                self.mouse_pos_screen = Vector2::new(position.x as f32, position.y as f32);
+
+                self.update_mouse_world();
+                self.gestures.cursor_moved(self.mouse_pos_screen);
+            }
+            &WindowEvent::MouseInput { button, state, .. } => {
+                self.mouse_button(application, button, state);
+
+                self.input.button(button, state);
+                match state {
+                    ElementState::Pressed => {
+                        self.gestures
+                            .press(button, self.mouse_pos_screen, self.mouse_pos_world);
+                    }
+                    ElementState::Released => {
+                        self.gestures.release(button, self.mouse_pos_screen);
+                    }
+                }
+            }
+            WindowEvent::CloseRequested if application.on_close_requested() => {
+                self.exit_requested = true;
+            }
+            &WindowEvent::MouseWheel { delta, .. } => {
+                let delta = input::scroll_lines(delta);
+                if application.on_scroll(delta, self.modifiers) {
+                    return Ok(());
+                }
+
+                self.camera_transition = None;
+                let camera = &mut self.camera_transforms.camera;
+                camera.zoom = scroll_zoom(camera.zoom, A::ZOOM_RATE, delta.y, A::ZOOM_RANGE);
+                if self.cursor_inside {
+                    self.update_mouse_world();
+                }
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let state = event.state;
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    application.on_key_event(code, state, event.repeat);
+                    self.input.key(code, state, event.repeat);
+                }
+
+                application.on_logical_key_event(&event.logical_key, state, event.repeat);
+                if let Some(text) = &event.text {
+                    application.on_text_input(text);
+                }
+            }
+            WindowEvent::DroppedFile(path) => {
+                application.on_file_dropped(path, self.mouse_pos_world);
+            }
+            WindowEvent::HoveredFile(path) => {
+                application.on_file_hovered(path, self.mouse_pos_world);
+            }
+            WindowEvent::HoveredFileCancelled => {
+                application.on_file_hover_cancelled();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = Modifiers::new(modifiers.state());
+            }
+            WindowEvent::Ime(ime) => {
+                application.on_ime(ime);
+            }
+            WindowEvent::RedrawRequested => self.redraw(application)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Forwards raw mouse motion while the cursor is grabbed.
+    pub fn handle_device_event<A: Renderable>(&mut self, application: &mut A, event: &DeviceEvent) {
+        if application.on_raw_device_event(event) {
+            return;
+        }
+        if let &DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            if self.cursor_grab != CursorGrabMode::None && self.focused {
+                application.on_mouse_delta(Vector2::new(x as f32, y as f32));
+            }
+        }
+    }
+
+    /// Renders and presents a frame. Skips it while the window is minimized or the surface
+    /// can't be drawn to right now.
+    pub fn redraw<A: Renderable>(&mut self, application: &mut A) -> Result<(), RenderError> {
+        if self.suspended {
+            return Ok(());
+        }
+
+        // acquired before anything is encoded, so a skipped frame leaves the per-frame command
+        // encoder untouched
+        let acquired = self.surface.get_current_texture();
+        let reconfigure = || self.surface.configure(&self.device, &self.surface_config);
+        let Some(texture) = acquire_frame(acquired, reconfigure)? else {
+            return Ok(());
+        };
+
+        self.frame_timing.record_present();
+        self.debug_overlay.record_present();
+
+        let render_controller = &mut self.render_controller;
+        render_controller.clear();
+        render_controller.paused = self.paused;
+        render_controller.interpolation_alpha = match self.config.update_mode {
+            UpdateMode::Reactive { .. } => 1.0,
+            UpdateMode::Continuous => self.tick_timer.alpha(),
+        };
+        {
+            profile_span!("render");
+            application.render(render_controller);
+            render_controller.sort_stages();
+            render_controller.resolve_origin(self.camera_transforms.origin);
+        }
+
+        self.stages.advance_frame();
+        let frame = self.frame_timing.frame_index();
+        let mut gpu_errors = Vec::new();
+        {
+            profile_span!("upload");
+            for stage in [RenderStage::Line, RenderStage::RectsAndCircles] {
+                let instances = render_controller.instance_count(stage);
+                let bytes = instances as u64 * self.stages.instance_size(stage);
+                let context =
+                    || format!("uploading {instances} {stage:?} instances ({bytes} bytes)");
+                let max_bytes = self.stages.max_instance_bytes(stage);
+                if bytes > max_bytes {
+                    gpu_errors.push(GpuError {
+                        frame,
+                        context: context(),
+                        kind: GpuErrorKind::TooManyInstances {
+                            stage,
+                            bytes,
+                            max_bytes,
+                        },
+                    });
+                }
+                util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+                    (self.stages).upload_stage(&self.device, &self.queue, render_controller, stage)
+                });
+            }
+        }
+        self.stages
+            .prepare_grid(&self.queue, render_controller, &self.camera_transforms);
+        self.debug_overlay.upload(
+            &self.device,
+            &self.queue,
+            render_controller,
+            self.frame_timing.fps(),
+            self.render_stats.total_bytes(),
+        );
+        let context = || "encoding the compute passes".to_string();
+        util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+            {
+                profile_span!("pre_render");
+                self.stages
+                    .pre_render(&self.device, &mut self.command_encoder);
+            }
+            for compute_stage in &self.compute_stages {
+                compute_stage.run(&mut self.command_encoder, &self.stages);
+            }
+            self.stages.cull(
+                &self.device,
+                &mut self.command_encoder,
+                &self.camera_transforms,
+            );
+        });
+
+        let view = texture
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+        let stage_view = self
+            .post_chain
+            .as_ref()
+            .map_or(&view, PostChain::scene_view);
+
+        // begin drawing
+        let context = || "encoding the render pass".to_string();
+        let mut draw_calls =
+            util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+                profile_span!("render_pass");
+                let mut render_pass =
+                    self.command_encoder
+                        .begin_render_pass(&RenderPassDescriptor {
+                            label: Some(&self.pass_label),
+                            color_attachments: &[Some(RenderPassColorAttachment {
+                                view: self.msaa_view.as_ref().unwrap_or(stage_view),
+                                resolve_target: self.msaa_view.as_ref().map(|_| stage_view),
+                                ops: Operations {
+                                    load: LoadOp::Clear(wgpu::Color::BLACK),
+                                    store: StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: None,
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+
+                self.stages.render(
+                    &mut render_pass,
+                    render_controller,
+                    &self.camera_transforms,
+                    self.profiler.as_ref().and_then(GpuProfiler::query_set),
+                )
+            });
+        if let Some(post_chain) = &self.post_chain {
+            let context = || "encoding the post effects".to_string();
+            util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+                profile_span!("post_effects");
+                post_chain.run(&self.device, &mut self.command_encoder, &view);
+            });
+        }
+
+        #[cfg(feature = "egui")]
+        self.ui.draw(
+            &self.device,
+            &self.queue,
+            &mut self.command_encoder,
+            &view,
+            self.inner_size,
+            self.scale_factor,
+            application,
+        );
+
+        let context = || "drawing the debug overlay".to_string();
+        draw_calls += util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+            (self.debug_overlay).render(&mut self.command_encoder, &view, &self.camera_transforms)
+        });
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.resolve(&mut self.command_encoder, &render_controller.render_order);
+        }
+
+        self.render_stats = RenderStats {
+            msaa_texture_bytes: self.msaa_texture_bytes(),
+            draw_calls,
+            ..self.stages.take_stats()
+        };
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.capture(&self.device, &mut self.command_encoder, &texture.texture);
+        }
+
+        let new_ce = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&self.encoder_label),
+            });
+        let old_ce = replace(&mut self.command_encoder, new_ce);
+        let context = || "submitting the frame".to_string();
+        util::catch_gpu_errors(&self.device, &mut gpu_errors, frame, context, || {
+            profile_span!("submit");
+            self.queue.submit(iter::once(old_ce.finish()));
+        });
+        self.stages.after_submit(&self.device);
+
+        for error in gpu_errors {
+            let message = error.to_string();
+            application.on_gpu_error(error);
+            if self.config.panic_on_gpu_error {
+                panic!("{message}");
+            }
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.after_submit();
+            profiler.collect(&self.device);
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.after_submit();
+            recorder.collect(&self.device, self.texture_format, false);
+        }
+
+        profile_span!("present");
+        texture.present();
+        Ok(())
+    }
+}
+
+impl Drop for Renderer<'_> {
+    /// Delivers the frames a recording still has in flight and saves the pipeline cache.
+    fn drop(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish(&self.device, self.texture_format);
+        }
+        if let Some(pipeline_cache) = &self.pipeline_cache {
+            pipeline_cache.store();
+        }
+    }
+}
+
+/// The texture a surface gave for this frame, or `None` to skip the frame when the surface
+/// can't be drawn to right now, calling `reconfigure` if it's outdated or lost.
+fn acquire_frame<T>(
+    acquired: Result<T, SurfaceError>,
+    reconfigure: impl FnOnce(),
+) -> Result<Option<T>, RenderError> {
+    match acquired {
+        Ok(texture) => Ok(Some(texture)),
+        Err(SurfaceError::Outdated | SurfaceError::Lost) => {
+            log::info!("surface outdated or lost, reconfiguring");
+            reconfigure();
+            Ok(None)
+        }
+        Err(SurfaceError::Timeout) => {
+            log::warn!("surface timed out, skipping frame");
+            Ok(None)
+        }
+        Err(SurfaceError::OutOfMemory) => Err(SurfaceError::OutOfMemory.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::acquire_frame;
+    use crate::{setup, util};
+    use std::iter;
+    use std::mem::replace;
+    use wgpu::{
+        CommandEncoderDescriptor, Extent3d, LoadOp, Maintain, Operations,
+        RenderPassColorAttachment, RenderPassDescriptor, StoreOp, SurfaceError, Texture,
+        TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    };
+
+    /// Only the error handling of [`acquire_frame`]; a real surface needs a window.
+    #[test]
+    fn acquire_frame_reconfigures_on_lost_or_outdated_and_skips_the_frame() {
+        let Some((device, queue)) = setup::test_device() else {
+            return;
+        };
+        // stands in for the surface texture
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        // kept across frames like the renderer's
+        let mut command_encoder = device.create_command_encoder(&Default::default());
+        let mut reconfigured = 0;
+        let mut errors = Vec::new();
+        for frame in 0..3 {
+            for error in [
+                SurfaceError::Outdated,
+                SurfaceError::Lost,
+                SurfaceError::Timeout,
+            ] {
+                let acquired = acquire_frame(Err::<&Texture, _>(error), || reconfigured += 1);
+                assert!(acquired.unwrap().is_none());
+            }
+            let texture = acquire_frame(Ok(&texture), || reconfigured += 1)
+                .unwrap()
+                .unwrap();
+
+            let view = texture.create_view(&Default::default());
+            command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(wgpu::Color::RED),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let new_ce = device.create_command_encoder(&CommandEncoderDescriptor::default());
+            let old_ce = replace(&mut command_encoder, new_ce);
+            let context = || "submitting the frame".to_string();
+            util::catch_gpu_errors(&device, &mut errors, frame, context, || {
+                queue.submit(iter::once(old_ce.finish()));
+            });
+            device.poll(Maintain::Wait);
+        }
+        assert_eq!(reconfigured, 6);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+}
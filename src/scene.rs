@@ -0,0 +1,302 @@
+use super::camera::Camera;
+use super::color::{Color, RawColor};
+use super::lines::Line;
+use super::rect_circle::RectOrCircle;
+use super::vectors::Vector2;
+use super::{RenderController, RenderStage};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::{fs, io};
+
+const MAGIC: &[u8; 8] = b"WGRSCENE";
+/// Files with another major version can't be read; newer minor versions only add sections
+/// and fields that older readers skip.
+const VERSION_MAJOR: u16 = 1;
+const VERSION_MINOR: u16 = 1;
+
+const CAMERA_SECTION: &[u8; 4] = b"CAMR";
+const LINE_SECTION: &[u8; 4] = b"LINE";
+const RECT_SECTION: &[u8; 4] = b"RECT";
+
+/// Bytes of a line record in this version: endpoints, premultiplied color, glow and flags.
+const LINE_RECORD_SIZE: u32 = 4 * 9 + 4;
+/// Bytes of a rectangle or circle record in this version: center, size, color, border color
+/// and border width.
+const RECT_RECORD_SIZE: u32 = 4 * 13;
+/// Bytes of a rectangle or circle record in version 1.0, which had no border.
+const RECT_RECORD_SIZE_1_0: u32 = 4 * 8;
+const SCREEN_SPACE_FLAG: u32 = 1;
+
+/// Shapes and a camera to keep beyond a single frame, e.g. to save a constructed scene and
+/// load it in another session.
+///
+/// The file format is a versioned header followed by sections of a tag and a length. Files
+/// from later minor versions load, skipping the sections and record fields this version
+/// doesn't know.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub camera: Camera,
+    pub lines: Vec<Line>,
+    pub rects: Vec<RectOrCircle>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the shapes to `render`, rectangles and circles below lines. Stages already added
+    /// keep their place.
+    pub fn submit(&self, render: &mut RenderController) {
+        if !self.rects.is_empty() {
+            render.try_add_stage(RenderStage::RectsAndCircles);
+            for &shape in &self.rects {
+                render.add_rect_or_circle(shape);
+            }
+        }
+        if !self.lines.is_empty() {
+            render.try_add_stage(RenderStage::Line);
+            for &line in &self.lines {
+                render.add_line(line);
+            }
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// The scene in the format [`Self::save`] writes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION_MAJOR.to_le_bytes());
+        bytes.extend(VERSION_MINOR.to_le_bytes());
+
+        let mut camera = Vec::new();
+        put_vector(&mut camera, self.camera.target);
+        camera.extend(self.camera.zoom.to_le_bytes());
+        put_section(&mut bytes, CAMERA_SECTION, &camera);
+
+        let mut lines = records_header(self.lines.len(), LINE_RECORD_SIZE);
+        for line in &self.lines {
+            put_vector(&mut lines, line.from());
+            put_vector(&mut lines, line.to());
+            put_color(&mut lines, line.color());
+            lines.extend(line.glow().to_le_bytes());
+            let flags = match line.is_screen_space() {
+                true => SCREEN_SPACE_FLAG,
+                false => 0,
+            };
+            lines.extend(flags.to_le_bytes());
+        }
+        put_section(&mut bytes, LINE_SECTION, &lines);
+
+        let mut rects = records_header(self.rects.len(), RECT_RECORD_SIZE);
+        for shape in &self.rects {
+            put_vector(&mut rects, shape.center);
+            put_vector(&mut rects, shape.size);
+            put_color(&mut rects, shape.color);
+            put_color(&mut rects, shape.border_color);
+            rects.extend(shape.border_width.to_le_bytes());
+        }
+        put_section(&mut bytes, RECT_SECTION, &rects);
+
+        bytes
+    }
+
+    /// Reads a scene in the format [`Self::save`] writes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SceneError> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SceneError::NotAScene);
+        }
+        let (major, minor) = (reader.u16()?, reader.u16()?);
+        if major != VERSION_MAJOR {
+            return Err(SceneError::UnsupportedVersion { major, minor });
+        }
+
+        let mut scene = Self::new();
+        while !reader.0.is_empty() {
+            let tag = reader.take(4)?;
+            let len = reader.u64()?;
+            let len = usize::try_from(len).map_err(|_| SceneError::Truncated)?;
+            let mut section = Reader(reader.take(len)?);
+
+            match tag {
+                _ if tag == CAMERA_SECTION => {
+                    // assigned rather than built, as files aren't trusted to hold a valid zoom
+                    scene.camera.target = section.vector()?;
+                    scene.camera.zoom = section.f32()?;
+                }
+                _ if tag == LINE_SECTION => {
+                    scene.lines = section.records(LINE_RECORD_SIZE, |record| {
+                        let (from, to) = (record.vector()?, record.vector()?);
+                        let color = record.color()?;
+                        let glow = record.f32()?;
+                        let line = match record.u32()? & SCREEN_SPACE_FLAG {
+                            0 => Line::new_raw(from, to, color),
+                            _ => Line::screen_raw(from, to, color),
+                        };
+                        Ok(line.with_glow(glow))
+                    })?;
+                }
+                _ if tag == RECT_SECTION => {
+                    scene.rects = section.records(RECT_RECORD_SIZE_1_0, |record| {
+                        let (center, size) = (record.vector()?, record.vector()?);
+                        let mut shape = RectOrCircle::rectangle_raw(center, size, record.color()?);
+                        if !record.0.is_empty() {
+                            shape.border_color = record.color()?;
+                            shape.border_width = record.f32()?;
+                        }
+                        Ok(shape)
+                    })?;
+                }
+                // written by a later version
+                _ => {}
+            }
+        }
+        Ok(scene)
+    }
+}
+
+fn put_section(bytes: &mut Vec<u8>, tag: &[u8; 4], section: &[u8]) {
+    bytes.extend(tag);
+    bytes.extend((section.len() as u64).to_le_bytes());
+    bytes.extend(section);
+}
+
+/// The start of a section holding `count` records of `record_size` bytes each.
+fn records_header(count: usize, record_size: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + count * record_size as usize);
+    bytes.extend((count as u32).to_le_bytes());
+    bytes.extend(record_size.to_le_bytes());
+    bytes
+}
+
+fn put_vector(bytes: &mut Vec<u8>, vector: Vector2) {
+    bytes.extend(vector.x.to_le_bytes());
+    bytes.extend(vector.y.to_le_bytes());
+}
+
+fn put_color(bytes: &mut Vec<u8>, color: RawColor) {
+    for channel in [color.red(), color.green(), color.blue(), color.alpha()] {
+        bytes.extend(channel.to_le_bytes());
+    }
+}
+
+/// Little endian values read off the front of a slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SceneError> {
+        if self.0.len() < len {
+            return Err(SceneError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SceneError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16, SceneError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, SceneError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, SceneError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, SceneError> {
+        self.array().map(f32::from_le_bytes)
+    }
+
+    fn vector(&mut self) -> Result<Vector2, SceneError> {
+        Ok(Vector2::new(self.f32()?, self.f32()?))
+    }
+
+    fn color(&mut self) -> Result<RawColor, SceneError> {
+        let [r, g, b, a] = [self.f32()?, self.f32()?, self.f32()?, self.f32()?];
+        Ok(Color::new(r, g, b, a).raw())
+    }
+
+    /// Reads a record count and size, then each record with `read`, skipping whatever is
+    /// left of records that are longer than `known_size`.
+    fn records<T>(
+        &mut self,
+        known_size: u32,
+        mut read: impl FnMut(&mut Reader<'a>) -> Result<T, SceneError>,
+    ) -> Result<Vec<T>, SceneError> {
+        let (count, size) = (self.u32()?, self.u32()?);
+        if size < known_size {
+            return Err(SceneError::Truncated);
+        }
+        // checked up front, so a corrupt count can't reserve more than the file holds
+        let total = count as u64 * size as u64;
+        if total > self.0.len() as u64 {
+            return Err(SceneError::Truncated);
+        }
+        let mut records = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            records.push(read(&mut Reader(self.take(size as usize)?))?);
+        }
+        Ok(records)
+    }
+}
+
+/// Why [`Scene::load`] or [`Scene::from_bytes`] failed.
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// The data doesn't start like a scene file.
+    NotAScene,
+    /// The file was written by an incompatible version.
+    UnsupportedVersion {
+        major: u16,
+        minor: u16,
+    },
+    /// The data ends in the middle of something.
+    Truncated,
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access scene file: {err}"),
+            Self::NotAScene => write!(f, "not a scene file"),
+            Self::UnsupportedVersion { major, minor } => write!(
+                f,
+                "scene file version {major}.{minor} isn't supported, only {VERSION_MAJOR}.x is"
+            ),
+            Self::Truncated => write!(f, "scene file is truncated"),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::NotAScene | Self::UnsupportedVersion { .. } | Self::Truncated => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
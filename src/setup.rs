@@ -0,0 +1,133 @@
+use super::error::RenderError;
+use pollster::block_on;
+use std::path::Path;
+use wgpu::{
+    Adapter, Device, DeviceDescriptor, Features, Instance, Limits, MemoryHints, PowerPreference,
+    Queue, RequestAdapterOptions, Surface,
+};
+
+/// Features every stage pipeline depends on.
+pub const REQUIRED_FEATURES: Features = Features::CLEAR_TEXTURE;
+
+/// Features requested whenever the adapter has them. Line alpha accumulation needs these,
+/// see [`LineAccumulation`](crate::LineAccumulation).
+pub const OPTIONAL_FEATURES: Features = Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+/// Requests a hardware adapter, falling back to the software adapter if allowed. Also returns
+/// whether it fell back.
+pub fn request_adapter(
+    instance: &Instance,
+    power_preference: PowerPreference,
+    allow_fallback_adapter: bool,
+    compatible_surface: Option<&Surface>,
+) -> Result<(Adapter, bool), RenderError> {
+    let request = |force_fallback_adapter| {
+        block_on(instance.request_adapter(&RequestAdapterOptions {
+            power_preference,
+            compatible_surface,
+            force_fallback_adapter,
+        }))
+    };
+
+    let (adapter, fallback) = request(false)
+        .map(|adapter| (adapter, false))
+        .or_else(|| {
+            if allow_fallback_adapter {
+                log::warn!("no hardware adapter found, trying the fallback adapter");
+                request(true).map(|adapter| (adapter, true))
+            } else {
+                None
+            }
+        })
+        .ok_or(RenderError::NoAdapter)?;
+
+    let info = adapter.get_info();
+    log::info!(
+        "using adapter {:?} ({:?}, {:?})",
+        info.name,
+        info.device_type,
+        info.backend
+    );
+    Ok((adapter, fallback))
+}
+
+/// Requests a device with [`REQUIRED_FEATURES`] plus `extra_features`, checking up front that
+/// the adapter supports them and `limits`, along with whichever [`OPTIONAL_FEATURES`] the
+/// adapter has.
+pub fn request_device(
+    adapter: &Adapter,
+    extra_features: Features,
+    limits: &Limits,
+    trace_dir: Option<&Path>,
+) -> Result<(Device, Queue), RenderError> {
+    let features = REQUIRED_FEATURES | extra_features;
+    let missing = features - adapter.features();
+    if !missing.is_empty() {
+        return Err(RenderError::UnsupportedFeatures(missing));
+    }
+
+    let mut exceeded = None;
+    limits.check_limits_with_fail_fn(&adapter.limits(), true, |name, requested, allowed| {
+        exceeded = Some(RenderError::UnsupportedLimit {
+            name,
+            requested,
+            allowed,
+        });
+    });
+    if let Some(err) = exceeded {
+        return Err(err);
+    }
+
+    let device = block_on(adapter.request_device(
+        &DeviceDescriptor {
+            label: Some("wgpu_rendering device"),
+            required_features: features | (adapter.features() & OPTIONAL_FEATURES),
+            required_limits: limits.clone(),
+            memory_hints: MemoryHints::Performance,
+        },
+        trace_dir,
+    ))?;
+    Ok(device)
+}
+
+/// The value of `result`, or `None` to skip the test when there's no adapter to render with.
+/// Any other error fails the test.
+#[cfg(test)]
+pub(crate) fn or_skip<T>(result: Result<T, RenderError>) -> Option<T> {
+    match result {
+        Err(RenderError::NoAdapter) => {
+            eprintln!("no adapter available, skipping");
+            None
+        }
+        result => Some(result.unwrap()),
+    }
+}
+
+/// A device on whichever adapter there is, or `None` to skip the test without one.
+#[cfg(test)]
+pub(crate) fn test_device() -> Option<(Device, Queue)> {
+    test_device_with(Features::empty(), &Limits::default())
+}
+
+/// Like [`test_device`] with `extra_features` and `limits`, skipping the test as well when the
+/// adapter lacks the features.
+#[cfg(test)]
+pub(crate) fn test_device_with(
+    extra_features: Features,
+    limits: &Limits,
+) -> Option<(Device, Queue)> {
+    let instance = Instance::new(Default::default());
+    let (adapter, _) = or_skip(request_adapter(
+        &instance,
+        PowerPreference::HighPerformance,
+        true,
+        None,
+    ))?;
+    match request_device(&adapter, extra_features, limits, None) {
+        Err(RenderError::UnsupportedFeatures(missing)) => {
+            eprintln!("adapter lacks {missing:?}, skipping");
+            None
+        }
+        result => Some(result.unwrap()),
+    }
+}
@@ -0,0 +1,508 @@
+use super::camera::CameraTransforms;
+use super::compact::{self, InstanceBuffer};
+use super::config::StageShaders;
+use super::culling::GpuCulling;
+use super::dynamic_storage::{GrowthStrategy, ShrinkPolicy, UploadMethod};
+use super::lines::{LineAccumulation, LineRenderPipeline};
+use super::rect_circle::RectCircleRenderPipeline;
+use super::reference_grid::GridRenderPipeline;
+use super::stats::RenderStats;
+use super::util::profile_span;
+use super::{RenderController, RenderStage};
+use std::thread;
+use wgpu::naga::front::wgsl;
+use wgpu::naga::valid::{Capabilities, ValidationFlags, Validator};
+use wgpu::naga::ShaderStage;
+use wgpu::BufferAddress;
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, PipelineCache, QuerySet, Queue,
+    RenderPass, ShaderModuleDescriptor, ShaderSource, TextureFormat,
+};
+use winit::dpi::PhysicalSize;
+
+/// The pipelines behind every [`RenderStage`], shared by the windowed and headless paths.
+pub struct StagePipelines {
+    pub line_render: LineRenderPipeline,
+    pub rect_circle_render: RectCircleRenderPipeline,
+    pub grid_render: GridRenderPipeline,
+    /// Why custom shaders were replaced by the built-in ones.
+    pub shader_errors: Vec<(RenderStage, String)>,
+}
+
+impl StagePipelines {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        texture_format: TextureFormat,
+        size: PhysicalSize<u32>,
+        use_line_alpha: bool,
+        line_accumulation: LineAccumulation,
+        sample_count: u32,
+        line_capacity: BufferAddress,
+        shape_capacity: BufferAddress,
+        compact_colors: bool,
+        shaders: &StageShaders,
+        cache: Option<&PipelineCache>,
+    ) -> Self {
+        let mut shader_errors = Vec::new();
+        let mut custom_shader = |stage, custom: &Option<ShaderModuleDescriptor<'static>>| {
+            let custom = custom.as_ref()?;
+            match validate_shader(custom) {
+                Ok(()) => Some(custom.clone()),
+                Err(error) => {
+                    log::error!(
+                        "custom {stage:?} shader is invalid, using the built-in one:\n{error}"
+                    );
+                    shader_errors.push((stage, error));
+                    None
+                }
+            }
+        };
+        let custom_line_shader = custom_shader(RenderStage::Line, &shaders.line);
+        let custom_rect_circle_shader =
+            custom_shader(RenderStage::RectsAndCircles, &shaders.rect_circle);
+        let line_layout = compact_colors.then_some(include_str!("lines_compact.wgsl"));
+        let rect_circle_layout = compact_colors.then_some(include_str!("rect_circle_compact.wgsl"));
+
+        // the pipelines are independent, so their shaders compile concurrently
+        thread::scope(|scope| {
+            let rect_circle_render = scope.spawn(|| {
+                // instance data can get large enough for the extra staging copy to show
+                let rect_circle_data = InstanceBuffer::with_label(
+                    device,
+                    "rect_circle instance buffer",
+                    shape_capacity,
+                    UploadMethod::WriteBufferWith,
+                    compact_colors,
+                );
+                let rect_circle_shader =
+                    device.create_shader_module(custom_rect_circle_shader.unwrap_or_else(|| {
+                        compact::shader(
+                            "rect_circle.wgsl",
+                            include_str!("rect_circle.wgsl"),
+                            rect_circle_layout,
+                        )
+                    }));
+                RectCircleRenderPipeline::new(
+                    device,
+                    rect_circle_data,
+                    rect_circle_shader,
+                    texture_format,
+                    sample_count,
+                    cache,
+                )
+            });
+
+            let line_data = InstanceBuffer::with_label(
+                device,
+                "line instance buffer",
+                line_capacity,
+                UploadMethod::WriteBufferWith,
+                compact_colors,
+            );
+            let line_shader =
+                device.create_shader_module(custom_line_shader.unwrap_or_else(|| {
+                    match line_accumulation {
+                        LineAccumulation::StorageTexture => {
+                            compact::shader("lines.wgsl", include_str!("lines.wgsl"), line_layout)
+                        }
+                        LineAccumulation::Unavailable => compact::shader(
+                            "lines_blend.wgsl",
+                            include_str!("lines_blend.wgsl"),
+                            line_layout,
+                        ),
+                    }
+                }));
+            let line_glow_shader = device.create_shader_module(compact::shader(
+                "lines_glow.wgsl",
+                include_str!("lines_glow.wgsl"),
+                line_layout,
+            ));
+            let line_render = LineRenderPipeline::new(
+                device,
+                line_data,
+                line_shader,
+                line_glow_shader,
+                texture_format,
+                size,
+                use_line_alpha,
+                line_accumulation,
+                sample_count,
+                cache,
+            );
+
+            let grid_render =
+                GridRenderPipeline::new(device, texture_format, size, sample_count, cache);
+
+            Self {
+                line_render,
+                rect_circle_render: rect_circle_render.join().unwrap(),
+                grid_render,
+                shader_errors,
+            }
+        })
+    }
+
+    pub fn resize(&mut self, device: &Device, queue: &Queue, new_size: PhysicalSize<u32>) {
+        self.line_render.resize(device, queue, new_size);
+        self.grid_render.resize(new_size);
+    }
+
+    /// Rebuilds every stage's pipelines to draw into `texture_format`, keeping their shaders.
+    pub fn set_texture_format(
+        &mut self,
+        device: &Device,
+        texture_format: TextureFormat,
+        cache: Option<&PipelineCache>,
+    ) {
+        self.line_render
+            .set_texture_format(device, texture_format, cache);
+        self.rect_circle_render
+            .set_texture_format(device, texture_format, cache);
+        self.grid_render
+            .set_texture_format(device, texture_format, cache);
+    }
+
+    /// Compiles `source` and rebuilds the pipeline of `stage` with it, keeping the current one
+    /// and returning the error if that fails.
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn reload_shader(
+        &mut self,
+        device: &Device,
+        stage: RenderStage,
+        label: &str,
+        source: String,
+        cache: Option<&PipelineCache>,
+    ) -> Result<(), String> {
+        let descriptor = ShaderModuleDescriptor {
+            label: Some(label),
+            source: ShaderSource::Wgsl(source.into()),
+        };
+        let result = validate_shader(&descriptor).and_then(|()| {
+            let shader = device.create_shader_module(descriptor);
+            match stage {
+                RenderStage::Line => self.line_render.set_shader(device, shader, cache),
+                RenderStage::RectsAndCircles => {
+                    self.rect_circle_render.set_shader(device, shader, cache)
+                }
+                RenderStage::Grid => return Err("the grid has no custom shader".to_string()),
+            }
+            .map_err(|err| err.to_string())
+        });
+
+        self.shader_errors.retain(|(errored, _)| *errored != stage);
+        if let Err(error) = &result {
+            self.shader_errors.push((stage, error.clone()));
+        }
+        result
+    }
+
+    /// Sets how many buffers each stage's instances alternate between, see
+    /// [`DynamicStorageBuffer::set_buffer_count`].
+    pub fn set_buffer_count(&mut self, device: &Device, count: usize) {
+        self.line_render.line_data.set_buffer_count(device, count);
+        self.rect_circle_render
+            .instance_data
+            .set_buffer_count(device, count);
+    }
+
+    /// Moves both stages on to their next instance buffer. Call once per frame, before
+    /// [`Self::upload`].
+    pub fn advance_frame(&mut self) {
+        self.line_render.line_data.advance_frame();
+        self.rect_circle_render.instance_data.advance_frame();
+    }
+
+    /// Uploads the shapes submitted to `render`.
+    pub fn upload(&mut self, device: &Device, queue: &Queue, render: &RenderController) {
+        self.upload_stage(device, queue, render, RenderStage::Line);
+        self.upload_stage(device, queue, render, RenderStage::RectsAndCircles);
+    }
+
+    /// Uploads the shapes submitted to `render` for `stage`.
+    pub fn upload_stage(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        render: &RenderController,
+        stage: RenderStage,
+    ) {
+        match stage {
+            RenderStage::Line => self.line_render.set_lines(device, queue, &render.lines),
+            RenderStage::RectsAndCircles => {
+                (self.rect_circle_render.instance_data).set_new_data(device, queue, &render.rects)
+            }
+            RenderStage::Grid => {}
+        }
+    }
+
+    /// Uploads the grid `render` asks for as seen through `camera_transforms`, if it has the
+    /// grid stage.
+    pub fn prepare_grid(
+        &mut self,
+        queue: &Queue,
+        render: &RenderController,
+        camera_transforms: &CameraTransforms,
+    ) {
+        if render.render_order.contains(&RenderStage::Grid) {
+            (self.grid_render).prepare(queue, &render.grid_style, camera_transforms);
+        }
+    }
+
+    /// Bytes per instance of `stage` on the GPU, which depends on
+    /// [`WindowConfig::compact_colors`](crate::WindowConfig::compact_colors).
+    pub fn instance_size(&self, stage: RenderStage) -> BufferAddress {
+        match stage {
+            RenderStage::Line => self.line_render.line_data.item_size(),
+            RenderStage::RectsAndCircles => self.rect_circle_render.instance_data.item_size(),
+            RenderStage::Grid => 0,
+        }
+    }
+
+    /// The most instance bytes `stage` can hold; the instances past it are dropped.
+    pub fn max_instance_bytes(&self, stage: RenderStage) -> BufferAddress {
+        let max = match stage {
+            RenderStage::Line => self.line_render.line_data.max_capacity(),
+            RenderStage::RectsAndCircles => self.rect_circle_render.instance_data.max_capacity(),
+            RenderStage::Grid => 0,
+        };
+        max * self.instance_size(stage)
+    }
+
+    pub fn set_growth_strategy(&mut self, growth_strategy: GrowthStrategy) {
+        self.line_render
+            .line_data
+            .set_growth_strategy(growth_strategy);
+        self.rect_circle_render
+            .instance_data
+            .set_growth_strategy(growth_strategy);
+    }
+
+    /// GPU memory held by the instance buffers, in bytes.
+    pub fn memory_usage(&self) -> BufferAddress {
+        self.line_render.line_data.memory_usage()
+            + self.rect_circle_render.instance_data.memory_usage()
+    }
+
+    pub fn set_shrink_policy(&mut self, shrink_policy: ShrinkPolicy) {
+        self.line_render.line_data.set_shrink_policy(shrink_policy);
+        self.rect_circle_render
+            .instance_data
+            .set_shrink_policy(shrink_policy);
+    }
+
+    /// Makes both stages take their instance counts from GPU buffers, see
+    /// [`LineRenderPipeline::enable_indirect`]. Shrinking is turned off, as the length known on
+    /// the CPU no longer says how much of the buffers is in use.
+    pub fn enable_indirect(&mut self, device: &Device) {
+        self.set_shrink_policy(ShrinkPolicy::Off);
+        self.line_render.enable_indirect(device);
+        self.rect_circle_render.enable_indirect(device);
+    }
+
+    /// Culls rects and circles on the GPU before drawing them, see
+    /// [`RectCircleRenderPipeline::enable_culling`]. Requires [`Self::enable_indirect`] first.
+    pub fn enable_culling(&mut self, device: &Device) {
+        self.rect_circle_render.enable_culling(device);
+    }
+
+    /// Encodes the culling pass, if culling is enabled. Goes after the compute stages, which
+    /// may write the instances.
+    pub fn cull(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        camera_transforms: &CameraTransforms,
+    ) {
+        profile_span!("cull");
+        self.rect_circle_render
+            .cull(device, command_encoder, camera_transforms);
+    }
+
+    /// Reads back what the frame just submitted left for the CPU to know.
+    pub fn after_submit(&mut self, device: &Device) {
+        if let Some(culling) = self.rect_circle_render.culling_mut() {
+            culling.after_submit(device);
+        }
+    }
+
+    /// The indirect args buffer `stage` draws with, if indirect draws are enabled.
+    pub fn indirect_args(&self, stage: RenderStage) -> Option<&Buffer> {
+        match stage {
+            RenderStage::Line => self.line_render.indirect_args(),
+            RenderStage::RectsAndCircles => self.rect_circle_render.indirect_args(),
+            RenderStage::Grid => None,
+        }
+    }
+
+    /// Lets compute shaders write the instances of `stage`, returning the layout of the bind
+    /// group that does so. Panics for [`RenderStage::Grid`], which has no instances.
+    pub fn enable_read_write(&mut self, device: &Device, stage: RenderStage) -> &BindGroupLayout {
+        match stage {
+            RenderStage::Line => self.line_render.line_data.enable_read_write(device),
+            RenderStage::RectsAndCircles => self
+                .rect_circle_render
+                .instance_data
+                .enable_read_write(device),
+            RenderStage::Grid => panic!("the grid has no instances for a compute stage to write"),
+        }
+    }
+
+    pub fn read_write_bind_group(&self, stage: RenderStage) -> Option<&BindGroup> {
+        match stage {
+            RenderStage::Line => self.line_render.line_data.read_write_bind_group(),
+            RenderStage::RectsAndCircles => self
+                .rect_circle_render
+                .instance_data
+                .read_write_bind_group(),
+            RenderStage::Grid => None,
+        }
+    }
+
+    /// Encodes the per-frame work that has to happen before the render pass.
+    pub fn pre_render(&mut self, device: &Device, command_encoder: &mut CommandEncoder) {
+        self.line_render.line_data.maintain(device, command_encoder);
+        self.rect_circle_render
+            .instance_data
+            .maintain(device, command_encoder);
+        self.line_render.pre_render(command_encoder);
+    }
+
+    /// Draws the stages in the order they were added to `render`, returning the number of
+    /// draw calls. With `timestamps`, the `i`th stage is bracketed by timestamp writes to
+    /// queries `2 * i` and `2 * i + 1`.
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        render: &RenderController,
+        camera_transforms: &CameraTransforms,
+        timestamps: Option<&QuerySet>,
+    ) -> u32 {
+        let mut draw_calls = 0;
+        for (index, &stage) in (0..).zip(&render.render_order) {
+            if let Some(query_set) = timestamps {
+                render_pass.write_timestamp(query_set, 2 * index);
+            }
+            match stage {
+                RenderStage::RectsAndCircles => {
+                    render_pass.push_debug_group("rect_circle stage");
+                    draw_calls += self
+                        .rect_circle_render
+                        .render(render_pass, camera_transforms);
+                }
+                RenderStage::Line => {
+                    render_pass.push_debug_group("line stage");
+                    draw_calls += self.line_render.render(render_pass, camera_transforms);
+                }
+                RenderStage::Grid => {
+                    render_pass.push_debug_group("grid stage");
+                    draw_calls += self.grid_render.render(render_pass);
+                }
+            }
+            render_pass.pop_debug_group();
+            if let Some(query_set) = timestamps {
+                render_pass.write_timestamp(query_set, 2 * index + 1);
+            }
+        }
+        draw_calls
+    }
+
+    /// The memory and reallocation figures of [`RenderStats`], plus the bytes uploaded since
+    /// the last call.
+    pub fn take_stats(&mut self) -> RenderStats {
+        let line_accum_texture_bytes = self.line_render.accum_texture_bytes();
+        let line_data = &mut self.line_render.line_data;
+        let shape_data = &mut self.rect_circle_render.instance_data;
+        RenderStats {
+            line_buffer_bytes: line_data.memory_usage(),
+            shape_buffer_bytes: shape_data.memory_usage(),
+            line_accum_texture_bytes,
+            uploaded_bytes: line_data.take_uploaded_bytes() + shape_data.take_uploaded_bytes(),
+            reallocations: line_data.reallocations() + shape_data.reallocations(),
+            culled_survivors: (self.rect_circle_render.culling()).and_then(GpuCulling::survivors),
+            ..RenderStats::default()
+        }
+    }
+}
+
+/// Checks a custom stage shader on the CPU, so it can be replaced before creating a pipeline
+/// from it would fail. Only WGSL is checked.
+pub(crate) fn validate_shader(descriptor: &ShaderModuleDescriptor) -> Result<(), String> {
+    let ShaderSource::Wgsl(source) = &descriptor.source else {
+        return Ok(());
+    };
+    let module = wgsl::parse_str(source).map_err(|err| err.emit_to_string(source))?;
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| err.emit_to_string(source))?;
+
+    for (name, stage) in [
+        ("vs_main", ShaderStage::Vertex),
+        ("fs_main", ShaderStage::Fragment),
+    ] {
+        let found = module
+            .entry_points
+            .iter()
+            .any(|entry_point| entry_point.name == name && entry_point.stage == stage);
+        if !found {
+            return Err(format!("no {stage:?} entry point named `{name}`"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_shader;
+    use crate::compact;
+    use wgpu::{include_wgsl, ShaderModuleDescriptor, ShaderSource};
+
+    fn shader_source<'a>(descriptor: &'a ShaderModuleDescriptor) -> &'a str {
+        match &descriptor.source {
+            ShaderSource::Wgsl(source) => source,
+            _ => unreachable!(),
+        }
+    }
+
+    fn wgsl(source: &'static str) -> ShaderModuleDescriptor<'static> {
+        ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(source.into()),
+        }
+    }
+
+    #[test]
+    fn built_in_shaders_validate() {
+        validate_shader(&include_wgsl!("rect_circle.wgsl")).unwrap();
+        validate_shader(&include_wgsl!("lines.wgsl")).unwrap();
+        validate_shader(&include_wgsl!("lines_blend.wgsl")).unwrap();
+        validate_shader(&include_wgsl!("lines_glow.wgsl")).unwrap();
+    }
+
+    #[test]
+    fn compact_variants_validate() {
+        let rect_circle = include_str!("rect_circle_compact.wgsl");
+        let line = include_str!("lines_compact.wgsl");
+        for (source, layout) in [
+            (include_str!("rect_circle.wgsl"), rect_circle),
+            (include_str!("lines.wgsl"), line),
+            (include_str!("lines_blend.wgsl"), line),
+            (include_str!("lines_glow.wgsl"), line),
+        ] {
+            let shader = compact::shader("compact", source, Some(layout));
+            assert!(!shader_source(&shader).contains("alias StoredInstance"));
+            validate_shader(&shader).unwrap();
+        }
+    }
+
+    #[test]
+    fn invalid_shaders_are_reported() {
+        let error = validate_shader(&wgsl("fn vs_main( {")).unwrap_err();
+        assert!(error.contains("error"), "{error}");
+
+        let vertex_only =
+            wgsl("@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }");
+        let error = validate_shader(&vertex_only).unwrap_err();
+        assert!(error.contains("fs_main"), "{error}");
+    }
+}
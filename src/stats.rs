@@ -0,0 +1,36 @@
+/// GPU memory held by the built-in stages and the work done for the last frame drawn, for
+/// debug overlays. Available through
+/// [`WindowAccess::render_stats`](crate::WindowAccess::render_stats).
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RenderStats {
+    /// Bytes held by the line instance buffers.
+    pub line_buffer_bytes: u64,
+    /// Bytes held by the rect and circle instance buffers.
+    pub shape_buffer_bytes: u64,
+    /// Bytes held by the line accumulation texture, 16 per pixel of the window.
+    pub line_accum_texture_bytes: u64,
+    /// Bytes held by the multisampled color target, 0 without MSAA.
+    pub msaa_texture_bytes: u64,
+    /// Instance bytes uploaded for the last frame drawn.
+    pub uploaded_bytes: u64,
+    /// Times an instance buffer was reallocated to grow or shrink since startup.
+    pub reallocations: u64,
+    /// Draw calls issued for the last frame drawn.
+    pub draw_calls: u32,
+    /// Rects and circles left to draw by [`WindowConfig::gpu_culling`] in a recent frame,
+    /// read back without waiting on the GPU, so a frame or two late. `None` without culling
+    /// or before the first count arrives.
+    ///
+    /// [`WindowConfig::gpu_culling`]: crate::WindowConfig::gpu_culling
+    pub culled_survivors: Option<u32>,
+}
+
+impl RenderStats {
+    /// Bytes held by every buffer and texture counted above.
+    pub fn total_bytes(&self) -> u64 {
+        self.line_buffer_bytes
+            + self.shape_buffer_bytes
+            + self.line_accum_texture_bytes
+            + self.msaa_texture_bytes
+    }
+}
@@ -0,0 +1,258 @@
+use super::camera::{Camera, CameraTransforms};
+use super::color::{Color, RawColor};
+use super::lines::Line;
+use super::rect_circle::RectOrCircle;
+use super::vectors::{DVector2, Vector2};
+use super::{RenderController, RenderStage};
+use std::fmt::Write;
+use winit::dpi::PhysicalSize;
+
+/// World → pixel mapping matching what `camera` shows in a viewport of `size`.
+struct SvgTransform {
+    target: Vector2,
+    scale: Vector2,
+    size: Vector2,
+}
+
+impl SvgTransform {
+    fn new(camera: &Camera, size: PhysicalSize<u32>) -> Self {
+        Self {
+            target: camera.target,
+            scale: CameraTransforms::get_aspect_transform(size) * camera.zoom,
+            size: Vector2::new(size.width as f32, size.height as f32),
+        }
+    }
+
+    fn point(&self, world_pos: Vector2) -> Vector2 {
+        let ndc = (world_pos - self.target) * self.scale;
+        (ndc * Vector2::new(1.0, -1.0) + Vector2::same(1.0)) / 2.0 * self.size
+    }
+
+    /// Pixels per world unit along each axis.
+    fn extent(&self, world_size: Vector2) -> Vector2 {
+        world_size * self.scale / 2.0 * self.size
+    }
+}
+
+/// `color` as an sRGB hex color, with its alpha as an opacity attribute if it isn't opaque.
+fn paint(attribute: &str, color: RawColor) -> String {
+    let [r, g, b, a] = Color::from_pre_mult(color).to_srgb_u8();
+    let mut paint = format!(r##"{attribute}="#{r:02x}{g:02x}{b:02x}""##);
+    if a < u8::MAX {
+        let _ = write!(paint, r#" {attribute}-opacity="{:.3}""#, a as f32 / 255.0);
+    }
+    paint
+}
+
+impl RenderController {
+    /// Describes what was submitted this frame as an SVG document of `viewport` pixels, seen
+    /// through `camera`, over the black the window clears to. Stages come out in render
+    /// order, by [priority](Self::set_stage_priority), and shapes in submission order, so
+    /// they layer like on screen.
+    ///
+    /// The output is an approximation: lines are one pixel wide strokes without their glow,
+    /// and translucent colors blend the usual way, whatever the line stage's blend mode or
+    /// accumulation. Double precision shapes are placed relative to the world origin. The grid
+    /// stage is left out.
+    pub fn export_svg(&self, camera: &Camera, viewport: (u32, u32)) -> String {
+        let (width, height) = viewport;
+        let transform = SvgTransform::new(camera, PhysicalSize::new(width, height));
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        svg.push('\n');
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>\n");
+
+        for stage in &self.sorted_render_order() {
+            match stage {
+                RenderStage::Line => {
+                    let lines_64 = self
+                        .lines_64
+                        .iter()
+                        .map(|line| line.relative_to(DVector2::ZERO));
+                    for line in self.lines.iter().copied().chain(lines_64) {
+                        write_line(&mut svg, &transform, &line);
+                    }
+                }
+                RenderStage::RectsAndCircles => {
+                    let rects_64 = self
+                        .rects_64
+                        .iter()
+                        .map(|shape| shape.relative_to(DVector2::ZERO));
+                    for shape in self.rects.iter().copied().chain(rects_64) {
+                        write_shape(&mut svg, &transform, &shape);
+                    }
+                }
+                RenderStage::Grid => {}
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn write_line(svg: &mut String, transform: &SvgTransform, line: &Line) {
+    let (from, to) = match line.is_screen_space() {
+        true => (line.from(), line.to()),
+        false => (transform.point(line.from()), transform.point(line.to())),
+    };
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}" {} stroke-width="1"/>"#,
+        from.x,
+        from.y,
+        to.x,
+        to.y,
+        paint("stroke", line.color()),
+    );
+}
+
+fn write_shape(svg: &mut String, transform: &SvgTransform, shape: &RectOrCircle) {
+    let center = transform.point(shape.center);
+    let circle = shape.is_circle();
+    let half_size = match circle {
+        true => transform.extent(Vector2::same(shape.size.x)),
+        false => transform.extent(shape.size),
+    };
+    let half_size = Vector2::new(half_size.x.abs(), half_size.y.abs());
+    // the aspect transform scales both axes alike, so a width is the same either way
+    let border = transform.extent(Vector2::same(shape.border_width)).x;
+    if border <= 0.0 {
+        write_outline(svg, center, half_size, circle, &paint("fill", shape.color));
+        return;
+    }
+
+    let inner = half_size - Vector2::same(border);
+    if inner.x <= 0.0 || inner.y <= 0.0 {
+        write_outline(
+            svg,
+            center,
+            half_size,
+            circle,
+            &paint("fill", shape.border_color),
+        );
+        return;
+    }
+    write_outline(svg, center, inner, circle, &paint("fill", shape.color));
+    // a stroke centered between the fill and the outline, so the two don't overlap
+    let stroke = format!(
+        r#"fill="none" {} stroke-width="{border:.3}""#,
+        paint("stroke", shape.border_color)
+    );
+    let middle = half_size - Vector2::same(border / 2.0);
+    write_outline(svg, center, middle, circle, &stroke);
+}
+
+/// A circle or rectangle around `center`, in pixels.
+fn write_outline(svg: &mut String, center: Vector2, half_size: Vector2, circle: bool, paint: &str) {
+    if circle {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{:.3}" cy="{:.3}" r="{:.3}" {paint}/>"#,
+            center.x, center.y, half_size.x,
+        );
+    } else {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.3}" y="{:.3}" width="{:.3}" height="{:.3}" {paint}/>"#,
+            center.x - half_size.x,
+            center.y - half_size.y,
+            half_size.x * 2.0,
+            half_size.y * 2.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Camera, Color, Line, RectOrCircle, RenderController, RenderStage, Vector2};
+
+    #[test]
+    fn shapes_follow_the_camera_and_stage_order() {
+        let mut render = RenderController::new();
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_stage(RenderStage::Line);
+        render.add_line(Line::new(Vector2::ZERO, Vector2::RIGHT, Color::WHITE));
+        render.add_rect_or_circle(RectOrCircle::circle(
+            Vector2::new(1.0, 1.0),
+            0.5,
+            Color::RED.with_alpha(0.5),
+        ));
+
+        let svg = render.export_svg(&Camera::new(Vector2::new(1.0, 0.0), 1.0), (200, 100));
+        // the circle's stage comes first, so it's drawn below the line
+        let circle = svg.find("<circle").unwrap();
+        let line = svg.find("<line").unwrap();
+        assert!(circle < line, "{svg}");
+
+        // 100 pixels per world unit vertically; horizontally, the aspect ratio keeps it square
+        assert!(svg.contains(
+            r##"<line x1="50.000" y1="50.000" x2="100.000" y2="50.000" stroke="#ffffff" stroke-width="1"/>"##
+        ), "{svg}");
+        assert!(svg.contains(
+            r##"<circle cx="100.000" cy="0.000" r="25.000" fill="#ff0000" fill-opacity="0.502"/>"##
+        ), "{svg}");
+    }
+
+    #[test]
+    fn stage_priorities_override_the_order_stages_were_added_in() {
+        let mut render = RenderController::new();
+        render.set_stage_priority(RenderStage::Line, -1);
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_stage(RenderStage::Line);
+        render.add_line(Line::new(Vector2::ZERO, Vector2::RIGHT, Color::WHITE));
+        render.add_rect_or_circle(RectOrCircle::circle(Vector2::ZERO, 0.5, Color::RED));
+
+        let svg = render.export_svg(&Camera::default(), (100, 100));
+        assert!(
+            svg.find("<line").unwrap() < svg.find("<circle").unwrap(),
+            "{svg}"
+        );
+
+        // equal priorities fall back to the order the stages were added in
+        render.set_stage_priority(RenderStage::Line, 0);
+        render.sort_stages();
+        assert_eq!(
+            render.render_order,
+            [RenderStage::RectsAndCircles, RenderStage::Line]
+        );
+        assert_eq!(render.stage_priority(RenderStage::Grid), 0);
+    }
+
+    #[test]
+    fn borders_are_strokes_inside_the_outline() {
+        let mut render = RenderController::new();
+        render.add_stage(RenderStage::RectsAndCircles);
+        let (fill, border) = (Color::WHITE, Color::BLUE);
+        render.add_rect_or_circle(RectOrCircle::circle_with_border(
+            Vector2::ZERO,
+            0.5,
+            fill,
+            border,
+            0.1,
+        ));
+        render.add_rect_or_circle(RectOrCircle::circle_with_border(
+            Vector2::ZERO,
+            0.5,
+            fill,
+            border,
+            0.5,
+        ));
+
+        let svg = render.export_svg(&Camera::default(), (100, 100));
+        assert!(
+            svg.contains(r##"<circle cx="50.000" cy="50.000" r="20.000" fill="#ffffff"/>"##),
+            "{svg}"
+        );
+        assert!(svg.contains(
+            r##"<circle cx="50.000" cy="50.000" r="22.500" fill="none" stroke="#0080ff" stroke-width="5.000"/>"##
+        ), "{svg}");
+        // a border as wide as the radius covers the whole circle
+        assert!(
+            svg.contains(r##"<circle cx="50.000" cy="50.000" r="25.000" fill="#0080ff"/>"##),
+            "{svg}"
+        );
+    }
+}
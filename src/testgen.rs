@@ -0,0 +1,185 @@
+//! Random shapes for demos, stress tests and benchmarks. Everything takes the random number
+//! generator to draw from, so seeding it with [`seeded`] makes the shapes the same on every
+//! run.
+
+use super::color::Color;
+use super::lines::Line;
+use super::rect::Rect;
+use super::rect_circle::RectOrCircle;
+use super::vectors::Vector2;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::TAU;
+use std::ops::Range;
+
+pub use rand;
+
+/// A small, fast generator that gives the same numbers for the same `seed` every run. They
+/// may differ between 32 and 64-bit platforms.
+pub fn seeded(seed: u64) -> SmallRng {
+    SmallRng::seed_from_u64(seed)
+}
+
+/// The values the generators here are made of, on any [`Rng`].
+pub trait RandExt {
+    /// In `[0, 1)`.
+    fn f32(&mut self) -> f32;
+    /// In `[-1, 1)`.
+    fn f32_centered(&mut self) -> f32;
+    /// In `range`, or its start if it's empty.
+    fn f32_in(&mut self, range: Range<f32>) -> f32;
+    fn u8(&mut self) -> u8;
+    /// Both components in `[-1, 1)`.
+    fn vec2_centered(&mut self) -> Vector2;
+    /// Uniformly inside `region`.
+    fn vec2_in(&mut self, region: Rect) -> Vector2;
+    /// An opaque color with uniformly random sRGB channels.
+    fn color_srgb(&mut self) -> Color;
+    /// One of `palette`, or [`Self::color_srgb`] if it's empty.
+    fn color_from(&mut self, palette: &[Color]) -> Color;
+}
+
+impl<R: Rng + ?Sized> RandExt for R {
+    fn f32(&mut self) -> f32 {
+        self.random::<f32>()
+    }
+
+    fn f32_centered(&mut self) -> f32 {
+        self.random::<f32>() * 2.0 - 1.0
+    }
+
+    fn f32_in(&mut self, range: Range<f32>) -> f32 {
+        let length = (range.end - range.start).max(0.0);
+        range.start + self.f32() * length
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.random::<u8>()
+    }
+
+    fn vec2_centered(&mut self) -> Vector2 {
+        Vector2::new(self.f32_centered(), self.f32_centered())
+    }
+
+    fn vec2_in(&mut self, region: Rect) -> Vector2 {
+        region.min + Vector2::new(self.f32(), self.f32()) * region.size()
+    }
+
+    fn color_srgb(&mut self) -> Color {
+        Color::srgb(self.u8(), self.u8(), self.u8())
+    }
+
+    fn color_from(&mut self, palette: &[Color]) -> Color {
+        match palette.len() {
+            0 => self.color_srgb(),
+            len => palette[(self.f32() * len as f32) as usize % len],
+        }
+    }
+}
+
+/// `count` circles centered in `region`, with radii in `radius_range` and colors from
+/// `palette`, or random ones if it's empty.
+pub fn random_circles<R: Rng + ?Sized>(
+    rng: &mut R,
+    count: usize,
+    region: Rect,
+    radius_range: Range<f32>,
+    palette: &[Color],
+) -> Vec<RectOrCircle> {
+    (0..count)
+        .map(|_| {
+            let center = rng.vec2_in(region);
+            let radius = rng.f32_in(radius_range.clone());
+            RectOrCircle::circle(center, radius, rng.color_from(palette))
+        })
+        .collect()
+}
+
+/// `count` lines with both ends in `region` and colors from `palette`, or random ones if
+/// it's empty.
+pub fn random_lines<R: Rng + ?Sized>(
+    rng: &mut R,
+    count: usize,
+    region: Rect,
+    palette: &[Color],
+) -> Vec<Line> {
+    (0..count)
+        .map(|_| {
+            let (start, end) = (rng.vec2_in(region), rng.vec2_in(region));
+            Line::new(start, end, rng.color_from(palette))
+        })
+        .collect()
+}
+
+/// `count` lines out from `center` in random directions, like the rays of a sun, with
+/// lengths in `length_range` and colors from `palette`, or random ones if it's empty.
+pub fn radial_chords<R: Rng + ?Sized>(
+    rng: &mut R,
+    count: usize,
+    center: Vector2,
+    length_range: Range<f32>,
+    palette: &[Color],
+) -> Vec<Line> {
+    (0..count)
+        .map(|_| {
+            let direction = Vector2::from_angle(rng.f32() * TAU);
+            let end = center + direction * rng.f32_in(length_range.clone());
+            Line::new(center, end, rng.color_from(palette))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{radial_chords, random_circles, random_lines, seeded, RandExt};
+    use crate::{Color, Rect, Vector2};
+
+    const REGION: Rect = Rect {
+        min: Vector2::new(-2.0, 1.0),
+        max: Vector2::new(3.0, 4.0),
+    };
+
+    #[test]
+    fn the_same_seed_gives_the_same_shapes() {
+        let circles = |seed| {
+            let circles = random_circles(&mut seeded(seed), 50, REGION, 0.1..0.5, &[]);
+            bytemuck::cast_slice::<_, u8>(&circles).to_vec()
+        };
+        assert_eq!(circles(7), circles(7));
+        assert_ne!(circles(7), circles(8));
+
+        let lines = |seed| {
+            let lines = random_lines(&mut seeded(seed), 50, REGION, &[]);
+            bytemuck::cast_slice::<_, u8>(&lines).to_vec()
+        };
+        assert_eq!(lines(7), lines(7));
+    }
+
+    #[test]
+    fn shapes_stay_within_their_parameters() {
+        let palette = [Color::RED, Color::BLUE];
+        let mut rng = seeded(1);
+
+        for circle in random_circles(&mut rng, 200, REGION, 0.1..0.5, &palette) {
+            assert!(REGION.contains(circle.center));
+            assert!((0.1..0.5).contains(&circle.size.x));
+            let colors = palette.map(Color::raw_pre_mult);
+            assert!(colors.contains(&circle.color));
+        }
+        for line in random_lines(&mut rng, 200, REGION, &palette) {
+            let bounds = line.bounds();
+            assert!(REGION.contains(bounds.min) && REGION.contains(bounds.max));
+        }
+        let center = Vector2::new(1.0, -1.0);
+        for line in radial_chords(&mut rng, 200, center, 1.0..2.0, &palette) {
+            let bounds = line.bounds();
+            assert!(bounds.contains(center));
+            let length = bounds.size().length();
+            assert!((0.999..2.001).contains(&length), "{length}");
+        }
+
+        // empty ranges stay at their start
+        assert_eq!(rng.f32_in(3.0..3.0), 3.0);
+        assert_eq!(rng.f32_in(3.0..1.0), 3.0);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Wall-clock timing of loop iterations, sampled once per iteration.
+pub(crate) struct FrameTiming {
+    start: Instant,
+    last_frame: Instant,
+    delta: Duration,
+    frame_index: u64,
+    frames_begun: u64,
+    frame_moments: VecDeque<Instant>,
+}
+
+impl FrameTiming {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last_frame: now,
+            delta: Duration::ZERO,
+            frame_index: 0,
+            frames_begun: 0,
+            frame_moments: VecDeque::new(),
+        }
+    }
+
+    /// Samples the clock for a new iteration, clamping the delta to `max_delta`.
+    pub fn begin_frame(&mut self, max_delta: Duration) {
+        let now = Instant::now();
+        self.delta = now.duration_since(self.last_frame).min(max_delta);
+        self.last_frame = now;
+        self.frame_index = self.frames_begun;
+        self.frames_begun += 1;
+    }
+
+    /// Records a presented frame for the fps count.
+    pub fn record_present(&mut self) {
+        let now = Instant::now();
+        self.frame_moments.push_back(now);
+        while self
+            .frame_moments
+            .front()
+            .is_some_and(|inst| now.duration_since(*inst).as_secs_f32() > 1.0)
+        {
+            self.frame_moments.pop_front();
+        }
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn time_since_start(&self) -> Duration {
+        self.last_frame.duration_since(self.start)
+    }
+
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Frames presented within the last second.
+    pub fn fps(&self) -> u32 {
+        self.frame_moments.len() as u32
+    }
+}
+
+/// Decides how many times `tick` runs per frame.
+///
+/// Without a tick rate every frame ticks exactly once. With one, real time is accumulated and
+/// consumed in fixed steps, running at most `max_catch_up` ticks per frame and dropping any
+/// backlog beyond that so a slow frame can't snowball into ever slower frames.
+pub(crate) struct TickTimer {
+    tick_rate: Option<f64>,
+    max_catch_up: u32,
+    accumulator: f64,
+    last_advance: Instant,
+}
+
+impl TickTimer {
+    pub fn new(tick_rate: Option<f64>, max_catch_up: u32) -> Self {
+        Self {
+            tick_rate,
+            max_catch_up,
+            accumulator: 0.0,
+            last_advance: Instant::now(),
+        }
+    }
+
+    /// Returns the number of ticks due this frame.
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_advance).as_secs_f64();
+        self.last_advance = now;
+
+        let Some(tick_rate) = self.tick_rate else {
+            return 1;
+        };
+
+        let step = tick_rate.recip();
+        self.accumulator += elapsed;
+
+        let due = (self.accumulator / step).floor() as u32;
+        if due > self.max_catch_up {
+            self.accumulator %= step;
+            self.max_catch_up
+        } else {
+            self.accumulator -= due as f64 * step;
+            due
+        }
+    }
+
+    /// How far the current time is between the last tick and the next, in `[0, 1)`.
+    /// Always `1.0` without a fixed tick rate, as rendering happens right after the tick.
+    pub fn alpha(&self) -> f32 {
+        match self.tick_rate {
+            Some(tick_rate) => (self.accumulator * tick_rate) as f32,
+            None => 1.0,
+        }
+    }
+}
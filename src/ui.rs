@@ -0,0 +1,238 @@
+use super::Renderable;
+use egui::{Event, Key as EguiKey, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect};
+use egui_wgpu::ScreenDescriptor;
+use std::mem::take;
+use std::time::Instant;
+use wgpu::{
+    CommandEncoder, Device, LoadOp, Operations, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, StoreOp, TextureFormat, TextureView,
+};
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+/// The egui overlay, fed the window's events and drawn over the shape stages each frame.
+///
+/// `egui-winit` only supports winit 0.30 alongside the wgpu version used here, so events are
+/// translated by hand.
+pub struct UiLayer {
+    context: egui::Context,
+    renderer: egui_wgpu::Renderer,
+    raw_input: RawInput,
+    pointer_pos: Option<Pos2>,
+    modifiers: egui::Modifiers,
+    /// What egui's time counts from, for its animations and double clicks.
+    start: Instant,
+}
+
+impl UiLayer {
+    /// egui draws after the MSAA resolve, so it always renders single-sampled.
+    pub fn new(device: &Device, texture_format: TextureFormat) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(device, texture_format, None, 1, false),
+            raw_input: RawInput::default(),
+            pointer_pos: None,
+            modifiers: egui::Modifiers::default(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Queues `event` for the next frame's UI, returning whether the UI consumes it, so the
+    /// application shouldn't see it. Only presses are consumed; releases always pass through
+    /// so nothing is left held down.
+    pub fn on_window_event(&mut self, event: &WindowEvent, scale_factor: f64) -> bool {
+        let pixels_per_point = scale_factor as f32;
+        let modifiers = self.modifiers;
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let pos = Pos2::new(position.x as f32, position.y as f32) / pixels_per_point;
+                self.pointer_pos = Some(pos);
+                self.raw_input.events.push(Event::PointerMoved(pos));
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.pointer_pos = None;
+                self.raw_input.events.push(Event::PointerGone);
+                false
+            }
+            &WindowEvent::MouseInput { button, state, .. } => {
+                let (Some(pos), Some(button)) = (self.pointer_pos, pointer_button(button)) else {
+                    return false;
+                };
+                let pressed = state == ElementState::Pressed;
+                self.raw_input.events.push(Event::PointerButton {
+                    pos,
+                    button,
+                    pressed,
+                    modifiers,
+                });
+                pressed && self.wants_pointer()
+            }
+            &WindowEvent::MouseWheel { delta, .. } => {
+                let (unit, delta) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (MouseWheelUnit::Line, egui::vec2(x, y)),
+                    MouseScrollDelta::PixelDelta(delta) => (
+                        MouseWheelUnit::Point,
+                        egui::vec2(delta.x as f32, delta.y as f32) / pixels_per_point,
+                    ),
+                };
+                self.raw_input.events.push(Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers,
+                });
+                self.wants_pointer()
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                if let Some(key) = egui_key(&event.logical_key) {
+                    self.raw_input.events.push(Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed,
+                        repeat: event.repeat,
+                        modifiers,
+                    });
+                }
+                if let Some(text) = event.text.as_ref().filter(|_| pressed) {
+                    let is_printable = text.chars().all(|c| !c.is_control());
+                    if is_printable && !modifiers.ctrl && !modifiers.command {
+                        self.raw_input.events.push(Event::Text(text.to_string()));
+                    }
+                }
+                pressed && self.context.wants_keyboard_input()
+            }
+            WindowEvent::Ime(winit::event::Ime::Commit(text)) => {
+                self.raw_input.events.push(Event::Text(text.clone()));
+                self.context.wants_keyboard_input()
+            }
+            WindowEvent::ModifiersChanged(state) => {
+                let state = state.state();
+                self.modifiers = egui::Modifiers {
+                    alt: state.alt_key(),
+                    ctrl: state.control_key(),
+                    shift: state.shift_key(),
+                    mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+                    command: match cfg!(target_os = "macos") {
+                        true => state.super_key(),
+                        false => state.control_key(),
+                    },
+                };
+                false
+            }
+            &WindowEvent::Focused(focused) => {
+                self.raw_input.focused = focused;
+                self.raw_input.events.push(Event::WindowFocused(focused));
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn wants_pointer(&self) -> bool {
+        self.context.wants_pointer_input() || self.context.is_pointer_over_area()
+    }
+
+    /// Runs [`Renderable::ui`] and draws the result over `view`, which is expected to hold
+    /// the frame's shapes already.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw<A: Renderable>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        command_encoder: &mut CommandEncoder,
+        view: &TextureView,
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+        application: &mut A,
+    ) {
+        let pixels_per_point = scale_factor as f32;
+        let mut raw_input = take(&mut self.raw_input);
+        // the focus lasts beyond this frame's events
+        self.raw_input.focused = raw_input.focused;
+        raw_input.modifiers = self.modifiers;
+        raw_input.time = Some(self.start.elapsed().as_secs_f64());
+        raw_input.max_texture_side = Some(device.limits().max_texture_dimension_2d as usize);
+        raw_input.screen_rect = Some(Rect::from_min_size(
+            Pos2::ZERO,
+            egui::vec2(size.width as f32, size.height as f32) / pixels_per_point,
+        ));
+        raw_input
+            .viewports
+            .entry(raw_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(pixels_per_point);
+
+        let output = self
+            .context
+            .run(raw_input, |context| application.ui(context));
+        let paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: output.pixels_per_point,
+        };
+
+        for (id, image_delta) in &output.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        // without paint callbacks, there are no extra command buffers to submit
+        self.renderer.update_buffers(
+            device,
+            queue,
+            command_encoder,
+            &paint_jobs,
+            &screen_descriptor,
+        );
+        {
+            let render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("egui render pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &paint_jobs,
+                &screen_descriptor,
+            );
+        }
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn pointer_button(button: MouseButton) -> Option<PointerButton> {
+    Some(match button {
+        MouseButton::Left => PointerButton::Primary,
+        MouseButton::Right => PointerButton::Secondary,
+        MouseButton::Middle => PointerButton::Middle,
+        MouseButton::Back => PointerButton::Extra1,
+        MouseButton::Forward => PointerButton::Extra2,
+        MouseButton::Other(_) => return None,
+    })
+}
+
+fn egui_key(key: &Key) -> Option<EguiKey> {
+    match key {
+        Key::Named(NamedKey::Space) => Some(EguiKey::Space),
+        Key::Named(named) => named.to_text().and_then(EguiKey::from_name).or_else(|| {
+            // the names of the keys egui knows match winit's
+            EguiKey::from_name(&format!("{named:?}"))
+        }),
+        Key::Character(text) => EguiKey::from_name(text),
+        _ => None,
+    }
+}
@@ -1,92 +1,98 @@
 #![allow(dead_code)]
 
-use crate::color::Color;
-use crate::vectors::Vector2;
+use crate::error::{GpuError, GpuErrorKind};
 use bytemuck::{cast_slice, NoUninit};
-use rand::rngs::SmallRng;
-use rand::Rng;
 use wgpu::{
-    BindGroupLayout, BlendState, Buffer, BufferDescriptor, BufferUsages, ColorTargetState,
-    ColorWrites, Device, FragmentState, FrontFace, MultisampleState, PipelineCompilationOptions,
-    PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
-    RenderPipeline, RenderPipelineDescriptor, ShaderModule, TextureFormat, VertexBufferLayout,
-    VertexState, VertexStepMode,
+    BindGroupLayout, BlendState, Buffer, BufferUsages, ColorTargetState, ColorWrites, Device,
+    ErrorFilter, Extent3d, FragmentState, FrontFace, MultisampleState, PipelineCache,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView, VertexState,
 };
+use winit::dpi::PhysicalSize;
 
-pub trait RandExt {
-    fn f32(&mut self) -> f32;
-    fn f32_centered(&mut self) -> f32;
-    fn u8(&mut self) -> u8;
-    fn vec2_centered(&mut self) -> Vector2;
-    fn color_srgb(&mut self) -> Color;
-}
-
-impl RandExt for SmallRng {
-    fn f32(&mut self) -> f32 {
-        self.random::<f32>()
-    }
-
-    fn f32_centered(&mut self) -> f32 {
-        self.random::<f32>() * 2.0 - 1.0
-    }
-
-    fn u8(&mut self) -> u8 {
-        self.random::<u8>()
-    }
-
-    fn vec2_centered(&mut self) -> Vector2 {
-        Vector2::new(self.f32_centered(), self.f32_centered())
-    }
-
-    fn color_srgb(&mut self) -> Color {
-        Color::srgb(self.u8(), self.u8(), self.u8())
-    }
+/// Enters a `tracing` span called `$name` until the end of the enclosing block, with the
+/// `profile` feature. Expands to nothing without it.
+macro_rules! profile_span {
+    ($name:literal) => {
+        #[cfg(feature = "profile")]
+        let _span = tracing::info_span!($name).entered();
+    };
 }
+pub(crate) use profile_span;
 
 pub fn cast_thing<T: NoUninit>(thing: &T) -> &[u8] {
     use std::slice;
     cast_slice(slice::from_ref(thing))
 }
 
-pub fn create_empty_vertex_buffer(device: &Device) -> Buffer {
-    device.create_buffer(&BufferDescriptor {
-        label: None,
-        size: 0,
-        usage: BufferUsages::VERTEX,
-        mapped_at_creation: false,
-    })
+/// Runs `f` in validation and out-of-memory error scopes, adding whatever they catch to
+/// `errors` as errors of `frame` while doing `context`.
+pub fn catch_gpu_errors<T>(
+    device: &Device,
+    errors: &mut Vec<GpuError>,
+    frame: u64,
+    context: impl Fn() -> String,
+    f: impl FnOnce() -> T,
+) -> T {
+    device.push_error_scope(ErrorFilter::OutOfMemory);
+    device.push_error_scope(ErrorFilter::Validation);
+    let value = f();
+    for scope in [device.pop_error_scope(), device.pop_error_scope()] {
+        if let Some(error) = pollster::block_on(scope) {
+            errors.push(GpuError {
+                frame,
+                context: context(),
+                kind: GpuErrorKind::Wgpu(error),
+            });
+        }
+    }
+    value
 }
 
 pub fn create_pipeline_layout(
     device: &Device,
+    label: &str,
     bind_group_layouts: &[&BindGroupLayout],
 ) -> PipelineLayout {
     device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: None,
+        label: Some(label),
         bind_group_layouts,
         push_constant_ranges: &[],
     })
 }
 
+/// Runs `f`, returning the first validation error it caused instead of passing it to the
+/// uncaptured error handler, which panics by default.
+pub fn catch_validation_error<T>(device: &Device, f: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(ErrorFilter::Validation);
+    let value = f();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_no_vertex_render_pipeline(
     device: &Device,
+    label: &str,
     shader: &ShaderModule,
     pipeline_layout: &PipelineLayout,
     texture_format: TextureFormat,
     topology: PrimitiveTopology,
+    blend: BlendState,
+    sample_count: u32,
+    cache: Option<&PipelineCache>,
 ) -> RenderPipeline {
     device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: None,
+        label: Some(label),
         layout: Some(pipeline_layout),
         vertex: VertexState {
             module: shader,
             entry_point: "vs_main",
             compilation_options: PipelineCompilationOptions::default(),
-            buffers: &[VertexBufferLayout {
-                array_stride: 0,
-                step_mode: VertexStepMode::Vertex,
-                attributes: &[],
-            }],
+            buffers: &[],
         },
         fragment: Some(FragmentState {
             module: shader,
@@ -94,7 +100,7 @@ pub fn create_no_vertex_render_pipeline(
             compilation_options: PipelineCompilationOptions::default(),
             targets: &[Some(ColorTargetState {
                 format: texture_format,
-                blend: Some(BlendState::REPLACE),
+                blend: Some(blend),
                 write_mask: ColorWrites::ALL,
             })],
         }),
@@ -108,8 +114,50 @@ pub fn create_no_vertex_render_pipeline(
             conservative: false,
         },
         depth_stencil: None,
-        multisample: MultisampleState::default(),
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
         multiview: None,
-        cache: None,
+        cache,
+    })
+}
+
+/// Creates the multisampled color target the stages render into before resolving to the
+/// surface, or `None` when multisampling is off.
+pub fn create_msaa_view(
+    device: &Device,
+    texture_format: TextureFormat,
+    size: PhysicalSize<u32>,
+    sample_count: u32,
+) -> Option<TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("msaa color target"),
+        size: Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: TextureDimension::D2,
+        format: texture_format,
+        usage: TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&Default::default()))
+}
+
+/// Creates a buffer holding indirect draw arguments that shaders may also write to.
+pub fn create_indirect_buffer(device: &Device, contents: &[u8]) -> Buffer {
+    use wgpu::util::{BufferInitDescriptor, DeviceExt};
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("indirect draw args"),
+        contents,
+        usage: BufferUsages::INDIRECT | BufferUsages::STORAGE | BufferUsages::COPY_DST,
     })
 }
@@ -4,6 +4,7 @@ use bytemuck::{Pod, Zeroable};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default, Zeroable, Pod, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
@@ -32,6 +33,251 @@ impl Vector2 {
     pub fn dot(self, rhs: Self) -> f32 {
         self.x * rhs.x + self.y * rhs.y
     }
+
+    /// The smaller of each component, e.g. the lower corner of a bounding box.
+    ///
+    /// ```
+    /// # use wgpu_rendering::Vector2;
+    /// let a = Vector2::new(1.0, 4.0);
+    /// let b = Vector2::new(3.0, 2.0);
+    /// assert_eq!(a.min(b), Vector2::new(1.0, 2.0));
+    /// assert_eq!(a.max(b), Vector2::new(3.0, 4.0));
+    /// ```
+    #[inline]
+    pub fn min(self, other: Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    /// The larger of each component, see [`Self::min`].
+    #[inline]
+    pub fn max(self, other: Self) -> Self {
+        Self::new(self.x.max(other.x), self.y.max(other.y))
+    }
+
+    /// Clamps each component between those of `min` and `max`. Panics if a component of
+    /// `min` is greater than that of `max`, like [`f32::clamp`].
+    ///
+    /// ```
+    /// # use wgpu_rendering::Vector2;
+    /// let clamped = Vector2::new(-5.0, 0.5).clamp(Vector2::ZERO, Vector2::same(1.0));
+    /// assert_eq!(clamped, Vector2::new(0.0, 0.5));
+    /// ```
+    #[inline]
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Self::new(self.x.clamp(min.x, max.x), self.y.clamp(min.y, max.y))
+    }
+
+    #[inline]
+    pub fn min_element(self) -> f32 {
+        self.x.min(self.y)
+    }
+
+    #[inline]
+    pub fn max_element(self) -> f32 {
+        self.x.max(self.y)
+    }
+
+    /// The unit vector at `angle` radians counterclockwise from [`Self::RIGHT`].
+    #[inline]
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    /// The vector scaled to length 1, or `None` if its length is zero, infinite or NaN.
+    #[inline]
+    pub fn normalized(self) -> Option<Self> {
+        let length = self.length();
+        (length.is_finite() && length > 0.0).then(|| self / length)
+    }
+
+    /// Like [`Self::normalized`], returning `default` where that returns `None`.
+    #[inline]
+    pub fn normalize_or(self, default: Self) -> Self {
+        self.normalized().unwrap_or(default)
+    }
+
+    /// The vector rotated a quarter turn counterclockwise.
+    #[inline]
+    pub fn perp(self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
+    /// The vector rotated `angle` radians counterclockwise.
+    #[inline]
+    pub fn rotated(self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// The point rotated `angle` radians counterclockwise around `pivot`.
+    #[inline]
+    pub fn rotated_around(self, pivot: Self, angle: f32) -> Self {
+        (self - pivot).rotated(angle) + pivot
+    }
+
+    /// The angle from [`Self::RIGHT`] in radians, in `-PI..=PI`. Zero for the zero vector.
+    #[inline]
+    pub fn angle(self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    /// The signed angle in radians, in `-PI..=PI`, that rotates `self` onto the direction
+    /// of `other`.
+    #[inline]
+    pub fn angle_to(self, other: Self) -> f32 {
+        let cross = self.x * other.y - self.y * other.x;
+        cross.atan2(self.dot(other))
+    }
+
+    /// Interpolates towards `other`, without clamping `t`.
+    #[inline]
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+
+    /// The component of the vector along `other`. NaN if `other` is zero.
+    #[inline]
+    pub fn project_onto(self, other: Self) -> Self {
+        other * (self.dot(other) / other.length_squared())
+    }
+
+    /// The vector mirrored by the surface with unit length `normal`, as for a bounce.
+    #[inline]
+    pub fn reflect(self, normal: Self) -> Self {
+        self - normal * (2.0 * self.dot(normal))
+    }
+}
+
+/// A double precision [`Vector2`], for world positions too large or too finely spaced for
+/// `f32`. See [`Line64`](crate::Line64).
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DVector2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl DVector2 {
+    pub const ZERO: Self = Self::new(0., 0.);
+
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    /// `self - origin` in double precision, then rounded to `f32`, so nearby points stay
+    /// apart however far they are from zero.
+    #[inline]
+    pub fn relative_to(self, origin: Self) -> Vector2 {
+        (self - origin).as_vector2()
+    }
+
+    /// Rounds both components to `f32`.
+    #[inline]
+    pub fn as_vector2(self) -> Vector2 {
+        Vector2::new(self.x as f32, self.y as f32)
+    }
+}
+
+impl From<Vector2> for DVector2 {
+    fn from(value: Vector2) -> Self {
+        Self::new(value.x as f64, value.y as f64)
+    }
+}
+
+impl Add for DVector2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for DVector2 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+/// An integer cell coordinate, see [`GridMapper`](crate::GridMapper).
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Zeroable, Pod, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IVec2 {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl IVec2 {
+    pub const ZERO: Self = Self::new(0, 0);
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Rounds both components down, unlike `as`, which rounds towards zero.
+    pub fn from_floor(value: Vector2) -> Self {
+        let value = value.floor();
+        Self::new(value.x as i32, value.y as i32)
+    }
+}
+
+impl From<IVec2> for Vector2 {
+    fn from(value: IVec2) -> Self {
+        Self::new(value.x as f32, value.y as f32)
+    }
+}
+
+impl From<(i32, i32)> for IVec2 {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl Add for IVec2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for IVec2 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Mul<i32> for IVec2 {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Neg for IVec2 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+impl AddAssign for IVec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for IVec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
 }
 
 impl From<(f32, f32)> for Vector2 {
@@ -47,6 +293,26 @@ impl From<[f32; 2]> for Vector2 {
 }
 
 macro_rules! vec2_op_impl {
+    (componentwise $($func_name: ident)*) => {
+        $(
+        #[doc = concat!("[`f32::", stringify!($func_name), "`] of each component.")]
+        #[inline]
+        pub fn $func_name(self) -> Self {
+            Self::new(self.x.$func_name(), self.y.$func_name())
+        }
+        )*
+    };
+    (self_unary $($trait_name: ident $func_name: ident $op: tt)*) => {
+        $(
+        impl $trait_name for Vector2 {
+            type Output = Self;
+
+            fn $func_name(self) -> Self::Output {
+                Self::new($op self.x, $op self.y)
+            }
+        }
+        )*
+    };
     (self_normal $($trait_name: ident $func_name: ident $op: tt)*) => {
         $(
         impl $trait_name for Vector2 {
@@ -98,12 +364,16 @@ impl From<Vec2> for Vector2 {
     }
 }
 
-#[cfg(feature = "glam")]
+/// Converts vectors of the math crates enabled by the `glam` and `mint` features. With
+/// `mint`, this covers every type convertible to `mint::Vector2<f32>`, which with both
+/// features includes glam's.
+#[cfg(any(feature = "glam", feature = "mint"))]
 pub trait AsVector2 {
+    #[allow(clippy::wrong_self_convention)]
     fn as_render_vec(self) -> Vector2;
 }
 
-#[cfg(feature = "glam")]
+#[cfg(all(feature = "glam", not(feature = "mint")))]
 impl<T> AsVector2 for T
 where
     Vec2: From<T>,
@@ -120,6 +390,50 @@ impl From<Vector2> for Vec2 {
     }
 }
 
+#[cfg(feature = "mint")]
+impl<T> AsVector2 for T
+where
+    mint::Vector2<f32>: From<T>,
+{
+    fn as_render_vec(self) -> Vector2 {
+        mint::Vector2::from(self).into()
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<mint::Vector2<f32>> for Vector2 {
+    fn from(value: mint::Vector2<f32>) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<mint::Point2<f32>> for Vector2 {
+    fn from(value: mint::Point2<f32>) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Vector2> for mint::Vector2<f32> {
+    fn from(value: Vector2) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Vector2> for mint::Point2<f32> {
+    fn from(value: Vector2) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
 use std::ops::{Add, Div, Mul, Sub};
 vec2_op_impl! {
     self_normal
@@ -137,6 +451,20 @@ vec2_op_impl! {
     Sub sub -
 }
 
+impl Vector2 {
+    vec2_op_impl! {
+        componentwise
+        abs floor ceil round
+    }
+}
+
+use std::ops::Neg;
+
+vec2_op_impl! {
+    self_unary
+    Neg neg -
+}
+
 use std::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
 
 vec2_op_impl! {
@@ -146,3 +474,141 @@ vec2_op_impl! {
     MulAssign mul_assign *=
     DivAssign div_assign /=
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Vector2;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn assert_close(a: Vector2, b: Vector2) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn double_precision_offsets() {
+        use super::DVector2;
+
+        let origin = DVector2::new(1e7, -1e7);
+        let a = DVector2::new(1e7 + 0.001, -1e7);
+        assert_close(a.relative_to(origin), Vector2::new(0.001, 0.0));
+        // in single precision the offset is lost entirely
+        assert_eq!(a.as_vector2(), origin.as_vector2());
+        assert_eq!(
+            DVector2::from(Vector2::new(0.5, 2.0)) + origin - origin,
+            DVector2::new(0.5, 2.0)
+        );
+    }
+
+    #[test]
+    fn componentwise() {
+        let v = Vector2::new(-1.5, 2.5);
+        assert_eq!(-v, Vector2::new(1.5, -2.5));
+        assert_eq!(v.abs(), Vector2::new(1.5, 2.5));
+        assert_eq!(v.floor(), Vector2::new(-2.0, 2.0));
+        assert_eq!(v.ceil(), Vector2::new(-1.0, 3.0));
+        assert_eq!(v.round(), Vector2::new(-2.0, 3.0));
+        assert_eq!(v.min_element(), -1.5);
+        assert_eq!(v.max_element(), 2.5);
+
+        let points = [Vector2::new(1.0, -3.0), v, Vector2::new(0.0, 4.0)];
+        let min = points.into_iter().reduce(Vector2::min).unwrap();
+        let max = points.into_iter().reduce(Vector2::max).unwrap();
+        assert_eq!(
+            (min, max),
+            (Vector2::new(-1.5, -3.0), Vector2::new(1.0, 4.0))
+        );
+        assert_eq!(
+            v.clamp(Vector2::same(-1.0), Vector2::same(1.0)),
+            Vector2::new(-1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn normalizing() {
+        assert_close(
+            Vector2::new(3.0, 4.0).normalized().unwrap(),
+            Vector2::new(0.6, 0.8),
+        );
+        assert_eq!(Vector2::ZERO.normalized(), None);
+        assert_eq!(Vector2::new(f32::NAN, 1.0).normalized(), None);
+        assert_eq!(Vector2::new(f32::INFINITY, 1.0).normalized(), None);
+        assert_eq!(Vector2::ZERO.normalize_or(Vector2::UP), Vector2::UP);
+        assert_eq!(
+            Vector2::new(0.0, -2.0).normalize_or(Vector2::UP),
+            Vector2::DOWN
+        );
+    }
+
+    #[test]
+    fn rotating() {
+        assert_eq!(Vector2::RIGHT.perp(), Vector2::UP);
+        assert_close(Vector2::RIGHT.rotated(FRAC_PI_2), Vector2::UP);
+        assert_close(Vector2::new(1.0, 2.0).rotated(PI), Vector2::new(-1.0, -2.0));
+        assert_close(
+            Vector2::new(2.0, 1.0).rotated_around(Vector2::new(1.0, 1.0), FRAC_PI_2),
+            Vector2::new(1.0, 2.0),
+        );
+        assert_close(Vector2::from_angle(-FRAC_PI_2), Vector2::DOWN);
+        assert_eq!(Vector2::ZERO.rotated(1.0), Vector2::ZERO);
+    }
+
+    #[test]
+    fn angles() {
+        assert_eq!(Vector2::ZERO.angle(), 0.0);
+        assert!((Vector2::LEFT.angle() - PI).abs() < 1e-6);
+        assert!((Vector2::from_angle(1.25).angle() - 1.25).abs() < 1e-6);
+        assert!((Vector2::RIGHT.angle_to(Vector2::UP) - FRAC_PI_2).abs() < 1e-6);
+        assert!((Vector2::UP.angle_to(Vector2::new(5.0, 0.0)) + FRAC_PI_2).abs() < 1e-6);
+        assert!(Vector2::new(f32::NAN, 0.0).angle().is_nan());
+    }
+
+    #[test]
+    fn lerp_project_reflect() {
+        let a = Vector2::new(1.0, 2.0);
+        let b = Vector2::new(3.0, -2.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vector2::new(2.0, 0.0));
+        assert_eq!(a.lerp(b, 2.0), Vector2::new(5.0, -6.0));
+
+        assert_close(
+            a.project_onto(Vector2::new(4.0, 0.0)),
+            Vector2::new(1.0, 0.0),
+        );
+        assert!(a.project_onto(Vector2::ZERO).x.is_nan());
+
+        assert_close(
+            Vector2::new(1.0, -1.0).reflect(Vector2::UP),
+            Vector2::new(1.0, 1.0),
+        );
+    }
+
+    #[cfg(feature = "mint")]
+    #[test]
+    fn mint_conversions() {
+        use crate::AsVector2;
+
+        let v = Vector2::new(1.0, -2.0);
+        let point = mint::Point2::from(v);
+        assert_eq!(Vector2::from(point), v);
+        assert_eq!(mint::Vector2::from(v).as_render_vec(), v);
+        assert_eq!([1.0f32, -2.0].as_render_vec(), v);
+        #[cfg(feature = "glam")]
+        assert_eq!(glam::Vec2::new(1.0, -2.0).as_render_vec(), v);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn matches_glam() {
+        use glam::Vec2;
+
+        let a = Vector2::new(1.5, -0.5);
+        let b = Vector2::new(-2.0, 3.0);
+        let (ga, gb) = (Vec2::from(a), Vec2::from(b));
+        assert_close(a.perp(), ga.perp().into());
+        assert_close(a.rotated(0.7), Vec2::from_angle(0.7).rotate(ga).into());
+        assert!((a.angle_to(b) - ga.angle_to(gb)).abs() < 1e-5);
+        assert_close(a.project_onto(b), ga.project_onto(gb).into());
+        assert_close(a.normalized().unwrap(), ga.normalize().into());
+    }
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use wgpu_rendering::{
+    render_to_image, Camera, Color, Line, LineBlendMode, RectOrCircle, RenderController,
+    RenderStage, Renderable, Vector2,
+};
+
+const SIZE: u32 = 16;
+/// The world y of the center of pixel row 8, with the default camera on a square image.
+const ROW_8_Y: f32 = -1.0 / SIZE as f32;
+const BACKGROUND: Color = Color::BLUE;
+const OVERLAY: Color = Color::RED.with_alpha(0.5);
+
+/// An opaque background with a translucent overlay across row 8, drawn as a line or a rect.
+struct Overlay {
+    as_line: bool,
+}
+
+impl Renderable for Overlay {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::RectsAndCircles);
+        render_controller.add_rect_or_circle(RectOrCircle::rectangle(
+            Vector2::ZERO,
+            Vector2::same(2.0),
+            BACKGROUND,
+        ));
+        if self.as_line {
+            render_controller.add_stage(RenderStage::Line);
+            render_controller.add_line(Line::new(
+                Vector2::new(-2.0, ROW_8_Y),
+                Vector2::new(2.0, ROW_8_Y),
+                OVERLAY,
+            ));
+        } else {
+            render_controller.add_rect_or_circle(RectOrCircle::rectangle(
+                Vector2::new(0.0, ROW_8_Y),
+                // exactly row 8, so smoothing its edges covers no more or less of it
+                Vector2::new(2.0, 1.0 / SIZE as f32),
+                OVERLAY,
+            ));
+        }
+    }
+}
+
+fn pixel(image: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let start = ((y * SIZE + x) * 4) as usize;
+    image[start..start + 4].try_into().unwrap()
+}
+
+#[test]
+fn translucent_lines_and_rects_blend_alike() {
+    let Some(line_image) = common::or_skip(render_to_image(
+        &mut Overlay { as_line: true },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )) else {
+        return;
+    };
+    let rect_image = render_to_image(
+        &mut Overlay { as_line: false },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )
+    .unwrap();
+
+    let line_pixel = pixel(&line_image, 8, 8);
+    let rect_pixel = pixel(&rect_image, 8, 8);
+    for (line, rect) in line_pixel.into_iter().zip(rect_pixel) {
+        assert!(line.abs_diff(rect) <= 1, "{line_pixel:?} != {rect_pixel:?}");
+    }
+
+    // the overlay mixes with the background rather than replacing it
+    let background = pixel(&rect_image, 8, 2);
+    assert_ne!(rect_pixel, background);
+    assert!(rect_pixel[0] > 0 && rect_pixel[2] > 0, "{rect_pixel:?}");
+}
+
+/// Lines across row 8 over the black clear color, with the line stage configured by `MODE`
+/// and `ACCUMULATE`.
+struct Glow<const MODE: u8, const ACCUMULATE: bool> {
+    lines: &'static [Color],
+}
+
+impl<const MODE: u8, const ACCUMULATE: bool> Renderable for Glow<MODE, ACCUMULATE> {
+    const USE_LINE_ALPHA: bool = ACCUMULATE;
+    const LINE_BLEND_MODE: LineBlendMode = match MODE {
+        0 => LineBlendMode::Alpha,
+        _ => LineBlendMode::Additive,
+    };
+
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::Line);
+        for &color in self.lines {
+            render_controller.add_line(Line::new(
+                Vector2::new(-2.0, ROW_8_Y),
+                Vector2::new(2.0, ROW_8_Y),
+                color,
+            ));
+        }
+    }
+}
+
+#[test]
+fn additive_lines_add_up() {
+    const HALF_RED: &[Color] = &[Color::RED.with_alpha(0.5), Color::RED.with_alpha(0.5)];
+    let Some(additive) = common::or_skip(render_to_image(
+        &mut Glow::<1, false> { lines: HALF_RED },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )) else {
+        return;
+    };
+    let alpha = render_to_image(
+        &mut Glow::<0, false> { lines: HALF_RED },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )
+    .unwrap();
+
+    // two halves add up to full red, while blending over leaves a quarter of the black
+    assert!(
+        pixel(&additive, 8, 8)[0] >= 254,
+        "{:?}",
+        pixel(&additive, 8, 8)
+    );
+    assert!(pixel(&alpha, 8, 8)[0] < 250, "{:?}", pixel(&alpha, 8, 8));
+    assert_eq!(pixel(&additive, 8, 2), pixel(&alpha, 8, 2));
+}
+
+#[test]
+fn additive_lines_match_accumulation_over_black() {
+    const DIM: &[Color] = &[Color::RED.with_alpha(0.2)];
+    let Some(additive) = common::or_skip(render_to_image(
+        &mut Glow::<1, false> { lines: DIM },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )) else {
+        return;
+    };
+    let accumulated = render_to_image(
+        &mut Glow::<0, true> { lines: DIM },
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )
+    .unwrap();
+
+    let (additive, accumulated) = (pixel(&additive, 8, 8), pixel(&accumulated, 8, 8));
+    assert!(additive[0] > 0 && accumulated[0] > 0);
+    assert!(
+        additive[0].abs_diff(accumulated[0]) <= 2,
+        "{additive:?} vs {accumulated:?}"
+    );
+}
+
+/// A glowing line across row 8, over the black clear color.
+struct Neon<const ACCUMULATE: bool>;
+
+impl<const ACCUMULATE: bool> Renderable for Neon<ACCUMULATE> {
+    const USE_LINE_ALPHA: bool = ACCUMULATE;
+
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::Line);
+        render_controller.add_line(
+            Line::new(
+                Vector2::new(-2.0, ROW_8_Y),
+                Vector2::new(2.0, ROW_8_Y),
+                Color::GREEN,
+            )
+            .with_glow(0.5),
+        );
+    }
+}
+
+#[test]
+fn glow_fades_from_the_centerline() {
+    let Some(plain) = common::or_skip(render_to_image(
+        &mut Neon::<false>,
+        SIZE,
+        SIZE,
+        Camera::default(),
+    )) else {
+        return;
+    };
+    let accumulated = render_to_image(&mut Neon::<true>, SIZE, SIZE, Camera::default()).unwrap();
+
+    for image in [plain, accumulated] {
+        // pixels are an eighth of a world unit, so the glow reaches 4 pixels either side
+        let green = |y| pixel(&image, 8, y)[1];
+        assert!(green(8) > 200, "{:?}", pixel(&image, 8, 8));
+        assert!(green(8) > green(6) && green(6) > green(5) && green(5) > 0);
+        assert_eq!(green(1), 0);
+    }
+}
+
+/// A screen line across pixel row 8 and a world line through the middle of the world, under a
+/// camera that puts the world line out of view.
+struct Ruler;
+
+impl Renderable for Ruler {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::Line);
+        render_controller.add_line(Line::screen(
+            Vector2::new(0.0, 8.5),
+            Vector2::new(SIZE as f32, 8.5),
+            Color::WHITE,
+        ));
+        render_controller.add_line(Line::new(
+            Vector2::new(-2.0, 0.0),
+            Vector2::new(2.0, 0.0),
+            Color::WHITE,
+        ));
+    }
+}
+
+#[test]
+fn screen_lines_ignore_the_camera() {
+    let camera = Camera::new(Vector2::new(5.0, 5.0), 3.0);
+    let Some(image) = common::or_skip(render_to_image(&mut Ruler, SIZE, SIZE, camera)) else {
+        return;
+    };
+
+    for x in 0..SIZE {
+        assert_eq!(pixel(&image, x, 8), [255; 4], "column {x}");
+    }
+    for y in (0..SIZE).filter(|&y| y != 8) {
+        assert_eq!(pixel(&image, 4, y), [0, 0, 0, 255], "row {y}");
+    }
+}
@@ -0,0 +1,15 @@
+//! Shared by the tests that render offscreen.
+
+use wgpu_rendering::RenderError;
+
+/// The value of `result`, or `None` to skip the test when there's no adapter to render with.
+/// Any other error fails the test.
+pub fn or_skip<T>(result: Result<T, RenderError>) -> Option<T> {
+    match result {
+        Err(RenderError::NoAdapter) => {
+            eprintln!("no adapter available, skipping");
+            None
+        }
+        result => Some(result.unwrap()),
+    }
+}
@@ -0,0 +1,390 @@
+//! Renders small scenes offscreen and compares them with reference images in
+//! `tests/goldens`. Run with `UPDATE_GOLDENS=1` to write the references anew after an
+//! intended change, and look at them before committing.
+
+mod common;
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use wgpu_rendering::{
+    render_to_image, Camera, Color, GridStyle, HeadlessTarget, Line, PostEffect, RectOrCircle,
+    RenderController, RenderStage, Renderable, Vector2, WindowConfig,
+};
+
+const SIZE: u32 = 256;
+/// How far apart a channel may be before the pixel counts as different.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Pixels allowed to differ anyway, for edges that drivers rasterize differently.
+const MAX_DIFFERENT_PIXELS: f32 = 0.005;
+
+/// Draws whatever its closure submits, accumulating line alpha if `LINE_ALPHA` is set.
+struct Shapes<F, const LINE_ALPHA: bool>(F);
+
+impl<F: FnMut(&mut RenderController), const LINE_ALPHA: bool> Renderable for Shapes<F, LINE_ALPHA> {
+    const USE_LINE_ALPHA: bool = LINE_ALPHA;
+
+    fn render(&mut self, render_controller: &mut RenderController) {
+        (self.0)(render_controller)
+    }
+}
+
+fn shapes<F: FnMut(&mut RenderController)>(draw: F) -> Shapes<F, false> {
+    Shapes(draw)
+}
+
+fn accumulating<F: FnMut(&mut RenderController)>(draw: F) -> Shapes<F, true> {
+    Shapes(draw)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/goldens")
+        .join(format!("{name}.png"))
+}
+
+fn write_png(path: &Path, (width, height): (u32, u32), pixels: &[u8]) {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path).unwrap()), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(pixels)
+        .unwrap();
+}
+
+fn read_png(path: &Path) -> Option<((u32, u32), Vec<u8>)> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    Some(((info.width, info.height), pixels))
+}
+
+/// Renders `application` and checks it against the golden image `name`. Returns quietly
+/// without an adapter.
+fn check<A: Renderable>(name: &str, mut application: A, size: (u32, u32), camera: Camera) {
+    let Some(pixels) = common::or_skip(render_to_image(&mut application, size.0, size.1, camera))
+    else {
+        return;
+    };
+    compare(name, size, &pixels);
+}
+
+/// Checks `pixels` against the golden image `name`.
+fn compare(name: &str, size: (u32, u32), pixels: &[u8]) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        write_png(&path, size, pixels);
+        return;
+    }
+    let Some((golden_size, golden)) = read_png(&path) else {
+        panic!(
+            "{} is missing, run with UPDATE_GOLDENS=1 to create it",
+            path.display()
+        );
+    };
+    assert_eq!(golden_size, size, "{name} changed size");
+
+    let different = pixels
+        .chunks_exact(4)
+        .zip(golden.chunks_exact(4))
+        .filter(|(pixel, golden)| {
+            (pixel.iter().zip(*golden)).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let allowed = (size.0 * size.1) as f32 * MAX_DIFFERENT_PIXELS;
+    if different as f32 > allowed {
+        let actual = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.actual.png"));
+        fs::create_dir_all(actual.parent().unwrap()).unwrap();
+        write_png(&actual, size, pixels);
+        panic!(
+            "{different} pixels of {name} differ from the golden, more than {allowed}; \
+            the rendered image is at {}",
+            actual.display()
+        );
+    }
+}
+
+#[test]
+fn centered_circle() {
+    let shapes = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::circle(Vector2::ZERO, 0.5, Color::WHITE));
+    });
+    check("centered_circle", shapes, (SIZE, SIZE), Camera::default());
+}
+
+#[test]
+fn axis_aligned_rect() {
+    let shapes = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::rectangle(
+            Vector2::new(0.25, -0.125),
+            Vector2::new(1.0, 0.5),
+            Color::ORANGE,
+        ));
+    });
+    check("axis_aligned_rect", shapes, (SIZE, SIZE), Camera::default());
+}
+
+#[test]
+fn diagonal_line() {
+    let shapes = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::Line);
+        render.add_line(Line::new(
+            Vector2::same(-0.8),
+            Vector2::same(0.8),
+            Color::GREEN,
+        ));
+    });
+    check("diagonal_line", shapes, (SIZE, SIZE), Camera::default());
+}
+
+#[test]
+fn overlapping_lines_accumulate_alpha() {
+    let shapes = accumulating(|render: &mut RenderController| {
+        render.add_stage(RenderStage::Line);
+        for y in [-0.5, 0.0, 0.5] {
+            let from = Vector2::new(-0.9, y);
+            render.add_line(Line::new(from, -from, Color::CYAN.with_alpha(0.4)));
+        }
+        render.add_line(Line::new(
+            Vector2::new(0.0, -0.9),
+            Vector2::new(0.0, 0.9),
+            Color::RED.with_alpha(0.6),
+        ));
+    });
+    check("overlapping_lines", shapes, (SIZE, SIZE), Camera::default());
+}
+
+#[test]
+fn wide_targets_keep_circles_round() {
+    let shapes = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::rectangle(
+            Vector2::ZERO,
+            Vector2::new(3.0, 1.5),
+            Color::DARK_GRAY,
+        ));
+        for x in [-1.0, 0.0, 1.0] {
+            render.add_rect_or_circle(RectOrCircle::circle(Vector2::new(x, 0.0), 0.4, Color::BLUE));
+        }
+    });
+    let camera = Camera::new(Vector2::new(0.0, 0.0), 0.9);
+    check("aspect_2_to_1", shapes, (SIZE, SIZE / 2), camera);
+}
+
+#[test]
+fn bordered_shapes() {
+    let shapes = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::rectangle_with_border(
+            Vector2::new(0.0, -0.5),
+            Vector2::new(0.8, 0.3),
+            Color::DARK_GRAY,
+            Color::YELLOW,
+            0.1,
+        ));
+        for x in [-0.5, 0.5] {
+            render.add_rect_or_circle(RectOrCircle::circle_with_border(
+                Vector2::new(x, 0.4),
+                0.4,
+                Color::WHITE,
+                Color::BLUE,
+                0.05,
+            ));
+        }
+    });
+    check("bordered_shapes", shapes, (SIZE, SIZE), Camera::default());
+}
+
+/// Renders a single shape, or nothing without an adapter.
+fn render_shape(shape: RectOrCircle) -> Option<Vec<u8>> {
+    let mut shapes = shapes(move |render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(shape);
+    });
+    common::or_skip(render_to_image(&mut shapes, SIZE, SIZE, Camera::default()))
+}
+
+#[test]
+fn border_widths_at_the_extremes() {
+    let (center, radius) = (Vector2::new(0.1, -0.2), 0.6);
+    let Some(plain) = render_shape(RectOrCircle::circle(center, radius, Color::RED)) else {
+        return;
+    };
+    let no_border = RectOrCircle::circle_with_border(center, radius, Color::RED, Color::GREEN, 0.0);
+    assert!(render_shape(no_border).unwrap() == plain);
+
+    let all_border =
+        RectOrCircle::circle_with_border(center, radius, Color::GREEN, Color::RED, radius);
+    assert!(render_shape(all_border).unwrap() == plain);
+
+    let size = Vector2::new(0.5, 0.3);
+    let plain = render_shape(RectOrCircle::rectangle(center, size, Color::RED)).unwrap();
+    let all_border =
+        RectOrCircle::rectangle_with_border(center, size, Color::GREEN, Color::RED, 0.3);
+    assert!(render_shape(all_border).unwrap() == plain);
+}
+
+/// A white circle in the middle, with its edges smoothed over `FEATHER` pixels.
+struct FeatheredCircle<const FEATHER: u8>;
+
+impl<const FEATHER: u8> Renderable for FeatheredCircle<FEATHER> {
+    const SHAPE_FEATHER: f32 = FEATHER as f32;
+
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::RectsAndCircles);
+        render_controller.add_rect_or_circle(RectOrCircle::circle(
+            Vector2::ZERO,
+            // an edge between pixel centers, so it has something to smooth
+            0.45,
+            Color::WHITE,
+        ));
+    }
+}
+
+/// The red channel across the middle row.
+fn middle_row<A: Renderable>(mut application: A) -> Option<Vec<u8>> {
+    let pixels = common::or_skip(render_to_image(
+        &mut application,
+        SIZE,
+        SIZE,
+        Camera::default(),
+    ))?;
+    let row = (SIZE / 2 * SIZE * 4) as usize;
+    Some(
+        pixels[row..row + SIZE as usize * 4]
+            .iter()
+            .step_by(4)
+            .copied()
+            .collect(),
+    )
+}
+
+#[test]
+fn circle_edges_are_smoothed_unless_feather_is_zero() {
+    let Some(smooth) = middle_row(FeatheredCircle::<1>) else {
+        return;
+    };
+    let partial = |row: &[u8]| row.iter().filter(|&&red| red > 0 && red < 255).count();
+    // one pixel of partial coverage on either side
+    assert!((2..=4).contains(&partial(&smooth)), "{smooth:?}");
+
+    let hard = middle_row(FeatheredCircle::<0>).unwrap();
+    assert_eq!(partial(&hard), 0, "{hard:?}");
+}
+
+#[test]
+fn deep_zoom_keeps_edges_in_place() {
+    // zoomed in on the right edge of a circle, so it runs down the middle of the image
+    let camera = Camera::new(Vector2::new(1.0, 0.0), 1000.0);
+    let circle = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::circle(Vector2::ZERO, 1.0, Color::WHITE));
+    });
+    let Some(pixels) = common::or_skip(render_to_image(&mut { circle }, SIZE, SIZE, camera)) else {
+        return;
+    };
+    let row = &pixels[(SIZE / 2 * SIZE * 4) as usize..][..SIZE as usize * 4];
+    let red = |x: u32| row[x as usize * 4];
+    assert_eq!(red(SIZE / 2 - 2), 255);
+    assert_eq!(red(SIZE / 2 + 1), 0);
+}
+
+#[test]
+fn reference_grid() {
+    let grid = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::Grid);
+        render.add_stage(RenderStage::RectsAndCircles);
+        render.add_rect_or_circle(RectOrCircle::circle(
+            Vector2::new(0.5, 0.5),
+            0.2,
+            Color::ORANGE,
+        ));
+    });
+    // off center, so the axes cross away from the middle
+    check(
+        "reference_grid",
+        grid,
+        (SIZE, SIZE),
+        Camera::new(Vector2::new(0.3, 0.2), 1.5),
+    );
+}
+
+#[test]
+fn far_away_grids_stay_on_world_multiples() {
+    let render_at = |x: f32| {
+        let mut grid = shapes(|render: &mut RenderController| {
+            render.add_stage(RenderStage::Grid);
+            render.set_grid_style(GridStyle {
+                y_axis_color: None,
+                ..GridStyle::default()
+            });
+        });
+        common::or_skip(render_to_image(
+            &mut grid,
+            SIZE,
+            SIZE,
+            Camera::new(Vector2::new(x, 0.0), 3.0),
+        ))
+    };
+    let Some(near) = render_at(0.0) else {
+        return;
+    };
+    assert!(near.chunks_exact(4).any(|pixel| pixel[0] > 0));
+    assert!(near == render_at(1e6).unwrap());
+}
+
+#[test]
+fn bloom_and_vignette() {
+    let mut circles = shapes(|render: &mut RenderController| {
+        render.add_stage(RenderStage::RectsAndCircles);
+        // for the vignette to darken
+        render.add_rect_or_circle(RectOrCircle::rectangle(
+            Vector2::ZERO,
+            Vector2::same(4.0),
+            Color::DARK_GRAY,
+        ));
+        render.add_rect_or_circle(RectOrCircle::circle(
+            Vector2::new(-0.3, 0.0),
+            0.15,
+            Color::WHITE,
+        ));
+        // too dim to glow
+        render.add_rect_or_circle(RectOrCircle::circle(
+            Vector2::new(0.4, 0.0),
+            0.15,
+            Color::GRAY,
+        ));
+    });
+    let config = WindowConfig {
+        post_effects: vec![
+            PostEffect::Bloom {
+                threshold: 0.8,
+                intensity: 2.0,
+                radius: 16.0,
+            },
+            PostEffect::Vignette {
+                strength: 0.8,
+                radius: 0.3,
+            },
+        ],
+        ..WindowConfig::default()
+    };
+    let Some(mut target) = common::or_skip(HeadlessTarget::with_config(
+        &mut circles,
+        SIZE,
+        SIZE,
+        &config,
+    )) else {
+        return;
+    };
+    target.set_camera(Camera::default());
+    let pixels = target.render(&mut circles).unwrap();
+    compare("bloom_and_vignette", (SIZE, SIZE), &pixels);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use wgpu_rendering::{render_to_image, Camera, GpuContext, RenderController, Renderable};
+
+/// Creates a buffer of its own in `init`, on the device it's rendered with.
+#[derive(Default)]
+struct OwnBuffer {
+    buffer: Option<wgpu::Buffer>,
+}
+
+impl Renderable for OwnBuffer {
+    fn init(&mut self, gpu: &GpuContext) {
+        self.buffer = Some(gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("application buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        gpu.queue()
+            .write_buffer(self.buffer.as_ref().unwrap(), 0, &[1; 16]);
+    }
+
+    fn render(&mut self, _render_controller: &mut RenderController) {
+        assert!(self.buffer.is_some(), "rendered before init");
+    }
+}
+
+#[test]
+fn init_runs_before_rendering() {
+    let mut app = OwnBuffer::default();
+    if common::or_skip(render_to_image(&mut app, 4, 4, Camera::default())).is_none() {
+        return;
+    }
+    assert_eq!(app.buffer.unwrap().size(), 16);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use wgpu_rendering::{
+    render_to_image_at, Camera, Color, DVector2, RectOrCircle64, RenderController, RenderStage,
+    Renderable, Vector2,
+};
+
+const SIZE: u32 = 16;
+const OFFSET: f64 = 10_000_000.0;
+const SPACING: f64 = 0.001;
+
+/// Two small dots `SPACING` apart, far enough from zero that `f32` can't tell them apart.
+struct FarDots;
+
+impl Renderable for FarDots {
+    fn render(&mut self, render_controller: &mut RenderController) {
+        render_controller.add_stage(RenderStage::RectsAndCircles);
+        for x in [OFFSET, OFFSET + SPACING] {
+            render_controller.add_rect_or_circle_64(RectOrCircle64::circle(
+                DVector2::new(x, 0.0),
+                0.0002,
+                Color::WHITE,
+            ));
+        }
+    }
+}
+
+#[test]
+fn distant_points_land_on_distinct_pixels() {
+    // the view spans 0.002 world units, putting the dots a quarter of the way in from
+    // either side
+    let camera = Camera::new(Vector2::ZERO, 1000.0);
+    let origin = DVector2::new(OFFSET + SPACING / 2.0, 0.0);
+    let Some(image) = common::or_skip(render_to_image_at(&mut FarDots, SIZE, SIZE, camera, origin))
+    else {
+        return;
+    };
+
+    let row = 8;
+    let lit: Vec<bool> = (0..SIZE)
+        .map(|x| {
+            let start = ((row * SIZE + x) * 4) as usize;
+            image[start] > 127
+        })
+        .collect();
+    let runs = lit.windows(2).filter(|pair| !pair[0] && pair[1]).count();
+    assert_eq!(runs, 2, "{lit:?}");
+    assert!(lit[4] && !lit[8] && lit[12], "{lit:?}");
+}
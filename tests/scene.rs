@@ -0,0 +1,130 @@
+use wgpu_rendering::{
+    Camera, Color, Line, RectOrCircle, RenderController, Scene, SceneError, Vector2,
+};
+
+fn scene() -> Scene {
+    Scene {
+        camera: Camera::new(Vector2::new(1.5, -2.0), 3.0),
+        lines: vec![
+            Line::new(Vector2::ZERO, Vector2::RIGHT, Color::RED.with_alpha(0.5)).with_glow(0.25),
+            Line::screen(Vector2::same(0.5), Vector2::same(8.5), Color::WHITE),
+        ],
+        rects: vec![
+            RectOrCircle::circle(Vector2::UP, 0.5, Color::BLUE),
+            RectOrCircle::rectangle(Vector2::DOWN, Vector2::new(2.0, 1.0), Color::GREEN),
+            RectOrCircle::rectangle_with_border(
+                Vector2::LEFT,
+                Vector2::same(0.5),
+                Color::ORANGE,
+                Color::BLACK.with_alpha(0.5),
+                0.1,
+            ),
+        ],
+    }
+}
+
+fn assert_same(a: &Scene, b: &Scene) {
+    assert_eq!(bytemuck::bytes_of(&a.camera), bytemuck::bytes_of(&b.camera));
+    assert_eq!(
+        bytemuck::cast_slice::<_, u8>(&a.lines),
+        bytemuck::cast_slice::<_, u8>(&b.lines)
+    );
+    assert_eq!(
+        bytemuck::cast_slice::<_, u8>(&a.rects),
+        bytemuck::cast_slice::<_, u8>(&b.rects)
+    );
+}
+
+#[test]
+fn round_trips_through_a_file() {
+    let path = std::env::temp_dir().join(format!("scene-{}.bin", std::process::id()));
+    scene().save(&path).unwrap();
+    let loaded = Scene::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_same(&loaded, &scene());
+    assert!(loaded.lines[1].is_screen_space());
+    assert_eq!(loaded.lines[0].glow(), 0.25);
+}
+
+#[test]
+fn skips_what_later_versions_add() {
+    let mut bytes = scene().to_bytes();
+    // a later minor version, with an extra section
+    bytes[10] = 3;
+    bytes.extend(b"NEWS");
+    bytes.extend(5u64.to_le_bytes());
+    bytes.extend([1, 2, 3, 4, 5]);
+
+    assert_same(&Scene::from_bytes(&bytes).unwrap(), &scene());
+}
+
+/// A scene holding just `rect`, written with records of `record_size` bytes taken from the
+/// start of its instance data, padded with `0xff`.
+fn scene_with_rect_records(rect: &RectOrCircle, record_size: usize) -> Vec<u8> {
+    let mut bytes = Scene::new().to_bytes();
+    // replace the empty rect section
+    let section_start = bytes.len() - (4 + 8 + 8);
+    bytes.truncate(section_start);
+    let mut records = 1u32.to_le_bytes().to_vec();
+    records.extend((record_size as u32).to_le_bytes());
+    let rect = bytemuck::bytes_of(rect);
+    records.extend(&rect[..record_size.min(52)]);
+    records.resize(8 + record_size, 0xff);
+    bytes.extend(b"RECT");
+    bytes.extend((records.len() as u64).to_le_bytes());
+    bytes.extend(records);
+    bytes
+}
+
+#[test]
+fn skips_longer_records() {
+    let rect = RectOrCircle::circle_with_border(Vector2::UP, 0.5, Color::BLUE, Color::RED, 0.1);
+    let loaded = Scene::from_bytes(&scene_with_rect_records(&rect, 56)).unwrap();
+    assert_eq!(
+        bytemuck::cast_slice::<_, u8>(&loaded.rects),
+        bytemuck::bytes_of(&rect)
+    );
+}
+
+#[test]
+fn reads_rects_without_borders_from_version_1_0() {
+    let rect = RectOrCircle::circle(Vector2::UP, 0.5, Color::BLUE);
+    let mut bytes = scene_with_rect_records(&rect, 32);
+    bytes[10] = 0;
+
+    let loaded = Scene::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        bytemuck::cast_slice::<_, u8>(&loaded.rects),
+        bytemuck::bytes_of(&rect)
+    );
+}
+
+#[test]
+fn rejects_other_major_versions_and_garbage() {
+    let mut bytes = scene().to_bytes();
+    bytes[8] = 2;
+    assert!(matches!(
+        Scene::from_bytes(&bytes),
+        Err(SceneError::UnsupportedVersion { major: 2, minor: 1 })
+    ));
+
+    assert!(matches!(
+        Scene::from_bytes(b"not a scene at all"),
+        Err(SceneError::NotAScene)
+    ));
+    let bytes = scene().to_bytes();
+    assert!(matches!(
+        Scene::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(SceneError::Truncated)
+    ));
+}
+
+#[test]
+fn submits_every_shape() {
+    let mut render = RenderController::new();
+    scene().submit(&mut render);
+    let bounds = render.bounding_box().unwrap();
+    assert_eq!(bounds.min, Vector2::new(-2.0, -2.0));
+    assert_eq!(bounds.max, Vector2::new(2.0, 1.5));
+}
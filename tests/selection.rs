@@ -0,0 +1,81 @@
+use wgpu_rendering::{
+    Color, DVector2, Drag, Line, Line64, Rect, RectOrCircle, RectOrCircle64, RenderController,
+    RenderStage, ShapeRef, Vector2,
+};
+
+fn line(index: usize) -> ShapeRef {
+    ShapeRef {
+        stage: RenderStage::Line,
+        index,
+    }
+}
+
+fn shape(index: usize) -> ShapeRef {
+    ShapeRef {
+        stage: RenderStage::RectsAndCircles,
+        index,
+    }
+}
+
+#[test]
+fn queries_every_kind_of_shape() {
+    let mut render = RenderController::new();
+    let at = |x, y| Vector2::new(x, y);
+    // crossing the query without an end inside, passing its corner, and one in pixels
+    render.add_line(Line::new(at(-3.0, 0.5), at(3.0, 0.5), Color::WHITE));
+    render.add_line(Line::new(at(0.5, 2.5), at(2.5, 0.5), Color::WHITE));
+    render.add_line(Line::screen(at(0.0, 0.0), at(1.0, 1.0), Color::WHITE));
+    render.add_line_64(Line64::new(
+        DVector2::new(0.5, -1.0),
+        DVector2::new(0.5, 0.5),
+        Color::WHITE,
+    ));
+    // a circle reaching in, one off the corner, and a rectangle overlapping one edge
+    render.add_rect_or_circle(RectOrCircle::circle(at(1.5, 0.5), 0.6, Color::WHITE));
+    render.add_rect_or_circle(RectOrCircle::circle(at(1.6, 1.6), 0.8, Color::WHITE));
+    render.add_rect_or_circle(RectOrCircle::rectangle(
+        at(-1.0, 0.5),
+        at(1.1, 0.1),
+        Color::WHITE,
+    ));
+    render.add_rect_or_circle_64(RectOrCircle64::circle(
+        DVector2::new(5.0, 5.0),
+        1.0,
+        Color::WHITE,
+    ));
+
+    let query = Rect::from_corners(Vector2::ZERO, Vector2::same(1.0));
+    assert_eq!(
+        render.query_rect(query),
+        [line(0), line(3), shape(0), shape(2)]
+    );
+    let empty = Rect::from_center_size(Vector2::new(-2.0, 2.0), Vector2::same(1.0));
+    assert_eq!(render.query_rect(empty), []);
+    assert_eq!(
+        render.query_rect(Rect::from_center_size(
+            Vector2::same(5.0),
+            Vector2::same(0.5)
+        )),
+        [shape(3)]
+    );
+}
+
+#[test]
+fn drags_span_rectangles() {
+    let drag = Drag {
+        start_screen: Vector2::new(40.0, 10.0),
+        start_world: Vector2::new(1.0, 2.0),
+        current_screen: Vector2::new(20.0, 30.0),
+        current_world: Vector2::new(-1.0, 0.0),
+    };
+    assert_eq!(drag.screen_rect().min, Vector2::new(20.0, 10.0));
+    assert_eq!(drag.screen_rect().max, Vector2::new(40.0, 30.0));
+    assert_eq!(drag.world_rect().min, Vector2::new(-1.0, 0.0));
+    assert_eq!(drag.world_rect().max, Vector2::new(1.0, 2.0));
+
+    let mut render = RenderController::new();
+    render.add_marquee(drag.screen_rect(), Color::WHITE);
+    // the marquee is drawn in pixels, so selecting never picks it up
+    assert_eq!(render.query_rect(drag.screen_rect()), []);
+    assert_eq!(render.bounding_box(), None);
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use wgpu_rendering::{
+    Binding, Camera, CameraBookmarks, Color, InputMap, KeyCode, Line, MouseButton, RectOrCircle,
+    Vector2,
+};
+
+/// Serializes `value` as JSON and bincode, checks both deserialize to something that
+/// serializes the same, and returns the JSON.
+fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> serde_json::Value {
+    let json = serde_json::to_value(value).unwrap();
+    let from_json: T = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&from_json).unwrap(), json);
+
+    let bytes = bincode::serialize(value).unwrap();
+    let from_bytes: T = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(bincode::serialize(&from_bytes).unwrap(), bytes);
+
+    json
+}
+
+#[test]
+fn camera_skips_padding() {
+    let camera = Camera::new(Vector2::new(1.5, -2.0), 3.0);
+    let json = round_trip(&camera);
+    assert_eq!(
+        json,
+        serde_json::json!({ "target": { "x": 1.5, "y": -2.0 }, "zoom": 3.0 })
+    );
+    // two f32s of target and one of zoom, without the 4 padding bytes
+    assert_eq!(bincode::serialize(&camera).unwrap().len(), 12);
+}
+
+#[test]
+fn shapes_serialize_semantic_fields() {
+    let color = Color::new(0.5, 0.25, 1.0, 0.5);
+    let line = Line::new(Vector2::ZERO, Vector2::new(1.0, 2.0), color);
+    let json = round_trip(&line);
+    // the color comes back straight, not premultiplied
+    assert_eq!(json["color"], serde_json::to_value(color).unwrap());
+
+    let circle = RectOrCircle::circle(Vector2::new(1.0, 1.0), 0.5, color);
+    let json = round_trip(&circle);
+    assert_eq!(json["Circle"]["radius"], 0.5);
+    assert_eq!(json["Circle"]["border"], serde_json::Value::Null);
+
+    let rect = RectOrCircle::rectangle(Vector2::ZERO, Vector2::new(2.0, 3.0), Color::RED);
+    let json = round_trip(&rect);
+    assert_eq!(
+        json["Rectangle"]["size"],
+        serde_json::json!({ "x": 2.0, "y": 3.0 })
+    );
+
+    let bordered =
+        RectOrCircle::circle_with_border(Vector2::ZERO, 1.0, Color::WHITE, Color::RED, 0.25);
+    let json = round_trip(&bordered);
+    assert_eq!(json["Circle"]["border"]["width"], 0.25);
+}
+
+#[test]
+fn colors_as_hex_strings() {
+    #[derive(Serialize, Deserialize)]
+    struct Theme {
+        #[serde(with = "wgpu_rendering::color_hex")]
+        accent: Color,
+    }
+
+    let theme = Theme {
+        accent: Color::ORANGE,
+    };
+    let json = round_trip(&theme);
+    assert_eq!(json, serde_json::json!({ "accent": "#ff8000ff" }));
+
+    let short: Theme = serde_json::from_str(r##"{ "accent": "#f80" }"##).unwrap();
+    assert_eq!(short.accent, Color::srgb(0xFF, 0x88, 0x00));
+    assert!(serde_json::from_str::<Theme>(r#"{ "accent": "orange" }"#).is_err());
+}
+
+#[test]
+fn input_maps_serialize_as_actions() {
+    let map = InputMap::new()
+        .with_binding("save", Binding::key(KeyCode::KeyS).with_ctrl())
+        .with_binding("select", MouseButton::Left);
+    let json = round_trip(&map);
+    assert_eq!(
+        json["save"][0]["input"],
+        serde_json::json!({ "Key": "KeyS" })
+    );
+    assert_eq!(json["save"][0]["ctrl"], true);
+
+    // unrequired modifiers may be left out
+    let select: InputMap =
+        serde_json::from_str(r#"{ "select": [{ "input": { "Button": "Left" } }] }"#).unwrap();
+    assert_eq!(select.bindings("select"), map.bindings("select"));
+}
+
+#[test]
+fn bookmarks_persist_to_files() {
+    let mut bookmarks = CameraBookmarks::new();
+    bookmarks.save(1, Camera::new(Vector2::new(3.0, 4.0), 0.5));
+    bookmarks.save("overview", Camera::default());
+
+    let path = std::env::temp_dir().join(format!("bookmarks-{}.json", std::process::id()));
+    bookmarks.save_file(&path).unwrap();
+    let loaded = CameraBookmarks::load_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(round_trip(&loaded), round_trip(&bookmarks));
+    assert_eq!(loaded.restore(1).unwrap().zoom, 0.5);
+}